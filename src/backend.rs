@@ -0,0 +1,199 @@
+use std::ops::Range;
+
+use crate::{CancelHandle, CpuSearcher, DeviceInfo, Eta, GpuStats, PrimeError, PrimeSearcher, Progression, Result, SearchReport, Target, partition_chunks};
+
+/// Search operations shared by every way of running a search, so callers such as the CLI can
+/// pick the OpenCL devices or the CPU at runtime and tests can substitute their own.
+///
+/// Each search covers the `range` it is given rather than the one the searcher was created
+/// with. Other setup, like a [`PrimeSearcher`]'s checkpoint, only applies to searches over its
+/// own range.
+pub trait Backend: Send + Sync {
+    /// The smallest prime in `range`.
+    fn find_first(&self, range: Range<u64>) -> Result<Option<u64>>;
+
+    /// The smallest twin primes `(p, p + 2)` with both in `range`.
+    fn find_twin(&self, range: Range<u64>) -> Result<Option<(u64, u64)>>;
+
+    /// Every prime in `range`, in ascending order.
+    fn find_all(&self, range: Range<u64>) -> Result<Vec<u64>>;
+
+    /// The number of primes in `range`.
+    fn count(&self, range: Range<u64>) -> Result<u64>;
+
+    /// The smallest prime in `range` among the terms of `progression`.
+    ///
+    /// Lists the primes window by window with [`find_all`](Self::find_all) and keeps the first
+    /// term among them; the built-in backends override it to test only the terms.
+    fn find_in_progression(&self, range: Range<u64>, progression: Progression) -> Result<Option<u64>> {
+        for window in partition_chunks(range, 1 << 20) {
+            let found = self.find_all(window)?;
+            if let Some(prime) = found.into_iter().find(|&prime| progression.contains(prime)) {
+                return Ok(Some(prime));
+            }
+            if self.cancel_handle().is_cancelled() {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Up to `count` primes after `after`, in ascending order, stopping short at `end`.
+    ///
+    /// Searches one window after another with [`find_all`](Self::find_all), the first sized to
+    /// hold about `count` primes and each one after twice as long. A cancelled window's primes
+    /// may have gaps, so they are dropped.
+    fn find_next(&self, after: u64, count: usize, end: u64) -> Result<Vec<u64>> {
+        let mut primes = vec![];
+        let mut start = after.saturating_add(1);
+        // Near `after`, primes are about ln(after) apart
+        let mut window = ((count as f64 * (after.max(3) as f64).ln()) as u64).max(64);
+        while primes.len() < count && start < end {
+            let stop = start.saturating_add(window).min(end);
+            let found = self.find_all(start..stop)?;
+            if self.cancel_handle().is_cancelled() {
+                break;
+            }
+            primes.extend(found);
+            start = stop;
+            window = window.saturating_mul(2);
+        }
+        primes.truncate(count);
+        Ok(primes)
+    }
+
+    /// The devices searches are split between.
+    fn devices(&self) -> Vec<DeviceInfo>;
+
+    /// Handle that stops searches from another thread.
+    fn cancel_handle(&self) -> CancelHandle;
+
+    /// What each device did during the last search, in the order of [`devices`](Self::devices).
+    fn report(&self) -> SearchReport;
+
+    /// Highest candidate each device had tested when the last search stopped.
+    fn progress(&self) -> Vec<u64>;
+
+    /// Latest monitoring reading for each device; none unless the backend monitors GPUs.
+    fn gpu_stats(&self) -> Vec<Option<GpuStats>> {
+        vec![None; self.devices().len()]
+    }
+
+    /// The last candidate each thread of each device had tested at the latest poll; none unless
+    /// the backend polls its devices' threads.
+    fn thread_statuses(&self) -> Vec<Vec<u64>> {
+        vec![vec![]; self.devices().len()]
+    }
+
+    /// Estimated time left for each device and the whole search; still estimating unless the
+    /// backend polls its devices' progress.
+    fn eta(&self) -> Eta {
+        Eta::estimating(self.devices().len())
+    }
+}
+
+impl Backend for PrimeSearcher {
+    fn find_first(&self, range: Range<u64>) -> Result<Option<u64>> {
+        self.check_backend_range(&range)?;
+        Ok(self.search_first(&range, Target::Prime)?.map(|prime| prime as u64))
+    }
+
+    fn find_twin(&self, range: Range<u64>) -> Result<Option<(u64, u64)>> {
+        self.check_backend_range(&range)?;
+        Ok(self.search_first(&range, Target::TwinPrime)?.map(|p| (p as u64, p as u64 + 2)))
+    }
+
+    fn find_all(&self, range: Range<u64>) -> Result<Vec<u64>> {
+        self.check_backend_range(&range)?;
+        self.find_all_in(&range)
+    }
+
+    fn count(&self, range: Range<u64>) -> Result<u64> {
+        self.check_backend_range(&range)?;
+        self.count_in(&range)
+    }
+
+    fn find_in_progression(&self, range: Range<u64>, progression: Progression) -> Result<Option<u64>> {
+        self.check_backend_range(&range)?;
+        Ok(self.search_first(&range, Target::Progression(progression))?.map(|prime| prime as u64))
+    }
+
+    fn devices(&self) -> Vec<DeviceInfo> {
+        PrimeSearcher::devices(self).to_vec()
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        PrimeSearcher::cancel_handle(self)
+    }
+
+    fn report(&self) -> SearchReport {
+        self.report.report()
+    }
+
+    fn progress(&self) -> Vec<u64> {
+        PrimeSearcher::progress(self)
+    }
+
+    fn gpu_stats(&self) -> Vec<Option<GpuStats>> {
+        PrimeSearcher::gpu_stats(self)
+    }
+
+    fn thread_statuses(&self) -> Vec<Vec<u64>> {
+        PrimeSearcher::thread_statuses(self)
+    }
+
+    fn eta(&self) -> Eta {
+        PrimeSearcher::eta(self)
+    }
+}
+
+impl PrimeSearcher {
+    // Ranges given through Backend are plain numbers, which new_u128 searchers can't take
+    fn check_backend_range(&self, range: &Range<u64>) -> Result<()> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("searchers created with new_u128 can't be used as a Backend".into()));
+        }
+        if range.start > range.end {
+            return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+        }
+        Ok(())
+    }
+}
+
+impl Backend for CpuSearcher {
+    fn find_first(&self, range: Range<u64>) -> Result<Option<u64>> {
+        self.find_first_in(range)
+    }
+
+    fn find_twin(&self, range: Range<u64>) -> Result<Option<(u64, u64)>> {
+        self.find_twin_in(range)
+    }
+
+    fn find_all(&self, range: Range<u64>) -> Result<Vec<u64>> {
+        self.find_all_in(range)
+    }
+
+    fn count(&self, range: Range<u64>) -> Result<u64> {
+        self.count_in(range)
+    }
+
+    fn find_in_progression(&self, range: Range<u64>, progression: Progression) -> Result<Option<u64>> {
+        self.find_in_progression_in(range, progression)
+    }
+
+    fn devices(&self) -> Vec<DeviceInfo> {
+        vec![self.device()]
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        CpuSearcher::cancel_handle(self)
+    }
+
+    fn report(&self) -> SearchReport {
+        CpuSearcher::report(self)
+    }
+
+    fn progress(&self) -> Vec<u64> {
+        vec![CpuSearcher::progress(self)]
+    }
+}
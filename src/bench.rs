@@ -0,0 +1,251 @@
+use ocl::{Buffer, MemFlags};
+use std::{thread, time::{Duration, Instant}};
+
+use crate::{PrimeSearcher, Result, order, status_work_size, tested_count, work_group};
+
+// Every batch re-tests the same candidates so results stay comparable between runs
+const BENCH_START: u64 = 10_000_000_000_000;
+// Candidates each kernel thread tests per batch
+const BENCH_ITERATIONS: u64 = 64;
+// Algorithm id flag that skips the kernels' small-prime precheck, matching the kernel source
+const NO_PRECHECK: u32 = 0x100;
+// The fewest threads a tune sweep tries
+const TUNE_MIN_THREADS: usize = 256;
+
+/// Throughput of one device over a [`PrimeSearcher::bench`] run.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    /// Candidates tested, counted from the status buffers
+    pub candidates: u64,
+    /// Bytes of status buffer read back to the host
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// Batches run in `elapsed`
+    pub batches: u64,
+    /// Host time spent reading the status buffers back after each batch's kernel finished,
+    /// by copying them or with [`SearcherConfig::mapped_status`](crate::SearcherConfig::mapped_status)
+    /// by mapping them
+    pub read_time: Duration,
+    /// Candidates tested over the second half of the run, with the small-prime precheck off
+    pub baseline_candidates: u64,
+    pub baseline_elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn candidates_per_sec(&self) -> f64 {
+        self.candidates as f64 / secs(self.elapsed)
+    }
+
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1e6 / secs(self.elapsed)
+    }
+
+    /// The average time one batch's status read took.
+    pub fn read_time_per_batch(&self) -> Duration {
+        self.read_time / self.batches.max(1) as u32
+    }
+
+    /// How many times faster the precheck makes the device.
+    pub fn precheck_speedup(&self) -> f64 {
+        let baseline = self.baseline_candidates as f64 / secs(self.baseline_elapsed);
+        self.candidates_per_sec() / baseline.max(f64::MIN_POSITIVE)
+    }
+}
+
+/// One device's throughput at each thread count a [`PrimeSearcher::tune`] sweep tried, from
+/// the fewest threads to the most.
+#[derive(Debug, Clone, Default)]
+pub struct TuneResult {
+    pub runs: Vec<(usize, BenchResult)>,
+}
+
+impl TuneResult {
+    /// The thread count that tested the most candidates per second.
+    pub fn best(&self) -> Option<usize> {
+        self.runs.iter()
+            .max_by(|(_, a), (_, b)| a.candidates_per_sec().total_cmp(&b.candidates_per_sec()))
+            .map(|&(threads, _)| threads)
+    }
+
+    // The rate of the run a request for `threads` would have used: the most threads tried that
+    // aren't more, as the device clamps and rounds the count down
+    fn candidates_per_sec_at(&self, threads: usize) -> f64 {
+        self.runs.iter().rev()
+            .find(|&&(tried, _)| tried <= threads)
+            .or(self.runs.first())
+            .map_or(0.0, |(_, result)| result.candidates_per_sec())
+    }
+
+    /// The one thread count, out of those tried on any device, that tests the most candidates
+    /// per second across all of them when every device is given it, with that total.
+    pub fn best_shared(results: &[TuneResult]) -> Option<(usize, f64)> {
+        let mut counts: Vec<usize> = results.iter().flat_map(|result| result.runs.iter().map(|&(threads, _)| threads)).collect();
+        counts.sort_unstable();
+        counts.dedup();
+        counts.into_iter()
+            .map(|threads| (threads, results.iter().map(|result| result.candidates_per_sec_at(threads)).sum::<f64>()))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+// The thread counts a sweep tries on a device: doubling from TUNE_MIN_THREADS while below its
+// limit, then the limit, each rounded down to whole work groups
+fn tune_sizes(limit: usize, local_size: Option<usize>) -> Vec<usize> {
+    let mut sizes: Vec<usize> = std::iter::successors(Some(TUNE_MIN_THREADS), |&size| size.checked_mul(2))
+        .take_while(|&size| size < limit)
+        .chain([limit])
+        .map(|size| local_size.map_or(size, |local| (size / local).max(1) * local))
+        .collect();
+    sizes.dedup();
+    sizes
+}
+
+// A run cancelled before its first batch has nothing to divide by
+fn secs(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+impl PrimeSearcher {
+    /// Measures every device's throughput for about `duration`, in the order of
+    /// [`devices`](Self::devices).
+    ///
+    /// Devices repeatedly run the counting kernel over a fixed batch of candidates, which
+    /// never stops on a prime. One untimed batch warms each device up first. The first half
+    /// of `duration` measures the kernels as searches run them, the second half without the
+    /// small-prime precheck, for comparison.
+    pub fn bench(&self, duration: Duration) -> Result<Vec<BenchResult>> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.pro_ques.len())
+                .map(|i| scope.spawn(move || self.bench_device(i, duration / 2, duration / 2)))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+
+    /// Benchmarks each device at a geometric sweep of thread counts, 256, 512, 1024 and so on
+    /// up to the most it runs at once, for about `duration` each, then leaves every device at
+    /// the count where it was fastest. Results are in the order of [`devices`](Self::devices).
+    ///
+    /// Devices are swept side by side, one step at a time, so each is timed at its own limit
+    /// while the others may still be climbing to theirs. The counts are measured with the
+    /// small-prime precheck on, as searches run. Cancelling ends the sweep at the current step.
+    pub fn tune(&mut self, duration: Duration) -> Result<Vec<TuneResult>> {
+        let sizes: Vec<Vec<usize>> = self.thread_limits.iter().map(|&limit| tune_sizes(limit, self.local_size)).collect();
+        let mut results = vec![TuneResult::default(); sizes.len()];
+        let steps = sizes.iter().map(Vec::len).max().unwrap_or(0);
+        for step in 0..steps {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            // Devices that have run out of sizes sit the step out
+            let stepping: Vec<usize> = (0..sizes.len()).filter(|&i| step < sizes[i].len()).collect();
+            for &i in &stepping {
+                self.set_thread_count(i, sizes[i][step])?;
+            }
+            let this = &*self;
+            let runs: Vec<BenchResult> = thread::scope(|scope| {
+                let handles: Vec<_> = stepping.iter()
+                    .map(|&i| scope.spawn(move || this.bench_device(i, duration, Duration::ZERO)))
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Result<_>>()
+            })?;
+            for (i, run) in stepping.into_iter().zip(runs) {
+                results[i].runs.push((self.thread_counts[i], run));
+            }
+        }
+        for (i, result) in results.iter().enumerate() {
+            if let Some(best) = result.best() {
+                self.set_thread_count(i, best)?;
+            }
+        }
+        Ok(results)
+    }
+
+    // Times the kernels as searches run them for `timed`, then without the precheck for
+    // `baseline`
+    fn bench_device(&self, i: usize, timed: Duration, baseline: Duration) -> Result<BenchResult> {
+        let pq = &self.pro_ques[i];
+        let sb = &self.status_buffers[i];
+        let halt = self.cancel.halt_kernels();
+        let threads = status_work_size(i, sb, self.thread_counts[i])? as u64;
+        let end = BENCH_START + threads * BENCH_ITERATIONS;
+
+        // A zero capacity keeps the kernel from storing the primes it counts
+        let primes = Buffer::<u64>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().write_only())
+            .len(1)
+            .build()?;
+        let count = Buffer::<u64>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(0u64)
+            .build()?;
+        halt.flag(i).cmd().fill(0, None).enq()?;
+        halt.pause_flag(i).cmd().fill(0, None).enq()?;
+
+        let algorithm = self.algorithm.kernel_id()?;
+        let kernel = pq.kernel_builder("search_all_primes")
+            .global_work_size(threads as usize)
+            .local_work_size(work_group(self.local_size))
+            .arg(BENCH_START)
+            .arg(end)
+            .arg(algorithm)
+            .arg(&primes)
+            .arg(&count)
+            .arg(0u32)
+            .arg(&**sb) // Dereference Arc
+            .arg(halt.flag(i))
+            .arg(halt.pause_flag(i))
+            .build()?;
+
+        let mut status = vec![0u64; threads as usize];
+        // The candidates a batch tested and how long reading them back took, timed from the
+        // end of its kernel
+        let mut run_batch = || -> Result<(u64, Duration)> {
+            sb.cmd().fill(0u64, None).enq()?;
+            order::enqueue_kernel(&kernel)?.wait_for()?;
+            let reading = Instant::now();
+            if self.mapped_status {
+                // SAFETY: the kernel has finished and this is the buffer's only mapping
+                let map = unsafe { sb.map().read().enq()? };
+                status.copy_from_slice(&map);
+                // The next batch's fill mustn't overtake the unmap on an out-of-order queue
+                drop(map);
+                pq.queue().finish()?;
+            } else {
+                sb.read(&mut status).enq()?;
+            }
+            Ok((tested_count(&status, BENCH_START, end - BENCH_START), reading.elapsed()))
+        };
+
+        run_batch()?;
+        let mut result = BenchResult {
+            candidates: 0,
+            bytes: 0,
+            elapsed: Duration::ZERO,
+            batches: 0,
+            read_time: Duration::ZERO,
+            baseline_candidates: 0,
+            baseline_elapsed: Duration::ZERO,
+        };
+        let started = Instant::now();
+        while result.elapsed < timed && !self.cancel.is_cancelled() {
+            let (candidates, read_time) = run_batch()?;
+            result.candidates += candidates;
+            result.bytes += threads * 8;
+            result.batches += 1;
+            result.read_time += read_time;
+            result.elapsed = started.elapsed();
+        }
+
+        kernel.set_arg(2, algorithm | NO_PRECHECK)?;
+        let started = Instant::now();
+        while result.baseline_elapsed < baseline && !self.cancel.is_cancelled() {
+            result.baseline_candidates += run_batch()?.0;
+            result.baseline_elapsed = started.elapsed();
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use crate::{Algorithm, DeviceFilter, Direction, KernelCache, PartitionStrategy, PrimeError, PrimeSearcher, Result, SearcherConfig, ThreadCount};
+
+/// Sets up a [`PrimeSearcher`] one option at a time, in the style of ocl's `ProQue::builder`.
+///
+/// Only the range is required; every other setter documents the default it replaces.
+#[derive(Debug, Clone)]
+pub struct PrimeSearcherBuilder {
+    range: Option<Range<u64>>,
+    algorithm: Algorithm,
+    threads: ThreadCount,
+    local_size: Option<usize>,
+    partition_strategy: PartitionStrategy,
+    direction: Direction,
+    verify: bool,
+    monitor: bool,
+    config: SearcherConfig,
+}
+
+impl Default for PrimeSearcherBuilder {
+    fn default() -> Self {
+        PrimeSearcherBuilder {
+            range: None,
+            algorithm: Algorithm::default(),
+            threads: ThreadCount::default(),
+            local_size: None,
+            partition_strategy: PartitionStrategy::default(),
+            direction: Direction::default(),
+            verify: true,
+            monitor: true,
+            config: SearcherConfig::default(),
+        }
+    }
+}
+
+impl PrimeSearcherBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The numbers to search. Required, and must hold at least one number.
+    pub fn range(mut self, range: Range<u64>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// The primality test the kernels run; trial division by default.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Kernel threads per device; 1024 by default.
+    pub fn threads(mut self, threads: ThreadCount) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Work-items per work group; `None`, the default, leaves it to the driver.
+    pub fn local_size(mut self, local_size: Option<usize>) -> Self {
+        self.local_size = local_size;
+        self
+    }
+
+    /// Which devices to search on; every device by default.
+    pub fn devices(mut self, devices: DeviceFilter) -> Self {
+        self.config.devices = devices;
+        self
+    }
+
+    /// Whether primes found are re-checked on the CPU; enabled by default.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// How the range is split between devices; even slices by default.
+    pub fn partition_strategy(mut self, strategy: PartitionStrategy) -> Self {
+        self.partition_strategy = strategy;
+        self
+    }
+
+    /// Which end of the range `find_first` searches from; up from the start by default.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Whether the devices are monitored while searching; enabled by default.
+    pub fn monitoring(mut self, monitor: bool) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Where compiled kernels are cached; none by default, so kernels are always built from source.
+    pub fn kernel_cache(mut self, cache: KernelCache) -> Self {
+        self.config.kernel_cache = Some(cache);
+        self
+    }
+
+    /// Checks that the options fit together, then sets up the devices.
+    pub fn build(self) -> Result<PrimeSearcher> {
+        let range = self.range.ok_or_else(|| PrimeError::InvalidRange("no range was given to the builder".into()))?;
+        if self.algorithm == Algorithm::LucasLehmer {
+            return Err(PrimeError::Unsupported("the Lucas-Lehmer test only checks Mersenne numbers, not a range; use test_mersenne".into()));
+        }
+        if range.start > range.end {
+            return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+        }
+        if range.is_empty() {
+            return Err(PrimeError::InvalidRange(format!("[{}, {}) is empty, so there is nothing to search", range.start, range.end)));
+        }
+        if self.local_size == Some(0) {
+            return Err(PrimeError::Unsupported("work groups need at least one work-item".into()));
+        }
+        if self.direction == Direction::Down && matches!(self.partition_strategy, PartitionStrategy::Dynamic { .. }) {
+            return Err(PrimeError::Unsupported("searching down needs static slices, not dynamic partitioning".into()));
+        }
+
+        let searcher = PrimeSearcher::new_with_config(range, &self.config)?
+            .with_algorithm(self.algorithm)
+            .with_partition_strategy(self.partition_strategy)
+            .with_direction(self.direction)
+            .with_verification(self.verify)
+            .with_monitoring(self.monitor);
+        // The devices are set up with the default thread count already
+        let searcher = if self.threads == ThreadCount::default() { searcher } else { searcher.with_thread_count(self.threads)? };
+        if self.local_size.is_none() {
+            return Ok(searcher);
+        }
+        searcher.with_local_size(self.local_size)
+    }
+}
@@ -0,0 +1,96 @@
+use std::{env, ffi::OsString, fs, io, path::{Path, PathBuf}};
+
+/// Directory of compiled kernel binaries, one per device, driver version and kernel source.
+#[derive(Debug, Clone)]
+pub struct KernelCache {
+    dir: PathBuf,
+}
+
+impl KernelCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        KernelCache { dir: dir.into() }
+    }
+
+    /// Like [`new`](Self::new), creating the directory if it's missing, so a directory that
+    /// can't be written fails here rather than once per device after each build.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let cache = Self::new(dir);
+        fs::create_dir_all(&cache.dir)?;
+        Ok(cache)
+    }
+
+    /// Where kernels are cached unless told otherwise, the first of:
+    ///
+    /// 1. `$OPENCL_PRIMES_CACHE_DIR`
+    /// 2. On Linux, `$XDG_CACHE_HOME/opencl-primes`
+    /// 3. The platform's cache directory: `~/Library/Caches/opencl-primes` on macOS,
+    ///    `%LOCALAPPDATA%\opencl-primes\cache` on Windows and `~/.cache/opencl-primes`
+    ///    elsewhere
+    ///
+    /// Empty variables count as unset, as does a relative `$XDG_CACHE_HOME`, which the XDG
+    /// base directory specification says to ignore. None when there is no home directory to
+    /// fall back on.
+    pub fn default_dir() -> Option<PathBuf> {
+        let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+        if let Some(dir) = var("OPENCL_PRIMES_CACHE_DIR") {
+            return Some(dir.into());
+        }
+        if cfg!(target_os = "linux") {
+            if let Some(dir) = var("XDG_CACHE_HOME").map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+                return Some(dir.join(APP_DIR));
+            }
+        }
+        platform_cache_dir(var)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Deletes every cached binary.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    pub(crate) fn load(&self, device: &str, driver: &str, src: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(device, driver, src)).ok()
+    }
+
+    // Written to a temporary file first so a concurrent run never loads half a binary
+    pub(crate) fn store(&self, device: &str, driver: &str, src: &str, binary: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(device, driver, src);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, binary)?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn path(&self, device: &str, driver: &str, src: &str) -> PathBuf {
+        let sanitize = |s: &str| -> String {
+            s.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+        };
+        self.dir.join(format!("{}-{}-{:016x}.bin", sanitize(device), sanitize(driver), fnv1a(src.as_bytes())))
+    }
+}
+
+// The directory under the platform's cache directory
+const APP_DIR: &str = "opencl-primes";
+
+fn platform_cache_dir(var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return var("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join(APP_DIR).join("cache"));
+    }
+    let home = PathBuf::from(var("HOME")?);
+    if cfg!(target_os = "macos") {
+        return Some(home.join("Library").join("Caches").join(APP_DIR));
+    }
+    Some(home.join(".cache").join(APP_DIR))
+}
+
+// Unlike std's hashers, FNV-1a gives the same key across Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
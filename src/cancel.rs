@@ -0,0 +1,120 @@
+use ocl::{Buffer, Queue};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use crate::Result;
+
+/// Stops a running search from another thread, e.g. a Ctrl-C handler.
+///
+/// Cancellation is sticky: once cancelled, later searches on the same searcher return
+/// without launching any kernels.
+#[derive(Clone)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+    halt: KernelHalt,
+}
+
+impl CancelHandle {
+    pub(crate) fn new(halt: KernelHalt) -> Self {
+        CancelHandle { cancelled: Arc::new(AtomicBool::new(false)), halt }
+    }
+
+    /// A handle for searches that run no kernels, such as a [`Backend`](crate::Backend) on the
+    /// host; cancelling only sets the flag the search polls.
+    pub fn without_kernels() -> Self {
+        Self::new(KernelHalt::new(vec![], vec![], vec![]))
+    }
+
+    /// Makes the monitor threads return and asks every running kernel to stop.
+    pub fn cancel(&self) -> Result<()> {
+        self.cancelled.store(true, Ordering::Release);
+        self.halt.halt()
+    }
+
+    /// Polled by every monitor and worker thread, so it's a single atomic load that never waits
+    /// on another thread.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn halt_kernels(&self) -> &KernelHalt {
+        &self.halt
+    }
+}
+
+// Cancel and pause flags, one of each per device, that the kernels poll between candidates.
+// A host write on the compute queue would wait for the running kernel, so the flags are set
+// through a separate control queue; whether a running kernel observes the write is up to the
+// driver, so this is best effort.
+//
+// Each flag is set by a non-blocking 4-byte write from a static, which needs no staging copy
+// and nothing kept alive until it runs, and the control queue is flushed straight after so the
+// write is submitted at once instead of whenever the driver next batches commands.
+#[derive(Clone)]
+pub(crate) struct KernelHalt {
+    control_queues: Vec<Queue>,
+    flags: Vec<Buffer<i32>>,
+    pause_flags: Vec<Buffer<i32>>,
+}
+
+impl KernelHalt {
+    pub(crate) fn new(control_queues: Vec<Queue>, flags: Vec<Buffer<i32>>, pause_flags: Vec<Buffer<i32>>) -> Self {
+        KernelHalt { control_queues, flags, pause_flags }
+    }
+
+    // Commands on it don't wait behind the device's running kernel
+    pub(crate) fn control_queue(&self, device: usize) -> &Queue {
+        &self.control_queues[device]
+    }
+
+    pub(crate) fn flag(&self, device: usize) -> &Buffer<i32> {
+        &self.flags[device]
+    }
+
+    pub(crate) fn pause_flag(&self, device: usize) -> &Buffer<i32> {
+        &self.pause_flags[device]
+    }
+
+    // Stops the kernels on every device after `device`
+    pub(crate) fn halt_above(&self, device: usize) -> Result<()> {
+        for (queue, flag) in self.control_queues.iter().zip(self.flags.iter()).skip(device + 1) {
+            set_flag(queue, flag, true)?;
+        }
+        Ok(())
+    }
+
+    // Stops the kernels on every device before `device`
+    pub(crate) fn halt_below(&self, device: usize) -> Result<()> {
+        for (queue, flag) in self.control_queues.iter().zip(self.flags.iter()).take(device) {
+            set_flag(queue, flag, true)?;
+        }
+        Ok(())
+    }
+
+    // Stops the kernels on `device` alone
+    pub(crate) fn halt_device(&self, device: usize) -> Result<()> {
+        set_flag(&self.control_queues[device], &self.flags[device], true)
+    }
+
+    // Makes the device's kernels spin in place until unpaused
+    pub(crate) fn set_paused(&self, device: usize, paused: bool) -> Result<()> {
+        set_flag(&self.control_queues[device], &self.pause_flags[device], paused)
+    }
+
+    pub(crate) fn halt(&self) -> Result<()> {
+        for (queue, flag) in self.control_queues.iter().zip(self.flags.iter()) {
+            set_flag(queue, flag, true)?;
+        }
+        Ok(())
+    }
+}
+
+// What a flag is set to, cleared or raised
+static FLAG_VALUES: [i32; 2] = [0, 1];
+
+fn set_flag(queue: &Queue, flag: &Buffer<i32>, raised: bool) -> Result<()> {
+    let value = &FLAG_VALUES[raised as usize..][..1];
+    // The source is static, so it outlives the write however long the driver takes
+    unsafe { flag.write(value).queue(queue).block(false).enq()? };
+    queue.flush()?;
+    Ok(())
+}
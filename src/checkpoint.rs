@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, io, ops::Range, path::{Path, PathBuf}, sync::Mutex, time::{Duration, Instant}};
+
+use crate::{DeviceInfo, PrimeError, Result, partition_pieces};
+
+/// Saved position of an interrupted search.
+///
+/// `start` and `end` are the original range; every number in `start..next` has been tested.
+/// Searches that split the range between devices also record each device's own position in
+/// `devices`, so resuming skips what the faster devices tested past `next`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub start: u64,
+    pub end: u64,
+    pub next: u64,
+    /// One entry per device in slice order; empty for checkpoints without them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceProgress>,
+}
+
+/// How far one device got through its slice of a checkpointed search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceProgress {
+    /// The device as [`DeviceInfo`] displays it
+    pub device: String,
+    /// Everything in the device's slice below `next` has been tested
+    pub next: u64,
+    /// End of the device's slice
+    pub end: u64,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let checkpoint: Checkpoint = toml::from_str(&text)
+            .map_err(|e| PrimeError::Checkpoint(format!("{}: {}", path.display(), e)))?;
+        if !(checkpoint.start <= checkpoint.next && checkpoint.next <= checkpoint.end) {
+            return Err(PrimeError::Checkpoint(format!("{}: position {} is outside [{}, {})", path.display(), checkpoint.next, checkpoint.start, checkpoint.end)));
+        }
+        let mut slice_start = checkpoint.next;
+        for progress in &checkpoint.devices {
+            if !(slice_start <= progress.next && progress.next <= progress.end && progress.end <= checkpoint.end) {
+                return Err(PrimeError::Checkpoint(format!("{}: position {} of {} is outside its slice", path.display(), progress.next, progress.device)));
+            }
+            slice_start = progress.end;
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a temporary file and renames it over `path`, so a crash
+    /// mid-write leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self).map_err(|e| PrimeError::Checkpoint(e.to_string()))?;
+        write_atomically(path, text)
+    }
+
+    /// Fails unless the checkpoint was taken for exactly `range`.
+    pub fn check_range(&self, range: &Range<u64>) -> Result<()> {
+        if self.start != range.start || self.end != range.end {
+            return Err(PrimeError::InvalidRange(format!(
+                "checkpoint is for [{}, {}) but [{}, {}) was requested",
+                self.start, self.end, range.start, range.end
+            )));
+        }
+        Ok(())
+    }
+
+    /// The numbers still to be searched, as ascending ranges.
+    pub fn unfinished(&self) -> Vec<Range<u64>> {
+        if self.devices.is_empty() {
+            return std::iter::once(self.next..self.end).collect();
+        }
+        self.devices.iter().map(|progress| progress.next..progress.end).filter(|range| !range.is_empty()).collect()
+    }
+
+    /// Slices for `devices` that together cover what is left of the search, in their order.
+    ///
+    /// On the devices the checkpoint was taken on, each one carries on from its own position.
+    /// Otherwise the unfinished numbers are split evenly between `devices`. Slices are
+    /// contiguous, so a device whose share spans several of the old slices tests the
+    /// finished stretches between them again.
+    pub fn resume_slices(&self, devices: &[DeviceInfo]) -> Vec<Range<u64>> {
+        let same_devices = self.devices.len() == devices.len()
+            && self.devices.iter().zip(devices).all(|(progress, device)| progress.device == device.to_string());
+        if same_devices {
+            return self.devices.iter().map(|progress| progress.next..progress.end).collect();
+        }
+        if !self.devices.is_empty() {
+            info!("The devices changed since the checkpoint was taken, splitting the unfinished range between the current ones");
+        }
+        match self.unfinished() {
+            unfinished if unfinished.is_empty() => vec![self.end..self.end; devices.len()],
+            unfinished => partition_pieces(&unfinished, devices.len()),
+        }
+    }
+}
+
+/// The last prime found by a search that keeps finding bigger ones, with the range it was
+/// found in. Each session carries on from just after `prime`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastPrime {
+    pub prime: u64,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl LastPrime {
+    /// Reads the state saved at `path`, or None when there's no file there yet.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let last: LastPrime = toml::from_str(&text)
+            .map_err(|e| PrimeError::Checkpoint(format!("{}: {}", path.display(), e)))?;
+        if !(last.start <= last.prime && last.prime < last.end) {
+            return Err(PrimeError::Checkpoint(format!("{}: prime {} is outside [{}, {})", path.display(), last.prime, last.start, last.end)));
+        }
+        Ok(Some(last))
+    }
+
+    /// Saves the state the same way as [`Checkpoint::save`].
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self).map_err(|e| PrimeError::Checkpoint(e.to_string()))?;
+        write_atomically(path, text)
+    }
+
+    /// The range the next session searches: from just after the prime to `end`, or to the
+    /// saved end. Fails when `start` is given and isn't where the search carries on from, or
+    /// when nothing is left before the end.
+    pub fn next_range(&self, start: Option<u64>, end: Option<u64>) -> Result<Range<u64>> {
+        let next = self.prime + 1;
+        if let Some(start) = start.filter(|&start| start != next) {
+            return Err(PrimeError::InvalidRange(format!(
+                "the last prime found was {}, so the search continues from {}, not {}", self.prime, next, start,
+            )));
+        }
+        let end = end.unwrap_or(self.end);
+        if end <= next {
+            return Err(PrimeError::InvalidRange(format!(
+                "the last prime found was {}, so an end of {} leaves nothing to search; extend it past {}", self.prime, end, next,
+            )));
+        }
+        Ok(next..end)
+    }
+}
+
+// Writes to a temporary file and renames it over `path`
+fn write_atomically(path: &Path, text: String) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Periodically saves how far each device has got through its slice, by the lowest candidate
+// any of its threads is still working on.
+pub(crate) struct CheckpointWriter {
+    path: PathBuf,
+    interval: Duration,
+    origin: u64,
+    end: u64,
+    devices: Vec<String>,
+    state: Mutex<(Vec<Range<u64>>, Instant)>,
+}
+
+impl CheckpointWriter {
+    pub(crate) fn new(path: PathBuf, interval: Duration, origin: u64, end: u64, devices: Vec<String>) -> Self {
+        CheckpointWriter { path, interval, origin, end, devices, state: Mutex::new((vec![], Instant::now())) }
+    }
+
+    // The same file and interval, for a searcher moved to another range
+    pub(crate) fn for_range(&self, origin: u64, end: u64) -> Self {
+        Self::new(self.path.clone(), self.interval, origin, end, self.devices.clone())
+    }
+
+    // Starts tracking a new search over the given slices
+    pub(crate) fn reset(&self, slices: &[Range<u64>]) {
+        *self.state.lock().unwrap() = (slices.to_vec(), Instant::now());
+    }
+
+    pub(crate) fn record(&self, device: usize, lowest: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let slice = &mut state.0[device];
+        slice.start = lowest.min(slice.end);
+        if state.1.elapsed() < self.interval {
+            return Ok(());
+        }
+        state.1 = Instant::now();
+        self.checkpoint(&state.0).save(&self.path)
+    }
+
+    pub(crate) fn flush(&self) -> Result<()> {
+        let state = self.state.lock().unwrap();
+        self.checkpoint(&state.0).save(&self.path)
+    }
+
+    fn checkpoint(&self, remaining: &[Range<u64>]) -> Checkpoint {
+        let next = remaining.iter().map(|slice| slice.start).min().unwrap_or(self.end).clamp(self.origin, self.end);
+        let devices = self.devices.iter().zip(remaining)
+            .map(|(device, slice)| DeviceProgress { device: device.clone(), next: slice.start, end: slice.end })
+            .collect();
+        Checkpoint { start: self.origin, end: self.end, next, devices }
+    }
+}
@@ -0,0 +1,106 @@
+use nvml::{Nvml, enums::device::GpuLockedClocksSetting, error::NvmlError};
+use std::sync::Arc;
+
+use crate::{PrimeSearcher, monitor::VENDOR_NVIDIA};
+
+/// Graphics and memory clocks in MHz to hold NVIDIA devices at, so benchmark numbers don't
+/// depend on how far each run happened to boost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSettings {
+    pub graphics_mhz: u32,
+    pub memory_mhz: u32,
+}
+
+/// Clocks locked by [`PrimeSearcher::lock_clocks`], reset to the driver's defaults when
+/// dropped.
+pub struct LockedClocks {
+    nvml: Option<Arc<Nvml>>,
+    // Per locked device: its index in the searcher, its NVML index, and whether the memory
+    // clock was locked as well as the graphics clock
+    devices: Vec<(usize, u32, bool)>,
+}
+
+impl LockedClocks {
+    /// Devices, as indices into [`PrimeSearcher::devices`], whose graphics clock is locked.
+    pub fn devices(&self) -> Vec<usize> {
+        self.devices.iter().map(|&(i, _, _)| i).collect()
+    }
+}
+
+impl Drop for LockedClocks {
+    fn drop(&mut self) {
+        let Some(nvml) = &self.nvml else {
+            return;
+        };
+        for &(i, index, memory) in &self.devices {
+            let reset = nvml.device_by_index(index).and_then(|mut device| {
+                device.reset_gpu_locked_clocks()?;
+                if memory {
+                    device.reset_mem_locked_clocks()?;
+                }
+                Ok(())
+            });
+            match reset {
+                Ok(()) => info!("GPU {}: clocks restored to the driver's defaults", i),
+                Err(e) => warn!("GPU {}: failed to restore clocks, they stay locked until the driver reloads: {}", i, e),
+            }
+        }
+    }
+}
+
+impl PrimeSearcher {
+    /// Locks every NVIDIA device's graphics and memory clocks until the returned guard is
+    /// dropped.
+    ///
+    /// Locking usually needs root, and memory clocks can only be locked on Ampere and newer.
+    /// A device that can't be locked is warned about and left at its own clocks rather than
+    /// failing the caller, as are devices from other vendors.
+    pub fn lock_clocks(&self, clocks: ClockSettings) -> LockedClocks {
+        let mut locked = LockedClocks { nvml: None, devices: vec![] };
+        for (i, device) in self.devices.iter().enumerate() {
+            if self.vendor_ids[i] != VENDOR_NVIDIA {
+                warn!("GPU {} ({}): clocks can only be locked on NVIDIA devices", i, device.name);
+                continue;
+            }
+            let Some((nvml, index)) = self.nvml().and_then(|nvml| Some((Arc::clone(&nvml), self.pci_addresses[i]?.nvml_index(&nvml)?))) else {
+                warn!("GPU {} ({}): not found through NVML, its clocks can't be locked", i, device.name);
+                continue;
+            };
+            let mut nvml_device = match nvml.device_by_index(index) {
+                Ok(nvml_device) => nvml_device,
+                Err(e) => {
+                    warn!("GPU {} ({}): clocks not locked: {}", i, device.name, e);
+                    continue;
+                }
+            };
+
+            let graphics = GpuLockedClocksSetting::Numeric { min_clock_mhz: clocks.graphics_mhz, max_clock_mhz: clocks.graphics_mhz };
+            if let Err(e) = nvml_device.set_gpu_locked_clocks(graphics) {
+                warn!("GPU {} ({}): clocks not locked: {}", i, device.name, describe(e));
+                continue;
+            }
+            let memory = match nvml_device.set_mem_locked_clocks(clocks.memory_mhz, clocks.memory_mhz) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("GPU {} ({}): only the graphics clock is locked: {}", i, device.name, describe(e));
+                    false
+                }
+            };
+            info!("GPU {} ({}): graphics clock locked at {} MHz{}", i, device.name, clocks.graphics_mhz,
+                if memory { format!(", memory clock at {} MHz", clocks.memory_mhz) } else { String::new() });
+            locked.nvml = Some(nvml);
+            locked.devices.push((i, index, memory));
+        }
+        locked
+    }
+}
+
+// The usual reasons a clock can't be locked, in terms of what to do about them
+fn describe(e: NvmlError) -> String {
+    match e {
+        NvmlError::NoPermission => "locking clocks needs root".to_string(),
+        NvmlError::NotSupported => "the device doesn't support it".to_string(),
+        NvmlError::InvalidArg => "the device doesn't support those clocks".to_string(),
+        e => e.to_string(),
+    }
+}
@@ -0,0 +1,46 @@
+use ocl::Event;
+use ocl::core::{CommandExecutionStatus, ffi::{c_void, cl_event}};
+use std::{sync::{Arc, Condvar, Mutex}, time::Duration};
+
+// Whether the kernel has completed, and the monitor thread waiting on it
+type Signal = (Mutex<bool>, Condvar);
+
+// Wakes a monitor thread as soon as its device's kernel completes, through a callback on the
+// kernel's event, instead of at the next poll. The thread then reads the result straight away,
+// and once it halts the devices that can no longer beat it their callbacks wake their threads
+// in turn. On a platform that can't register the callback, waiting is just sleeping until the
+// next poll.
+pub(crate) struct Completion {
+    signal: Arc<Signal>,
+}
+
+impl Completion {
+    pub(crate) fn watch(device: usize, event: &Event) -> Self {
+        let signal = Arc::new((Mutex::new(false), Condvar::new()));
+        let user_data = Arc::into_raw(Arc::clone(&signal)) as *mut c_void;
+        // A callback registered after the event completed runs straight away
+        let registered = unsafe {
+            ocl::core::set_event_callback(&**event, CommandExecutionStatus::Complete, Some(signal_complete), user_data)
+        };
+        if let Err(e) = registered {
+            drop(unsafe { Arc::from_raw(user_data as *const Signal) });
+            debug!("GPU {}: no completion callback, polling instead: {}", device, e);
+        }
+        Completion { signal }
+    }
+
+    // Sleeps for up to `timeout`, returning early once the kernel completes
+    pub(crate) fn wait(&self, timeout: Duration) {
+        let (complete, woken) = &*self.signal;
+        let complete = complete.lock().unwrap();
+        let _ = woken.wait_timeout_while(complete, timeout, |complete| !*complete).unwrap();
+    }
+}
+
+extern "C" fn signal_complete(_event: cl_event, _status: i32, user_data: *mut c_void) {
+    // Takes back the reference handed over when the callback was registered
+    let signal = unsafe { Arc::from_raw(user_data as *const Signal) };
+    let (complete, woken) = &*signal;
+    *complete.lock().unwrap() = true;
+    woken.notify_all();
+}
@@ -0,0 +1,200 @@
+use std::{ops::Range, sync::{Mutex, atomic::{AtomicU64, Ordering}}, thread, time::{Duration, Instant}};
+
+use crate::{CancelHandle, DeviceInfo, DeviceReport, PrimeError, Progression, Result, SearchReport, verify::is_prime};
+
+// Candidates a worker claims at a time; small enough that find_first stops soon after a hit
+const BLOCK: u64 = 1 << 14;
+
+/// Multi-threaded search on the host, for machines without a usable OpenCL device.
+///
+/// Candidates are tested with [`verify::is_prime`](crate::verify::is_prime), the same
+/// deterministic Miller-Rabin the `MillerRabin` kernel runs. Workers claim blocks of the
+/// range in ascending order, so [`find_first`](Self::find_first) returns the smallest prime.
+pub struct CpuSearcher {
+    range: Range<u64>,
+    threads: usize,
+    cancel: CancelHandle,
+    // Highest block end reached and the report of the last search
+    progress: AtomicU64,
+    report: Mutex<SearchReport>,
+}
+
+impl CpuSearcher {
+    /// Searches `range` on one thread per available core.
+    pub fn new(range: Range<u64>) -> Result<Self> {
+        check_range(&range)?;
+        Ok(CpuSearcher {
+            range,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            cancel: CancelHandle::without_kernels(),
+            progress: AtomicU64::new(0),
+            report: Mutex::new(SearchReport { devices: vec![], elapsed: Duration::ZERO, found_by: None }),
+        })
+    }
+
+    /// Sets the number of worker threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    /// The host, described as a single device with one compute unit per worker thread.
+    pub fn device(&self) -> DeviceInfo {
+        DeviceInfo { index: 0, platform: "Host".into(), name: format!("CPU ({} threads)", self.threads), compute_units: self.threads as u32, ..DeviceInfo::default() }
+    }
+
+    /// Handle that stops searches on this searcher from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Highest candidate the workers had reached when the last search stopped.
+    pub fn progress(&self) -> u64 {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// What the workers did during the last search, as a single device.
+    pub fn report(&self) -> SearchReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Returns the smallest prime in the range.
+    pub fn find_first(&self) -> Result<Option<u64>> {
+        self.find_first_in(self.range.clone())
+    }
+
+    /// Returns the smallest twin primes (p, p + 2) with both in the range.
+    pub fn find_twin(&self) -> Result<Option<(u64, u64)>> {
+        self.find_twin_in(self.range.clone())
+    }
+
+    /// Returns every prime in the range in ascending order.
+    pub fn find_all(&self) -> Result<Vec<u64>> {
+        self.find_all_in(self.range.clone())
+    }
+
+    /// Counts the primes in the range.
+    pub fn count(&self) -> Result<u64> {
+        self.count_in(self.range.clone())
+    }
+
+    /// Returns the smallest prime of the form `a * k + b` in the range; see
+    /// [`Progression::new`] for which `a` and `b` are accepted.
+    pub fn find_in_progression(&self, a: u64, b: u64) -> Result<Option<u64>> {
+        self.find_in_progression_in(self.range.clone(), Progression::new(a, b)?)
+    }
+
+    pub(crate) fn find_first_in(&self, range: Range<u64>) -> Result<Option<u64>> {
+        check_range(&range)?;
+        Ok(self.first_match(range, is_prime))
+    }
+
+    pub(crate) fn find_twin_in(&self, range: Range<u64>) -> Result<Option<(u64, u64)>> {
+        check_range(&range)?;
+        let candidates = range.start..range.end.saturating_sub(2).max(range.start);
+        Ok(self.first_match(candidates, |n| is_prime(n) && is_prime(n + 2)).map(|p| (p, p + 2)))
+    }
+
+    pub(crate) fn find_in_progression_in(&self, range: Range<u64>, progression: Progression) -> Result<Option<u64>> {
+        check_range(&range)?;
+        // Scanned anyway, so the report shows the range as searched
+        progression.check_meets(&range);
+        Ok(self.first_match(range, |n| progression.contains(n) && is_prime(n)))
+    }
+
+    pub(crate) fn find_all_in(&self, range: Range<u64>) -> Result<Vec<u64>> {
+        check_range(&range)?;
+        let mut blocks = self.scan(range, |_| true, |block| {
+            block.filter(|&n| is_prime(n)).collect::<Vec<_>>()
+        });
+        blocks.sort_unstable_by_key(|(start, _)| *start);
+        Ok(blocks.into_iter().flat_map(|(_, primes)| primes).collect())
+    }
+
+    pub(crate) fn count_in(&self, range: Range<u64>) -> Result<u64> {
+        check_range(&range)?;
+        let blocks = self.scan(range, |_| true, |block| block.filter(|&n| is_prime(n)).count() as u64);
+        Ok(blocks.into_iter().map(|(_, count)| count).sum())
+    }
+
+    // Smallest n in `range` passing `test`. A block is skipped once a hit below its start is
+    // known, and every block below a hit has been claimed earlier, so the minimum is exact.
+    fn first_match(&self, range: Range<u64>, test: impl Fn(u64) -> bool + Sync) -> Option<u64> {
+        let best = AtomicU64::new(u64::MAX);
+        self.scan(range, |start| start < best.load(Ordering::Relaxed), |mut block| {
+            if let Some(n) = block.find(|&n| test(n)) {
+                best.fetch_min(n, Ordering::Relaxed);
+            }
+        });
+        let best = Some(best.into_inner()).filter(|&n| n != u64::MAX);
+        let mut report = self.report.lock().unwrap();
+        report.devices[0].found_prime = best.is_some();
+        report.found_by = best.map(|_| self.device());
+        best
+    }
+
+    // Runs `visit` over BLOCK-sized pieces of `range`, handed out in ascending order to the
+    // worker threads, and returns each result with the start of its block. Workers stop once
+    // `wanted` rejects the next block or the search is cancelled.
+    fn scan<T, W, V>(&self, range: Range<u64>, wanted: W, visit: V) -> Vec<(u64, T)>
+    where
+        T: Send,
+        W: Fn(u64) -> bool + Sync,
+        V: Fn(Range<u64>) -> T + Sync,
+    {
+        let started = Instant::now();
+        self.progress.store(0, Ordering::Relaxed);
+        let tested = AtomicU64::new(0);
+        let next = AtomicU64::new(0);
+        let results = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(start) = index.checked_mul(BLOCK).and_then(|offset| range.start.checked_add(offset)) else { break };
+                    if start >= range.end || !wanted(start) || self.cancel.is_cancelled() {
+                        break;
+                    }
+                    let block = start..start.saturating_add(BLOCK).min(range.end);
+                    let end = block.end;
+                    let result = visit(block);
+                    tested.fetch_add(end - start, Ordering::Relaxed);
+                    self.progress.fetch_max(end - 1, Ordering::Relaxed);
+                    results.lock().unwrap().push((start, result));
+                });
+            }
+        });
+
+        let elapsed = started.elapsed();
+        *self.report.lock().unwrap() = SearchReport {
+            devices: vec![DeviceReport {
+                name: self.device().name,
+                tested: tested.into_inner(),
+                remaining: 0,
+                wall_time: elapsed,
+                peak_temperature: None,
+                found_prime: false,
+                kernel_time: None,
+                kernel_launches: 0,
+            }],
+            elapsed,
+            found_by: None,
+        };
+        results.into_inner().unwrap()
+    }
+}
+
+fn check_range(range: &Range<u64>) -> Result<()> {
+    if range.start > range.end {
+        return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+    }
+    Ok(())
+}
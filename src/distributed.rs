@@ -0,0 +1,368 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, io::{self, BufReader, Read, Write}, net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs}, ops::Range, sync::{Arc, Condvar, Mutex}, thread, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Backend, CancelHandle, PrimeError, Result, partition_chunks, primality::is_prime_u64};
+
+// One search spread over several machines. A Coordinator splits the range into chunks and
+// hands them out over TCP to workers, each running run_worker on its own backend, which
+// search one chunk at a time and report the smallest prime in it. The smallest prime of the
+// range is known once some chunk has one and every chunk before it has been reported empty.
+//
+// Every message is a big-endian u32 length followed by that many bytes of JSON. A worker
+// sends RequestChunk and gets AssignChunk or Done back, and sends ReportResult once it has
+// searched a chunk. There is no authentication, so the coordinator should only listen where
+// its workers can reach it.
+
+/// A message between a [`Coordinator`] and a worker, see [`write_message`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    /// From a worker that is ready for a chunk
+    RequestChunk,
+    /// The chunk `[start, end)` for the worker to search, reported back as `id`
+    AssignChunk { id: u64, start: u64, end: u64 },
+    /// The smallest prime in chunk `id`, or no primes if it has none
+    ReportResult { id: u64, primes: Vec<u64> },
+    /// Sent instead of a chunk once the search is over
+    Done,
+}
+
+/// The longest message [`read_message`] accepts, in bytes.
+pub const MAX_MESSAGE_LEN: u32 = 1 << 20;
+
+// How long the coordinator waits between checks for new workers
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+/// Writes `message` as its length, a big-endian `u32`, followed by its JSON.
+pub fn write_message(mut writer: impl Write, message: &Message) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    let len = u32::try_from(body.len()).ok().filter(|&len| len <= MAX_MESSAGE_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("a {} byte message is too long to send", body.len())))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+/// Reads a message written by [`write_message`], failing on one longer than
+/// [`MAX_MESSAGE_LEN`] without reading it.
+pub fn read_message(mut reader: impl Read) -> io::Result<Message> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("a {} byte message is longer than the {} allowed", len, MAX_MESSAGE_LEN)));
+    }
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Hands out a range in chunks to workers connecting over TCP and collects the smallest prime
+/// they find, see [`run_worker`].
+///
+/// Chunks go out in ascending order, each to one worker at a time. The chunks of a worker that
+/// disconnects before reporting them are handed out again, as are those whose reported prime
+/// the coordinator doesn't confirm on the CPU, though never back to the worker that reported
+/// it. Chunks past the first one known to hold a prime are never handed out.
+///
+/// A chunk that every connected worker has reported a wrong prime for, with no other chunk
+/// being searched, ends the run with [`PrimeError::UntrustedWorkers`].
+pub struct Coordinator {
+    listener: TcpListener,
+    queue: Arc<(Mutex<WorkQueue>, Condvar)>,
+}
+
+impl Coordinator {
+    /// Listens at `addr` to hand out `range` in chunks of `chunk_size` numbers. Port 0 picks a
+    /// free one, see [`local_addr`](Self::local_addr).
+    pub fn bind(addr: impl ToSocketAddrs, range: Range<u64>, chunk_size: u64) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let chunks = partition_chunks(range, chunk_size.max(1));
+        let queue = WorkQueue {
+            pending: (0..chunks.len()).collect(),
+            outstanding: HashMap::new(),
+            failed_by: HashMap::new(),
+            connected: HashSet::new(),
+            untrusted: None,
+            results: vec![None; chunks.len()],
+            chunks,
+            stopped: false,
+            workers: 0,
+        };
+        Ok(Coordinator { listener, queue: Arc::new((Mutex::new(queue), Condvar::new())) })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves workers until the smallest prime in the range is known, returning it, or until
+    /// every chunk has been searched without finding one. Cancelling `cancel` stops the search
+    /// early and returns None. Workers asking for a chunk afterwards are told the search is
+    /// done for as long as the coordinator is still running.
+    pub fn run(&self, cancel: &CancelHandle) -> Result<Option<u64>> {
+        let (lock, changed) = &*self.queue;
+        loop {
+            let work = lock.lock().unwrap();
+            if let Some(answer) = work.answer() {
+                return Ok(answer);
+            }
+            if let Some(id) = work.untrusted {
+                let chunk = work.chunks[id].clone();
+                drop(work);
+                self.stop();
+                return Err(PrimeError::UntrustedWorkers { start: chunk.start, end: chunk.end });
+            }
+            if cancel.is_cancelled() {
+                drop(work);
+                self.stop();
+                return Ok(None);
+            }
+            drop(work);
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    info!("Worker {} connected", peer);
+                    let queue = Arc::clone(&self.queue);
+                    thread::spawn(move || serve_worker(stream, peer, queue));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // Woken early by a worker's report
+                    let work = lock.lock().unwrap();
+                    drop(changed.wait_timeout(work, ACCEPT_POLL).unwrap());
+                }
+                Err(e) => {
+                    warn!("Failed to accept a worker: {}", e);
+                    thread::sleep(ACCEPT_POLL);
+                }
+            }
+        }
+    }
+
+    /// The number of chunks yet to be reported, including those being searched.
+    pub fn chunks_left(&self) -> usize {
+        self.queue.0.lock().unwrap().results.iter().filter(|result| result.is_none()).count()
+    }
+
+    // Tells the workers waiting for a chunk, and those asking later, that there are none
+    fn stop(&self) {
+        let (lock, changed) = &*self.queue;
+        lock.lock().unwrap().stopped = true;
+        changed.notify_all();
+    }
+}
+
+impl Drop for Coordinator {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// The coordinator's record of which chunks are searched, and what was found in them
+struct WorkQueue {
+    chunks: Vec<Range<u64>>,
+    // Indices of the chunks waiting for a worker, smallest first
+    pending: BTreeSet<usize>,
+    // Chunk index to the worker searching it
+    outstanding: HashMap<usize, usize>,
+    // Chunk index to the workers that reported a wrong prime for it, which don't get it again
+    failed_by: HashMap<usize, HashSet<usize>>,
+    // Workers connected now
+    connected: HashSet<usize>,
+    // A chunk no connected worker can be given, which ends the run
+    untrusted: Option<usize>,
+    // The smallest prime reported for each chunk, None until it is reported
+    results: Vec<Option<Option<u64>>>,
+    stopped: bool,
+    // Workers that have connected, numbering the next one
+    workers: usize,
+}
+
+impl WorkQueue {
+    // The smallest prime in the range once every chunk up to the one holding it has been
+    // reported, or None among them all once every chunk has
+    fn answer(&self) -> Option<Option<u64>> {
+        for result in &self.results {
+            match result {
+                None => return None,
+                Some(Some(prime)) => return Some(Some(*prime)),
+                Some(None) => {}
+            }
+        }
+        Some(None)
+    }
+
+    fn is_over(&self) -> bool {
+        self.stopped || self.untrusted.is_some() || self.answer().is_some()
+    }
+
+    // A chunk that has to be searched but that every connected worker got wrong, once no other
+    // chunk is being searched that could still settle the answer or free a worker for it
+    fn find_untrusted(&self) -> Option<usize> {
+        if !self.outstanding.is_empty() || self.connected.is_empty() {
+            return None;
+        }
+        let bound = self.results.iter().position(|result| matches!(result, Some(Some(_)))).unwrap_or(self.chunks.len());
+        self.pending.iter().copied()
+            .take_while(|&id| id < bound)
+            .find(|id| self.failed_by.get(id).is_some_and(|workers| workers.is_superset(&self.connected)))
+    }
+
+    // The next chunk for `worker`, skipping those past a chunk known to hold a prime and those
+    // it got wrong before
+    fn take(&mut self, worker: usize) -> Option<usize> {
+        let bound = self.results.iter().position(|result| matches!(result, Some(Some(_)))).unwrap_or(self.chunks.len());
+        let id = self.pending.iter().copied()
+            .take_while(|&id| id < bound)
+            .find(|id| !self.failed_by.get(id).is_some_and(|workers| workers.contains(&worker)))?;
+        self.pending.remove(&id);
+        self.outstanding.insert(id, worker);
+        Some(id)
+    }
+
+    // Records `worker`'s result for chunk `id`, checking it first. A wrong prime puts the chunk
+    // back to be searched again by another worker.
+    fn report(&mut self, worker: usize, id: usize, primes: &[u64]) -> std::result::Result<(), String> {
+        if self.outstanding.get(&id) != Some(&worker) {
+            return Err(format!("chunk {} wasn't assigned to it", id));
+        }
+        self.outstanding.remove(&id);
+        let chunk = &self.chunks[id];
+        let prime = primes.iter().copied().min();
+        if let Some(prime) = prime.filter(|&prime| !chunk.contains(&prime) || !is_prime_u64(prime)) {
+            self.pending.insert(id);
+            self.failed_by.entry(id).or_default().insert(worker);
+            return Err(format!("{} isn't a prime in chunk {}, [{}, {})", prime, id, chunk.start, chunk.end));
+        }
+        self.results[id] = Some(prime);
+        Ok(())
+    }
+
+    // Puts back the chunks a lost worker was searching
+    fn release(&mut self, worker: usize) {
+        let lost: Vec<usize> = self.outstanding.iter().filter(|&(_, &by)| by == worker).map(|(&id, _)| id).collect();
+        for id in lost {
+            self.outstanding.remove(&id);
+            self.pending.insert(id);
+        }
+    }
+}
+
+// Answers one worker's messages on its own thread until it disconnects or is told it's done
+fn serve_worker(stream: TcpStream, peer: SocketAddr, queue: Arc<(Mutex<WorkQueue>, Condvar)>) {
+    let worker = {
+        let mut work = queue.0.lock().unwrap();
+        work.workers += 1;
+        let worker = work.workers;
+        work.connected.insert(worker);
+        worker
+    };
+    let served = serve(stream, peer, worker, &queue);
+    let (lock, changed) = &*queue;
+    let mut work = lock.lock().unwrap();
+    let searching = work.outstanding.values().filter(|&&by| by == worker).count();
+    work.release(worker);
+    work.connected.remove(&worker);
+    changed.notify_all();
+    match served {
+        Ok(()) => debug!("Worker {} is done", peer),
+        Err(e) if searching > 0 => warn!("Lost worker {}: {}, handing its {} chunk(s) out again", peer, e, searching),
+        Err(e) => info!("Worker {} disconnected: {}", peer, e),
+    }
+}
+
+fn serve(stream: TcpStream, peer: SocketAddr, worker: usize, queue: &(Mutex<WorkQueue>, Condvar)) -> io::Result<()> {
+    let (lock, changed) = queue;
+    // Accepted connections may inherit the listener's non-blocking mode
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    loop {
+        match read_message(&mut reader)? {
+            Message::RequestChunk => {
+                let assigned = {
+                    let mut work = lock.lock().unwrap();
+                    loop {
+                        if work.is_over() {
+                            break None;
+                        }
+                        if let Some(id) = work.take(worker) {
+                            break Some((id, work.chunks[id].clone()));
+                        }
+                        if let Some(id) = work.find_untrusted() {
+                            let chunk = &work.chunks[id];
+                            warn!("Every connected worker reported a wrong prime for chunk {}, [{}, {}), giving up", id, chunk.start, chunk.end);
+                            work.untrusted = Some(id);
+                            changed.notify_all();
+                            break None;
+                        }
+                        // Every chunk left is being searched, and one may come back if its
+                        // worker is lost
+                        work = changed.wait(work).unwrap();
+                    }
+                };
+                let Some((id, chunk)) = assigned else {
+                    return write_message(&mut writer, &Message::Done);
+                };
+                debug!("Assigned chunk {}, [{}, {}), to worker {}", id, chunk.start, chunk.end, peer);
+                write_message(&mut writer, &Message::AssignChunk { id: id as u64, start: chunk.start, end: chunk.end })?;
+            }
+            Message::ReportResult { id, primes } => {
+                let mut work = lock.lock().unwrap();
+                let checked = usize::try_from(id).map_err(|_| format!("chunk {} doesn't exist", id)).and_then(|id| work.report(worker, id, &primes));
+                if let Err(reason) = checked {
+                    warn!("Ignoring a result from worker {}: {}", peer, reason);
+                }
+                changed.notify_all();
+            }
+            other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("a worker sent {:?}", other))),
+        }
+    }
+}
+
+/// Connects to the [`Coordinator`] at `addr` and searches the chunks it hands out on
+/// `backend`, one at a time, returning how many were searched once it has no more.
+///
+/// A coordinator that hangs up has finished the search and exited, so that ends the work
+/// too. Cancelling the backend stops after the chunk being searched, which isn't reported,
+/// since a cancelled search may have missed its prime; the coordinator hands it out again.
+pub fn run_worker(addr: impl ToSocketAddrs, backend: &dyn Backend) -> Result<u64> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let cancel = backend.cancel_handle();
+    let mut searched = 0;
+    loop {
+        let reply = write_message(&mut writer, &Message::RequestChunk).and_then(|()| read_message(&mut reader));
+        let chunk = match reply {
+            Ok(Message::AssignChunk { id, start, end }) if start <= end => (id, start..end),
+            Ok(Message::Done) => return Ok(searched),
+            Ok(other) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("the coordinator sent {:?}", other)).into()),
+            Err(e) if hung_up(&e) => {
+                info!("The coordinator closed the connection");
+                return Ok(searched);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (id, range) = chunk;
+        debug!("Searching chunk {}, [{}, {})", id, range.start, range.end);
+        let prime = backend.find_first(range)?;
+        if cancel.is_cancelled() {
+            return Ok(searched);
+        }
+        match write_message(&mut writer, &Message::ReportResult { id, primes: prime.into_iter().collect() }) {
+            Err(e) if hung_up(&e) => {
+                info!("The coordinator closed the connection");
+                return Ok(searched);
+            }
+            reported => reported?,
+        }
+        searched += 1;
+    }
+}
+
+fn hung_up(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe)
+}
@@ -0,0 +1,362 @@
+use ocl::{Buffer, Event, MemFlags};
+use std::{collections::VecDeque, ops::Range, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}}};
+
+use crate::{PrimeError, PrimeSearcher, Relaunch, Result, Target, partition_chunks, status_work_size, verify, work_group};
+
+// Work shared by the monitor threads of a dynamically partitioned search
+struct Chunks {
+    // Chunks still to search, in ascending order
+    queue: Mutex<VecDeque<Range<u64>>>,
+    // The chunk each device is running
+    running: Mutex<Vec<Range<u64>>>,
+    // Whether each device will still ask for a chunk, only changed with `queue` locked so a
+    // chunk handed back is never left without a device to take it
+    active: Mutex<Vec<bool>>,
+}
+
+impl PrimeSearcher {
+    // search_first for PartitionStrategy::Dynamic. Chunks go out in ascending order and a hit
+    // halts every device on a higher chunk, so the lowest verified hit is the smallest prime.
+    pub(crate) fn search_first_dynamic(&self, range: &Range<u64>, target: Target, chunk_size: u64) -> Result<Option<u128>> {
+        let mut chunks = partition_chunks(range.clone(), chunk_size);
+        if target == Target::TwinPrime {
+            // Overlap each chunk with the next so a pair straddling the boundary is still seen
+            for chunk in &mut chunks {
+                chunk.end = chunk.end.saturating_add(2).min(range.end).max(chunk.start);
+            }
+        }
+        let name = match target {
+            Target::Prime => "search_for_large_prime",
+            Target::TwinPrime => "search_twin_primes",
+            Target::Progression(_) => "search_progression",
+        };
+        let algorithm = self.algorithm.kernel_id()?;
+        let best: Arc<Mutex<Option<(u64, usize)>>> = Arc::new(Mutex::new(None));
+
+        let launch = {
+            let (pro_ques, result_buffers, status_buffers) = (self.pro_ques.clone(), self.result_buffers.clone(), self.status_buffers.clone());
+            let (thread_counts, local_size, halt, retry) = (self.thread_counts.clone(), self.local_size, self.cancel.halt_kernels().clone(), self.retry);
+            move |i: usize, chunk: Range<u64>| -> Result<Event> {
+                result_buffers[i].cmd().fill(u64::MAX, None).enq()?;
+                status_buffers[i].cmd().fill(0u64, None).enq()?;
+                halt.flag(i).cmd().fill(0, None).enq()?;
+                let mut builder = pro_ques[i].kernel_builder(name);
+                builder
+                    .global_work_size(status_work_size(i, &status_buffers[i], thread_counts[i])?)
+                    .local_work_size(work_group(local_size));
+                match target {
+                    // The kernel starts the chunk at its first term
+                    Target::Progression(progression) => {
+                        let first = progression.next_term(chunk.start).unwrap_or(chunk.end);
+                        builder.arg(first).arg(chunk.end).arg(algorithm).arg(progression.modulus())
+                    }
+                    _ => builder.arg(chunk.start).arg(chunk.end).arg(algorithm),
+                };
+                let kernel = builder
+                    .arg(&*result_buffers[i])
+                    .arg(&*status_buffers[i])
+                    .arg(halt.flag(i))
+                    .arg(halt.pause_flag(i))
+                    .build()?;
+                retry.enqueue(i, &kernel)
+            }
+        };
+
+        let complete = {
+            let (result_buffers, halt, cancel) = (self.result_buffers.clone(), self.cancel.halt_kernels().clone(), self.cancel.clone());
+            let (verify, print_status, best, retry) = (self.verify, self.print_status, Arc::clone(&best), self.retry);
+            let (devices, prime_hook) = (self.devices.clone(), self.prime_hook.clone());
+            move |i: usize, chunk: Range<u64>, chunks: &Chunks| -> Result<()> {
+                let needed = |start: u64| best.lock().unwrap().is_none_or(|(value, _)| start < value);
+
+                // A halt meant for the device's previous chunk can land on this one, which then
+                // has to be searched again if it still matters
+                let mut halted = [0i32];
+                retry.run(i, "halt flag read", || Ok(halt.flag(i).read(&mut halted[..]).enq()?))?;
+                if halted[0] != 0 {
+                    if !cancel.is_cancelled() && !chunk.is_empty() && needed(chunk.start) {
+                        chunks.queue.lock().unwrap().push_front(chunk);
+                    }
+                    return Ok(());
+                }
+
+                let mut result = [0u64];
+                retry.run(i, "result read", || Ok(result_buffers[i].read(&mut result[..]).enq()?))?;
+                let value = result[0];
+                if value == u64::MAX {
+                    return Ok(());
+                }
+                let verified = !verify || match target {
+                    Target::Prime | Target::Progression(_) => verify::is_prime(value),
+                    Target::TwinPrime => verify::is_prime(value) && verify::is_prime(value + 2),
+                };
+                if !verified {
+                    // Everything in the chunk below the rejected value has been tested
+                    let values = match target {
+                        Target::Prime | Target::Progression(_) => vec![value as u128],
+                        Target::TwinPrime => vec![value as u128, value as u128 + 2],
+                    };
+                    warn!(
+                        "GPU {} ({}) reported {} as prime but it failed CPU verification, continuing past it: {}",
+                        i, devices[i], value, verify::diagnose(&values, chunk.start as u128..chunk.end as u128),
+                    );
+                    chunks.queue.lock().unwrap().push_front(value + 1..chunk.end);
+                    return Ok(());
+                }
+
+                prime_hook.found(i, value as u128);
+                let mut best = best.lock().unwrap();
+                if best.is_none_or(|(lowest, _)| value < lowest) {
+                    *best = Some((value, i));
+                    if print_status {
+                        match target {
+                            Target::Prime => info!("Prime found by GPU {}, {}: {}", i, devices[i], value),
+                            Target::Progression(progression) => info!("Prime of the form {} found by GPU {}, {}: {}", progression, i, devices[i], value),
+                            Target::TwinPrime => info!("Twin primes found by GPU {}, {}: ({}, {})", i, devices[i], value, value + 2),
+                        }
+                    }
+                }
+                // Devices on higher chunks can no longer find anything smaller
+                for (j, running) in chunks.running.lock().unwrap().iter().enumerate() {
+                    if j != i && running.start > value {
+                        halt.halt_device(j)?;
+                    }
+                }
+                Ok(())
+            }
+        };
+
+        let wanted = {
+            let best = Arc::clone(&best);
+            move |chunk: &Range<u64>| best.lock().unwrap().is_none_or(|(value, _)| chunk.start < value)
+        };
+        self.run_chunks(range, chunks, launch, complete, wanted)?;
+
+        let best = *best.lock().unwrap();
+        if let Some((_, device)) = best {
+            self.report.mark_found(device);
+        }
+        Ok(best.map(|(value, _)| value as u128))
+    }
+
+    // count for PartitionStrategy::Dynamic
+    pub(crate) fn count_dynamic(&self, range: &Range<u64>, chunk_size: u64) -> Result<u64> {
+        let algorithm = self.algorithm.kernel_id()?;
+        let mut count_buffers = vec![];
+        for pq in &self.pro_ques {
+            count_buffers.push(Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .fill_val(0u64)
+                .build()?);
+        }
+        let total = Arc::new(AtomicU64::new(0));
+
+        let launch = {
+            let (pro_ques, status_buffers, thread_counts, local_size) = (self.pro_ques.clone(), self.status_buffers.clone(), self.thread_counts.clone(), self.local_size);
+            let (halt, count_buffers, retry) = (self.cancel.halt_kernels().clone(), count_buffers.clone(), self.retry);
+            move |i: usize, chunk: Range<u64>| -> Result<Event> {
+                status_buffers[i].cmd().fill(0u64, None).enq()?;
+                halt.flag(i).cmd().fill(0, None).enq()?;
+                let kernel = pro_ques[i].kernel_builder("count_primes")
+                    .global_work_size(status_work_size(i, &status_buffers[i], thread_counts[i])?)
+                    .local_work_size(work_group(local_size))
+                    .arg(chunk.start)
+                    .arg(chunk.end)
+                    .arg(algorithm)
+                    .arg(&count_buffers[i])
+                    .arg(&*status_buffers[i])
+                    .arg(halt.flag(i))
+                    .arg(halt.pause_flag(i))
+                    .build()?;
+                retry.enqueue(i, &kernel)
+            }
+        };
+
+        // Each count is cleared once added, so the final sweep below only picks up the chunks
+        // a cancellation interrupted
+        let collect = {
+            let (count_buffers, total, retry) = (count_buffers.clone(), Arc::clone(&total), self.retry);
+            move |i: usize| -> Result<()> {
+                let mut count = [0u64];
+                retry.run(i, "count read", || Ok(count_buffers[i].read(&mut count[..]).enq()?))?;
+                count_buffers[i].cmd().fill(0u64, None).enq()?;
+                total.fetch_add(count[0], Ordering::Relaxed);
+                Ok(())
+            }
+        };
+        let complete = {
+            let collect = collect.clone();
+            move |i: usize, _: Range<u64>, _: &Chunks| collect(i)
+        };
+        let retired = self.run_chunks(range, partition_chunks(range.clone(), chunk_size), launch, complete, |_| true)?;
+        for i in (0..self.pro_ques.len()).filter(|&i| !retired[i]) {
+            collect(i)?;
+        }
+        Ok(total.load(Ordering::Relaxed))
+    }
+
+    // find_all for PartitionStrategy::Dynamic; `capacity` is per chunk rather than per device
+    pub(crate) fn find_all_dynamic(&self, range: &Range<u64>, chunk_size: u64, capacity: usize, kernel_capacity: u32) -> Result<Vec<u64>> {
+        let algorithm = self.algorithm.kernel_id()?;
+        let mut prime_buffers = vec![];
+        let mut count_buffers = vec![];
+        for pq in &self.pro_ques {
+            prime_buffers.push(Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().write_only())
+                .len(capacity.max(1))
+                .build()?);
+            count_buffers.push(Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .fill_val(0u64)
+                .build()?);
+        }
+        let primes = Arc::new(Mutex::new(vec![]));
+
+        let launch = {
+            let (pro_ques, status_buffers, thread_counts, local_size) = (self.pro_ques.clone(), self.status_buffers.clone(), self.thread_counts.clone(), self.local_size);
+            let (halt, prime_buffers, count_buffers, retry) = (self.cancel.halt_kernels().clone(), prime_buffers.clone(), count_buffers.clone(), self.retry);
+            move |i: usize, chunk: Range<u64>| -> Result<Event> {
+                status_buffers[i].cmd().fill(0u64, None).enq()?;
+                halt.flag(i).cmd().fill(0, None).enq()?;
+                let kernel = pro_ques[i].kernel_builder("search_all_primes")
+                    .global_work_size(status_work_size(i, &status_buffers[i], thread_counts[i])?)
+                    .local_work_size(work_group(local_size))
+                    .arg(chunk.start)
+                    .arg(chunk.end)
+                    .arg(algorithm)
+                    .arg(&prime_buffers[i])
+                    .arg(&count_buffers[i])
+                    .arg(kernel_capacity)
+                    .arg(&*status_buffers[i])
+                    .arg(halt.flag(i))
+                    .arg(halt.pause_flag(i))
+                    .build()?;
+                retry.enqueue(i, &kernel)
+            }
+        };
+
+        // As in count_dynamic, a collected chunk's count is cleared
+        let collect = {
+            let (prime_buffers, count_buffers, primes, retry) = (prime_buffers.clone(), count_buffers.clone(), Arc::clone(&primes), self.retry);
+            move |i: usize| -> Result<()> {
+                let mut count = [0u64];
+                retry.run(i, "count read", || Ok(count_buffers[i].read(&mut count[..]).enq()?))?;
+                let found = usize::try_from(count[0]).unwrap_or(usize::MAX);
+                if found > capacity {
+                    return Err(PrimeError::ResultOverflow { found, capacity });
+                }
+                if found > 0 {
+                    let mut chunk_primes = vec![0u64; found];
+                    retry.run(i, "prime read", || Ok(prime_buffers[i].read(&mut chunk_primes).len(found).enq()?))?;
+                    // Cleared before the primes are kept, so a device given up on here
+                    // never has its chunk counted twice
+                    count_buffers[i].cmd().fill(0u64, None).enq()?;
+                    primes.lock().unwrap().extend(chunk_primes);
+                }
+                Ok(())
+            }
+        };
+        let complete = {
+            let collect = collect.clone();
+            move |i: usize, _: Range<u64>, _: &Chunks| collect(i)
+        };
+        let retired = self.run_chunks(range, partition_chunks(range.clone(), chunk_size), launch, complete, |_| true)?;
+        for i in (0..self.pro_ques.len()).filter(|&i| !retired[i]) {
+            collect(i)?;
+        }
+
+        let mut primes = std::mem::take(&mut *primes.lock().unwrap());
+        primes.sort_unstable();
+        Ok(primes)
+    }
+
+    // Starts every device on a chunk, then has each device's monitor thread hand it the next
+    // chunk `wanted` accepts once its current one completes and `complete` has read it.
+    // `wanted` rejecting a chunk ends the search, so it must only do so for all later ones.
+    //
+    // A device whose launch or `complete` still fails after the retries is given up on and
+    // its chunk handed back for the others, unless it is the last one left. Returns which
+    // devices were given up on, whose buffers may hold a chunk's partial results.
+    fn run_chunks<L, C, W>(&self, range: &Range<u64>, chunks: Vec<Range<u64>>, launch: L, complete: C, wanted: W) -> Result<Vec<bool>>
+    where
+        L: Fn(usize, Range<u64>) -> Result<Event> + Send + Sync + 'static,
+        C: Fn(usize, Range<u64>, &Chunks) -> Result<()> + Send + Sync + 'static,
+        W: Fn(&Range<u64>) -> bool + Send + Sync + 'static,
+    {
+        self.start_tracking(range, &[]);
+        let devices = self.pro_ques.len();
+        // Pauses from the thermal limit carry over between chunks, so only clear them here
+        for i in 0..devices {
+            self.cancel.halt_kernels().pause_flag(i).cmd().fill(0, None).enq()?;
+        }
+        let chunks = Arc::new(Chunks {
+            queue: Mutex::new(chunks.into()),
+            running: Mutex::new(vec![range.end..range.end; devices]),
+            active: Mutex::new(vec![true; devices]),
+        });
+        let retired = Arc::new(Mutex::new(vec![false; devices]));
+
+        let launch = Arc::new(launch);
+        let next = {
+            let (chunks, launch) = (Arc::clone(&chunks), Arc::clone(&launch));
+            move |i: usize| -> Result<Option<(Event, Range<u64>)>> {
+                let chunk = {
+                    let mut queue = chunks.queue.lock().unwrap();
+                    match queue.pop_front() {
+                        Some(chunk) if wanted(&chunk) => chunk,
+                        _ => {
+                            queue.clear();
+                            chunks.active.lock().unwrap()[i] = false;
+                            return Ok(None);
+                        }
+                    }
+                };
+                chunks.running.lock().unwrap()[i] = chunk.clone();
+                Ok(Some((launch(i, chunk.clone())?, chunk)))
+            }
+        };
+
+        // Devices left without a chunk run an empty one so every device has an event to watch
+        let mut events = vec![];
+        let mut slices = vec![];
+        for i in 0..devices {
+            let (event, chunk) = match next(i)? {
+                Some(started) => started,
+                None => (launch(i, range.end..range.end)?, range.end..range.end),
+            };
+            events.push(event);
+            slices.push(chunk);
+        }
+
+        let cancel = self.cancel.clone();
+        let given_up = Arc::clone(&retired);
+        let relaunch: Relaunch = Arc::new(move |i| {
+            let done = chunks.running.lock().unwrap()[i].clone();
+            let outcome = complete(i, done, &chunks).and_then(|()| if cancel.is_cancelled() { Ok(None) } else { next(i) });
+            let Err(PrimeError::Ocl(e)) = outcome else {
+                return outcome;
+            };
+            // The chunk is either the one just run, whose results weren't read, or the next
+            // one, which failed to start
+            let mut queue = chunks.queue.lock().unwrap();
+            let mut active = chunks.active.lock().unwrap();
+            active[i] = false;
+            if !active.contains(&true) {
+                return Err(PrimeError::Ocl(e));
+            }
+            let chunk = chunks.running.lock().unwrap()[i].clone();
+            error!("GPU {} failed ({}), handing [{}, {}) to the other devices", i, e, chunk.start, chunk.end);
+            queue.push_front(chunk);
+            given_up.lock().unwrap()[i] = true;
+            Ok(None)
+        });
+        self.monitor(events, &slices, None, Some(relaunch), |_, _| Ok(None::<()>))?;
+        let retired = retired.lock().unwrap().clone();
+        Ok(retired)
+    }
+}
@@ -0,0 +1,119 @@
+use nvml::error::NvmlError;
+use std::fmt;
+
+/// Errors returned by the prime search.
+pub enum PrimeError {
+    Ocl(ocl::Error),
+    Nvml(NvmlError),
+    NoDevices,
+    /// The device filter excluded all of this many devices
+    NoMatchingDevices(usize),
+    /// The devices the filter selected all lack what the kernels need, for these reasons; see
+    /// [`int64_incompatibility`](crate::int64_incompatibility)
+    UnsupportedDevices(Vec<String>),
+    InvalidRange(String),
+    /// An arithmetic progression that can't hold more than one prime, see
+    /// [`Progression::new`](crate::Progression::new)
+    InvalidProgression(String),
+    ResultOverflow { found: usize, capacity: usize },
+    Unsupported(String),
+    Io(std::io::Error),
+    Checkpoint(String),
+    /// The kernels failed to compile for a device; `log` is the compiler's build log
+    KernelBuild { device: String, log: String },
+    /// A custom kernel source lacks the expected entry point
+    KernelSource(String),
+    /// A device's kernel got a known number wrong in [`PrimeSearcher::self_check`](crate::PrimeSearcher::self_check)
+    SelfCheck { device: String, n: u64, reported_prime: bool },
+    /// A kernel was about to launch with more or fewer threads than device `device`'s status
+    /// buffer has slots for
+    StatusBufferMismatch { device: usize, len: usize, threads: usize },
+    /// These devices' monitor threads stopped responding and were abandoned, see
+    /// [`PrimeSearcher::with_watchdog_timeout`](crate::PrimeSearcher::with_watchdog_timeout)
+    Unresponsive(Vec<String>),
+    /// A device's kernel reported `value`, which lies outside the slice `start..end` it was given
+    ResultOutsideSlice { device: String, value: u128, start: u128, end: u128 },
+    /// Every worker connected to a [`Coordinator`](crate::Coordinator) reported a wrong prime
+    /// for the chunk `start..end`, so none is left to search it
+    UntrustedWorkers { start: u64, end: u64 },
+}
+
+impl fmt::Display for PrimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimeError::Ocl(e) => write!(f, "OpenCL error: {}", e),
+            PrimeError::Nvml(e) => write!(f, "NVML error: {}", e),
+            PrimeError::NoDevices => write!(f, "No OpenCL devices found"),
+            PrimeError::NoMatchingDevices(count) => write!(f, "None of the {} OpenCL devices match the device filter", count),
+            PrimeError::UnsupportedDevices(devices) => write!(f, "None of the selected devices can run the kernels: {}", devices.join(", ")),
+            PrimeError::InvalidRange(msg) => write!(f, "Invalid range: {}", msg),
+            PrimeError::InvalidProgression(msg) => write!(f, "Invalid progression: {}", msg),
+            PrimeError::ResultOverflow { found, capacity } => {
+                write!(f, "Found {} primes but the result buffer only holds {}", found, capacity)
+            }
+            PrimeError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            PrimeError::Io(e) => write!(f, "I/O error: {}", e),
+            PrimeError::Checkpoint(msg) => write!(f, "Invalid checkpoint: {}", msg),
+            PrimeError::KernelBuild { device, log } => {
+                write!(f, "Failed to build the kernels for {}:\n{}", device, log.trim_end())
+            }
+            PrimeError::KernelSource(msg) => write!(f, "Invalid kernel source: {}", msg),
+            PrimeError::SelfCheck { device, n, reported_prime } => write!(
+                f, "Self-check failed: {} reports {} as {}, so its kernel or driver can't be trusted",
+                device, n, if *reported_prime { "prime" } else { "composite" },
+            ),
+            PrimeError::StatusBufferMismatch { device, len, threads } => {
+                write!(f, "GPU {}'s status buffer has {} slots but its kernel would run {} threads", device, len, threads)
+            }
+            PrimeError::Unresponsive(devices) => write!(f, "Gave up waiting for {}, which stopped responding", devices.join(", ")),
+            PrimeError::ResultOutsideSlice { device, value, start, end } => {
+                write!(f, "{} reported {}, outside the slice {}..{} it searched", device, value, start, end)
+            }
+            PrimeError::UntrustedWorkers { start, end } => {
+                write!(f, "Every connected worker reported a wrong prime for [{}, {}), so none is left to search it", start, end)
+            }
+        }
+    }
+}
+
+// `main` returns this error directly, so Debug prints the readable message
+impl fmt::Debug for PrimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for PrimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrimeError::Ocl(e) => Some(e),
+            PrimeError::Nvml(e) => Some(e),
+            PrimeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ocl::Error> for PrimeError {
+    fn from(e: ocl::Error) -> Self {
+        PrimeError::Ocl(e)
+    }
+}
+
+impl From<ocl::OclCoreError> for PrimeError {
+    fn from(e: ocl::OclCoreError) -> Self {
+        PrimeError::Ocl(e.into())
+    }
+}
+
+impl From<NvmlError> for PrimeError {
+    fn from(e: NvmlError) -> Self {
+        PrimeError::Nvml(e)
+    }
+}
+
+impl From<std::io::Error> for PrimeError {
+    fn from(e: std::io::Error) -> Self {
+        PrimeError::Io(e)
+    }
+}
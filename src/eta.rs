@@ -0,0 +1,88 @@
+use std::{fmt::Write, time::{Duration, Instant}};
+
+// Weight of each new reading in the smoothed rate; lower rides out more of the jitter
+// between polls but takes longer to follow a real change, like a thermal pause
+const SMOOTHING: f64 = 0.3;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the rest of a search should take, for each device and for the whole search.
+///
+/// Derived from the rate each device's slowest thread has been closing in on the end of its
+/// slice. `None` means still estimating: the device hasn't been polled twice yet, or hasn't
+/// moved since it was.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Eta {
+    pub devices: Vec<Option<Duration>>,
+    pub total: Option<Duration>,
+}
+
+impl Eta {
+    pub(crate) fn estimating(devices: usize) -> Self {
+        Eta { devices: vec![None; devices], total: None }
+    }
+}
+
+/// Exponentially smoothed rate at which a search closes in on the end of its range.
+#[derive(Debug, Clone, Default)]
+pub struct RateEstimate {
+    last: Option<(Instant, u64)>,
+    rate: Option<f64>,
+}
+
+impl RateEstimate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `remaining` numbers were left at `now`. Readings are sampled a second
+    /// apart, so frequent polls don't make the rate jumpy; one with more left than the last,
+    /// as when a device moves on to a new chunk, restarts the measurement but keeps the rate.
+    pub fn record(&mut self, now: Instant, remaining: u64) {
+        if let Some((then, before)) = self.last {
+            let elapsed = now.duration_since(then);
+            if remaining <= before && elapsed < SAMPLE_INTERVAL {
+                return;
+            }
+            if remaining <= before {
+                let rate = (before - remaining) as f64 / elapsed.as_secs_f64();
+                self.rate = Some(self.rate.map_or(rate, |smoothed| smoothed + SMOOTHING * (rate - smoothed)));
+            }
+        }
+        self.last = Some((now, remaining));
+    }
+
+    /// Numbers per second, once two readings have been recorded.
+    pub fn rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// How long `remaining` numbers should take at the smoothed rate.
+    pub fn time_left(&self, remaining: u64) -> Option<Duration> {
+        time_at(remaining, self.rate?)
+    }
+}
+
+// None while nothing is moving, which would otherwise be an endless wait
+pub(crate) fn time_at(remaining: u64, rate: f64) -> Option<Duration> {
+    if remaining == 0 {
+        return Some(Duration::ZERO);
+    }
+    (rate > 0.0).then(|| Duration::try_from_secs_f64(remaining as f64 / rate).unwrap_or(Duration::MAX))
+}
+
+/// A time left as `1h 02m 03s`, `2m 03s` or `3s`, or `estimating...` without one.
+pub fn format_time_left(time: Option<Duration>) -> String {
+    let Some(time) = time else {
+        return "estimating...".into();
+    };
+    let secs = time.as_secs();
+    let mut text = String::new();
+    if secs >= 3600 {
+        let _ = write!(text, "{}h {:02}m {:02}s", secs / 3600, secs / 60 % 60, secs % 60);
+    } else if secs >= 60 {
+        let _ = write!(text, "{}m {:02}s", secs / 60, secs % 60);
+    } else {
+        let _ = write!(text, "{}s", secs);
+    }
+    text
+}
@@ -0,0 +1,121 @@
+use ocl::Event;
+use ocl::core::{CommandExecutionStatus, ffi::{c_void, cl_event}};
+use std::{future::Future, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll, Waker}};
+
+use crate::{Algorithm, Direction, PrimeError, PrimeSearcher, Result, Target, next_round};
+
+impl PrimeSearcher {
+    /// Like [`find_first`](Self::find_first), but waits for the kernels without blocking a
+    /// thread, so it can run on an async executor alongside other tasks.
+    ///
+    /// The future is woken by OpenCL event callbacks, so it doesn't depend on any particular
+    /// runtime; the tests drive it with both a bare `block_on` and tokio. Reading a device's
+    /// result once its kernel has completed doesn't wait on the device, so it is done inline
+    /// rather than on a blocking pool. Status polling, GPU monitoring and checkpoints are
+    /// left to the synchronous API.
+    pub async fn find_first_async(&self) -> Result<Option<u64>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("searchers created with new_u128 must use find_first_u128".into()));
+        }
+        if self.algorithm == Algorithm::SegmentedSieve {
+            return Err(PrimeError::Unsupported("the segmented sieve only lists primes through find_all".into()));
+        }
+        if self.direction == Direction::Down {
+            return Err(PrimeError::Unsupported("find_first_async only searches upwards".into()));
+        }
+        if self.cancel.is_cancelled() {
+            return Ok(None);
+        }
+        self.report.reset();
+
+        // The same rounds as search_first, collecting the devices in order
+        let mut slices = self.slices_of(&self.range);
+        let mut best = None;
+        loop {
+            let events = self.launch_first(&slices, Target::Prime)?;
+            let reader = self.candidate_reader(&slices, Target::Prime);
+            let mut candidates = vec![];
+            for (i, event) in events.into_iter().enumerate() {
+                EventComplete::new(event).await?;
+                self.report.finish_device(i);
+                let candidate = reader.read(i)?;
+                if candidate.is_some_and(|candidate| candidate.verified) {
+                    self.cancel.halt_kernels().halt_above(i)?;
+                }
+                candidates.push(candidate);
+            }
+            if !next_round(&mut slices, &candidates, &mut best, Direction::Up) || self.cancel.is_cancelled() {
+                break;
+            }
+        }
+
+        if let Some((_, device)) = best {
+            self.report.mark_found(device);
+        }
+        // The range fits in u64, so any prime found does too
+        Ok(best.map(|(prime, _)| prime as u64))
+    }
+}
+
+// Shared with the event callback: the task to wake, and whether the callback has run
+type WakeSlot = Mutex<(Option<Waker>, bool)>;
+
+// Resolves once an OpenCL event completes
+struct EventComplete {
+    event: Event,
+    slot: Option<Arc<WakeSlot>>,
+}
+
+impl EventComplete {
+    fn new(event: Event) -> Self {
+        EventComplete { event, slot: None }
+    }
+}
+
+impl Future for EventComplete {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        if self.event.is_complete()? {
+            return Poll::Ready(Ok(()));
+        }
+        match &self.slot {
+            Some(slot) => {
+                let mut slot = slot.lock().unwrap();
+                // The callback also runs when the command fails, leaving the event incomplete.
+                // It may also have run since the check above, so look again before failing
+                if slot.1 && !self.event.is_complete()? {
+                    return Poll::Ready(Err(PrimeError::Ocl("the search kernel failed to complete".to_string().into())));
+                }
+                slot.0 = Some(cx.waker().clone());
+            }
+            None => {
+                let slot = Arc::new(Mutex::new((Some(cx.waker().clone()), false)));
+                let user_data = Arc::into_raw(Arc::clone(&slot)) as *mut c_void;
+                // A callback registered after the event completed runs straight away
+                let registered = unsafe {
+                    ocl::core::set_event_callback(&*self.event, CommandExecutionStatus::Complete, Some(wake_on_complete), user_data)
+                };
+                if let Err(e) = registered {
+                    drop(unsafe { Arc::from_raw(user_data as *const WakeSlot) });
+                    return Poll::Ready(Err(PrimeError::Ocl(e.into())));
+                }
+                self.slot = Some(slot);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+extern "C" fn wake_on_complete(_event: cl_event, _status: i32, user_data: *mut c_void) {
+    // Takes back the reference handed over when the callback was registered
+    let slot = unsafe { Arc::from_raw(user_data as *const WakeSlot) };
+    let waker = {
+        let mut slot = slot.lock().unwrap();
+        slot.1 = true;
+        slot.0.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
@@ -0,0 +1,613 @@
+/// OpenCL C source for every search kernel, built once per device.
+pub const KERNEL_SRC: &str = r#"
+    // The search kernels keep the smallest hit with a 64-bit atomic_min, count_primes totals
+    // with a 64-bit atomic add, and search_all_primes counts with a 64-bit increment that more
+    // than 2^32 primes in a slice can't wrap
+    #pragma OPENCL EXTENSION cl_khr_int64_base_atomics : enable
+    #pragma OPENCL EXTENSION cl_khr_int64_extended_atomics : enable
+
+    int is_prime_trial(ulong n) {
+        if (n <= 1) return 0;
+        if (n <= 3) return 1;
+        if (n % 2 == 0 || n % 3 == 0) return 0;
+        // i <= n / i rather than i * i <= n, which wraps once i passes 2^32
+        for (ulong i = 5; i <= n / i; i += 6) {
+            if (n % i == 0 || n % (i + 2) == 0) return 0;
+        }
+        return 1;
+    }
+
+    // Montgomery product a * b * 2^-64 mod n for odd n, where n_inv = n^-1 mod 2^64.
+    // The low words of a * b and m * n cancel, so only the high halves are subtracted.
+    ulong mont_mul(ulong a, ulong b, ulong n, ulong n_inv) {
+        ulong m = a * b * n_inv;
+        ulong hi = mul_hi(a, b);
+        ulong mn = mul_hi(m, n);
+        return hi >= mn ? hi - mn : hi - mn + n;
+    }
+
+    // What every Miller-Rabin round on an odd n > 2 shares, in Montgomery form
+    typedef struct { ulong n, n_inv, one, minus_one, r2, d; int s; } mont_mr;
+
+    mont_mr mont_mr_setup(ulong n) {
+        mont_mr m;
+        m.n = n;
+        m.n_inv = n;
+        for (int i = 0; i < 5; i++) m.n_inv *= 2 - n * m.n_inv;
+
+        // one = 2^64 mod n, r2 = 2^128 mod n (by doubling one 64 times)
+        m.one = (0 - n) % n;
+        m.r2 = m.one;
+        for (int i = 0; i < 64; i++) m.r2 = m.r2 >= n - m.r2 ? m.r2 - (n - m.r2) : m.r2 + m.r2;
+        m.minus_one = n - m.one;
+
+        m.d = n - 1;
+        m.s = 0;
+        while ((m.d & 1) == 0) { m.d >>= 1; m.s++; }
+        return m;
+    }
+
+    // Whether n is a strong probable prime to base a, for 1 < a < n - 1
+    int mont_mr_round(const mont_mr* m, ulong a) {
+        ulong base = mont_mul(a, m->r2, m->n, m->n_inv);
+        ulong x = m->one;
+        for (ulong e = m->d; e > 0; e >>= 1) {
+            if (e & 1) x = mont_mul(x, base, m->n, m->n_inv);
+            base = mont_mul(base, base, m->n, m->n_inv);
+        }
+        if (x == m->one || x == m->minus_one) return 1;
+        for (int r = 1; r < m->s; r++) {
+            x = mont_mul(x, x, m->n, m->n_inv);
+            if (x == m->minus_one) return 1;
+        }
+        return 0;
+    }
+
+    // Deterministic for all 64-bit n with the witnesses 2..37
+    int is_prime_miller_rabin(ulong n) {
+        const ulong witnesses[12] = {2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37};
+        if (n < 2) return 0;
+        for (int i = 0; i < 12; i++) {
+            if (n == witnesses[i]) return 1;
+            if (n % witnesses[i] == 0) return 0;
+        }
+
+        mont_mr m = mont_mr_setup(n);
+        for (int i = 0; i < 12; i++) {
+            if (!mont_mr_round(&m, witnesses[i])) return 0;
+        }
+        return 1;
+    }
+
+    // 128-bit candidates as two ulongs for the Wide128 path
+    typedef struct { ulong hi; ulong lo; } wide;
+
+    wide wide_make(ulong hi, ulong lo) { wide r; r.hi = hi; r.lo = lo; return r; }
+    int wide_lt(wide a, wide b) { return a.hi < b.hi || (a.hi == b.hi && a.lo < b.lo); }
+    int wide_eq(wide a, wide b) { return a.hi == b.hi && a.lo == b.lo; }
+    wide wide_add(wide a, wide b) { wide r = wide_make(a.hi + b.hi, a.lo + b.lo); r.hi += r.lo < a.lo; return r; }
+    wide wide_sub(wide a, wide b) { wide r = wide_make(a.hi - b.hi - (a.lo < b.lo), a.lo - b.lo); return r; }
+
+    // Low 128 bits of a * b
+    wide wide_mul_lo(wide a, wide b) {
+        return wide_make(mul_hi(a.lo, b.lo) + a.hi * b.lo + a.lo * b.hi, a.lo * b.lo);
+    }
+
+    // High 128 bits of a * b, summing the 64-bit partial products column by column
+    wide wide_mul_hi(wide a, wide b) {
+        ulong mid = mul_hi(a.lo, b.lo);
+        ulong carry = 0;
+        mid += a.lo * b.hi; carry += mid < a.lo * b.hi;
+        mid += a.hi * b.lo; carry += mid < a.hi * b.lo;
+
+        wide r = wide_make(mul_hi(a.hi, b.hi), mul_hi(a.lo, b.hi));
+        r.lo += mul_hi(a.hi, b.lo); r.hi += r.lo < mul_hi(a.hi, b.lo);
+        r.lo += a.hi * b.hi; r.hi += r.lo < a.hi * b.hi;
+        r.lo += carry; r.hi += r.lo < carry;
+        return r;
+    }
+
+    // a mod p for p < 2^32
+    ulong wide_mod_small(wide a, ulong p) {
+        return ((a.hi % p) * ((0 - p) % p) + a.lo % p) % p;
+    }
+
+    // a mod n by binary long division; only used once per candidate
+    wide wide_mod(wide a, wide n) {
+        wide r = wide_make(0, 0);
+        for (int i = 127; i >= 0; i--) {
+            ulong overflow = r.hi >> 63;
+            ulong bit = i >= 64 ? (a.hi >> (i - 64)) & 1 : (a.lo >> i) & 1;
+            r = wide_make((r.hi << 1) | (r.lo >> 63), (r.lo << 1) | bit);
+            if (overflow || !wide_lt(r, n)) r = wide_sub(r, n);
+        }
+        return r;
+    }
+
+    // Same Montgomery reduction as mont_mul with R = 2^128
+    wide wide_mont_mul(wide a, wide b, wide n, wide n_inv) {
+        wide m = wide_mul_lo(wide_mul_lo(a, b), n_inv);
+        wide hi = wide_mul_hi(a, b);
+        wide mn = wide_mul_hi(m, n);
+        return wide_lt(hi, mn) ? wide_add(wide_sub(hi, mn), n) : wide_sub(hi, mn);
+    }
+
+    // mont_mr for two-word n
+    typedef struct { wide n, n_inv, one, minus_one, r2, d; int s; } wide_mr;
+
+    wide_mr wide_mr_setup(wide n) {
+        wide_mr m;
+        m.n = n;
+        m.n_inv = n;
+        for (int i = 0; i < 6; i++) m.n_inv = wide_mul_lo(m.n_inv, wide_sub(wide_make(0, 2), wide_mul_lo(n, m.n_inv)));
+
+        // one = 2^128 mod n, r2 = 2^256 mod n (by doubling one 128 times)
+        m.one = wide_mod(wide_sub(wide_make(0, 0), n), n);
+        m.r2 = m.one;
+        for (int i = 0; i < 128; i++) {
+            wide rest = wide_sub(n, m.r2);
+            m.r2 = wide_lt(m.r2, rest) ? wide_add(m.r2, m.r2) : wide_sub(m.r2, rest);
+        }
+        m.minus_one = wide_sub(n, m.one);
+
+        m.d = wide_sub(n, wide_make(0, 1));
+        m.s = 0;
+        while ((m.d.lo & 1) == 0) {
+            m.d = wide_make(m.d.hi >> 1, (m.d.lo >> 1) | (m.d.hi << 63));
+            m.s++;
+        }
+        return m;
+    }
+
+    // Whether n is a strong probable prime to base a, for 1 < a < n - 1
+    int wide_mr_round(const wide_mr* m, ulong a) {
+        wide base = wide_mont_mul(wide_make(0, a), m->r2, m->n, m->n_inv);
+        wide x = m->one;
+        for (wide e = m->d; e.hi != 0 || e.lo != 0; e = wide_make(e.hi >> 1, (e.lo >> 1) | (e.hi << 63))) {
+            if (e.lo & 1) x = wide_mont_mul(x, base, m->n, m->n_inv);
+            base = wide_mont_mul(base, base, m->n, m->n_inv);
+        }
+        if (wide_eq(x, m->one) || wide_eq(x, m->minus_one)) return 1;
+        for (int r = 1; r < m->s; r++) {
+            x = wide_mont_mul(x, x, m->n, m->n_inv);
+            if (wide_eq(x, m->minus_one)) return 1;
+        }
+        return 0;
+    }
+
+    // Miller-Rabin with the witnesses 2..41: deterministic below 3.3 * 10^24, a strong
+    // probable-prime test above that
+    int is_prime_wide(wide n) {
+        const ulong witnesses[13] = {2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41};
+        if (n.hi == 0 && n.lo < 2) return 0;
+        for (int i = 0; i < 13; i++) {
+            if (n.hi == 0 && n.lo == witnesses[i]) return 1;
+            if (wide_mod_small(n, witnesses[i]) == 0) return 0;
+        }
+
+        wide_mr m = wide_mr_setup(n);
+        for (int i = 0; i < 13; i++) {
+            if (!wide_mr_round(&m, witnesses[i])) return 0;
+        }
+        return 1;
+    }
+
+    // Bit i of the mask is set when i is coprime to 2 * 3 * 5 * 7 = 210
+    __constant ulong WHEEL_210[4] = {0x28208a20a08a2802UL, 0x820228a202088288UL, 0x8828228820a08a08UL, 0x200a2UL};
+
+    // Whether n > 47 has one of the first 15 primes as a factor. Two 64-bit remainders cover
+    // 11..47, since 11 * 13 * 17 * 19 * 23 and 29 * ... * 47 both fit in 32 bits.
+    int has_small_factor(ulong n) {
+        uint w = n % 210;
+        if (!((WHEEL_210[w >> 6] >> (w & 63)) & 1)) return 1;
+        uint a = n % 1062347;
+        if (a % 11 == 0 || a % 13 == 0 || a % 17 == 0 || a % 19 == 0 || a % 23 == 0) return 1;
+        uint b = n % 2756205443UL;
+        return b % 29 == 0 || b % 31 == 0 || b % 37 == 0 || b % 41 == 0 || b % 43 == 0 || b % 47 == 0;
+    }
+
+    // The Jacobi symbol (a / m) for odd m
+    int jacobi(ulong a, ulong m) {
+        int t = 1;
+        a %= m;
+        while (a != 0) {
+            while ((a & 1) == 0) {
+                a >>= 1;
+                if ((m & 7) == 3 || (m & 7) == 5) t = -t;
+            }
+            ulong r = a; a = m; m = r;
+            if ((a & 3) == 3 && (m & 3) == 3) t = -t;
+            a %= m;
+        }
+        return m == 1 ? t : 0;
+    }
+
+    // Whether n is a perfect square, finding its root bit by bit
+    int wide_is_square(wide n) {
+        ulong root = 0;
+        for (int bit = 63; bit >= 0; bit--) {
+            ulong next = root | (1UL << bit);
+            if (!wide_lt(n, wide_make(mul_hi(next, next), next * next))) root = next;
+        }
+        return wide_eq(n, wide_make(mul_hi(root, root), root * root));
+    }
+
+    // Selfridge's D for the strong Lucas test: the first of 5, -7, 9, -11, ... with
+    // (D / n) = -1, returned as |D|, D being negative when |D| = 3 mod 4. Every D is 1 mod 4,
+    // so (D / n) = (n mod |D| / |D|). Returns 0 when n is composite: a square, which has no
+    // such D (checked once a few have failed), or sharing a factor with a smaller |D|.
+    ulong selfridge_d(wide n) {
+        for (ulong d = 5; ; d += 2) {
+            if (d == 21 && wide_is_square(n)) return 0;
+            int j = jacobi(wide_mod_small(n, d), d);
+            if (j == -1) return d;
+            if (j == 0 && wide_lt(wide_make(0, d), n)) return 0;
+        }
+    }
+
+    // a + b, a - b and a / 2 mod odd n, for a, b < n
+    ulong add_mod(ulong a, ulong b, ulong n) { return a >= n - b ? a - (n - b) : a + b; }
+    ulong sub_mod(ulong a, ulong b, ulong n) { return a >= b ? a - b : a + (n - b); }
+    ulong half_mod(ulong a, ulong n) { return (a & 1) ? (a >> 1) + (n >> 1) + 1 : a >> 1; }
+
+    // Strong Lucas probable-prime test with P = 1 and Q = (1 - D) / 4 for Selfridge's D, in
+    // Montgomery form: with n + 1 = k * 2^s and k odd, n passes when U_k = 0 or
+    // V_(k * 2^r) = 0 for some r < s. U, V and Q^j are doubled and stepped along k's bits.
+    int mont_strong_lucas(const mont_mr* m) {
+        ulong n = m->n;
+        ulong d = selfridge_d(wide_make(0, n));
+        if (d == 0) return 0;
+        int negative = (d & 3) == 3;
+        ulong q = negative ? (d + 1) / 4 : (d - 1) / 4;
+        ulong dm = d % n, qm = q % n;
+        dm = mont_mul(negative && dm ? n - dm : dm, m->r2, n, m->n_inv);
+        qm = mont_mul(!negative && qm ? n - qm : qm, m->r2, n, m->n_inv);
+
+        // n + 1 can't wrap, since 2^64 - 1 has the factor 3
+        ulong k = n + 1;
+        int s = 0;
+        while ((k & 1) == 0) { k >>= 1; s++; }
+        ulong u = m->one, v = m->one, qk = qm;
+        for (int bit = 62 - (int)clz(k); bit >= 0; bit--) {
+            // U_2j = U_j V_j, V_2j = V_j^2 - 2Q^j
+            u = mont_mul(u, v, n, m->n_inv);
+            v = sub_mod(mont_mul(v, v, n, m->n_inv), add_mod(qk, qk, n), n);
+            qk = mont_mul(qk, qk, n, m->n_inv);
+            if ((k >> bit) & 1) {
+                // U_(j+1) = (U_j + V_j) / 2, V_(j+1) = (D U_j + V_j) / 2
+                ulong next_u = half_mod(add_mod(u, v, n), n);
+                v = half_mod(add_mod(mont_mul(dm, u, n, m->n_inv), v, n), n);
+                u = next_u;
+                qk = mont_mul(qk, qm, n, m->n_inv);
+            }
+        }
+        if (u == 0 || v == 0) return 1;
+        for (int r = 1; r < s; r++) {
+            v = sub_mod(mont_mul(v, v, n, m->n_inv), add_mod(qk, qk, n), n);
+            qk = mont_mul(qk, qk, n, m->n_inv);
+            if (v == 0) return 1;
+        }
+        return 0;
+    }
+
+    // Baillie-PSW: a strong probable-prime test to base 2, then a strong Lucas test. No
+    // composite below 2^64 passes both.
+    int is_prime_bpsw(ulong n) {
+        if (n <= 47) return is_prime_trial(n);
+        if (has_small_factor(n)) return 0;
+        mont_mr m = mont_mr_setup(n);
+        return mont_mr_round(&m, 2) && mont_strong_lucas(&m);
+    }
+
+    wide wide_add_mod(wide a, wide b, wide n) { wide rest = wide_sub(n, b); return wide_lt(a, rest) ? wide_add(a, b) : wide_sub(a, rest); }
+    wide wide_sub_mod(wide a, wide b, wide n) { return wide_lt(a, b) ? wide_add(a, wide_sub(n, b)) : wide_sub(a, b); }
+    wide wide_half_mod(wide a, wide n) {
+        wide half = wide_make(a.hi >> 1, (a.lo >> 1) | (a.hi << 63));
+        return (a.lo & 1) ? wide_add(wide_add(half, wide_make(n.hi >> 1, (n.lo >> 1) | (n.hi << 63))), wide_make(0, 1)) : half;
+    }
+
+    // mont_strong_lucas for n past 2^64, which is larger than any |D| or |Q|
+    int wide_strong_lucas(const wide_mr* m) {
+        wide n = m->n;
+        ulong d = selfridge_d(n);
+        if (d == 0) return 0;
+        int negative = (d & 3) == 3;
+        ulong q = negative ? (d + 1) / 4 : (d - 1) / 4;
+        wide dm = wide_mont_mul(negative ? wide_sub(n, wide_make(0, d)) : wide_make(0, d), m->r2, n, m->n_inv);
+        wide qm = wide_mont_mul(negative ? wide_make(0, q) : wide_sub(n, wide_make(0, q)), m->r2, n, m->n_inv);
+
+        // n + 1 can't wrap, since 2^128 - 1 has the factor 3
+        wide k = wide_add(n, wide_make(0, 1));
+        int s = 0;
+        while ((k.lo & 1) == 0) { k = wide_make(k.hi >> 1, (k.lo >> 1) | (k.hi << 63)); s++; }
+        wide u = m->one, v = m->one, qk = qm;
+        int top = k.hi ? 127 - (int)clz(k.hi) : 63 - (int)clz(k.lo);
+        for (int bit = top - 1; bit >= 0; bit--) {
+            u = wide_mont_mul(u, v, n, m->n_inv);
+            v = wide_sub_mod(wide_mont_mul(v, v, n, m->n_inv), wide_add_mod(qk, qk, n), n);
+            qk = wide_mont_mul(qk, qk, n, m->n_inv);
+            if ((bit >= 64 ? k.hi >> (bit - 64) : k.lo >> bit) & 1) {
+                wide next_u = wide_half_mod(wide_add_mod(u, v, n), n);
+                v = wide_half_mod(wide_add_mod(wide_mont_mul(dm, u, n, m->n_inv), v, n), n);
+                u = next_u;
+                qk = wide_mont_mul(qk, qm, n, m->n_inv);
+            }
+        }
+        wide zero = wide_make(0, 0);
+        if (wide_eq(u, zero) || wide_eq(v, zero)) return 1;
+        for (int r = 1; r < s; r++) {
+            v = wide_sub_mod(wide_mont_mul(v, v, n, m->n_inv), wide_add_mod(qk, qk, n), n);
+            qk = wide_mont_mul(qk, qk, n, m->n_inv);
+            if (wide_eq(v, zero)) return 1;
+        }
+        return 0;
+    }
+
+    // Baillie-PSW in two words, past 2^64 a strong probable-prime test with no known
+    // counterexample
+    int is_prime_bpsw_wide(wide n) {
+        const ulong small_primes[13] = {2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41};
+        if (n.hi == 0) return is_prime_bpsw(n.lo);
+        for (int i = 0; i < 13; i++) {
+            if (wide_mod_small(n, small_primes[i]) == 0) return 0;
+        }
+        wide_mr m = wide_mr_setup(n);
+        return wide_mr_round(&m, 2) && wide_strong_lucas(&m);
+    }
+
+    // Algorithm ids match Algorithm::kernel_id on the host. Candidates with a small factor are
+    // rejected before the full test unless the host sets NO_PRECHECK, which only the benchmark
+    // does to measure what the precheck saves.
+    #define NO_PRECHECK 0x100
+    int is_prime(ulong n, uint algorithm) {
+        if (!(algorithm & NO_PRECHECK) && n > 47 && has_small_factor(n)) return 0;
+        switch (algorithm & ~NO_PRECHECK) {
+            case 1: return is_prime_miller_rabin(n);
+            case 2: return is_prime_wide(wide_make(0, n));
+            case 3: return is_prime_bpsw(n);
+            default: return is_prime_trial(n);
+        }
+    }
+
+    // Spins while the host has paused the device for cooling; returns nonzero once cancelled.
+    int wait_if_paused(volatile __global const int* pause, volatile __global const int* cancel) {
+        while (*pause && !*cancel) {}
+        return *cancel;
+    }
+
+    __kernel void search_all_primes(ulong start, ulong end, uint algorithm, __global ulong* primes, volatile __global ulong* count, uint capacity, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        // start + tid and n + num_threads could wrap around for slices ending near 2^64
+        if (end <= start || end - start <= tid) return;
+        for (ulong n = start + tid; ; n += num_threads) {
+            if (wait_if_paused(pause, cancel)) return;
+            status[tid] = n;
+            if (is_prime(n, algorithm)) {
+                // Keep counting past the capacity so the host can report how many were missed
+                ulong idx = atom_inc(count);
+                if (idx < capacity) primes[idx] = n;
+            }
+            if (end - n <= num_threads) return;
+        }
+    }
+
+    // Each thread counts its own primes and adds them to the total once, so the counter sees
+    // one atomic per thread instead of one per prime
+    __kernel void count_primes(ulong start, ulong end, uint algorithm, volatile __global ulong* count, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        ulong found = 0;
+        if (end <= start || end - start <= tid) return;
+        for (ulong n = start + tid; ; n += num_threads) {
+            if (wait_if_paused(pause, cancel)) break;
+            status[tid] = n;
+            if (is_prime(n, algorithm)) found++;
+            if (end - n <= num_threads) break;
+        }
+        atom_add(count, found);
+    }
+
+    // Marks the multiples of the base primes in [seg_start, seg_start + seg_len), one base
+    // prime per work-item; composite[i] refers to seg_start + i. Work-items may store to the
+    // same byte, but they all store 1.
+    __kernel void sieve_segment(ulong seg_start, ulong seg_len, __global const uint* base_primes, uint num_base, __global uchar* composite, volatile __global const int* cancel) {
+        ulong seg_end = seg_start + seg_len;
+        for (ulong i = get_global_id(0); i < num_base; i += get_global_size(0)) {
+            if (*cancel) return;
+            ulong p = base_primes[i];
+            // Start at p^2, or at the first multiple in the segment if that is later
+            ulong rem = seg_start % p;
+            ulong skip = rem ? p - rem : 0;
+            if (skip >= seg_len) continue;
+            ulong m = max(p * p, seg_start + skip);
+            // Stepping past seg_end could wrap around for segments ending near 2^64
+            while (m < seg_end) {
+                composite[m - seg_start] = 1;
+                if (seg_end - m <= p) break;
+                m += p;
+            }
+        }
+    }
+
+    // Leaves the smallest prime in [start, end) in result, which the host initializes to
+    // ULONG_MAX for none. Threads keep going until they pass the best prime found so far.
+    __kernel void search_for_large_prime(ulong start, ulong end, uint algorithm, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        // start + tid and n + num_threads could wrap around for slices ending near 2^64
+        if (end <= start || end - start <= tid) return;
+        for (ulong n = start + tid; ; n += num_threads) {
+            if (n >= *result || wait_if_paused(pause, cancel)) return;
+            status[tid] = n;
+            if (is_prime(n, algorithm)) {
+                atom_min(result, n);
+                return;
+            }
+            if (end - n <= num_threads) return;
+        }
+    }
+
+    // Like search_for_large_prime over start, start + step, start + 2 * step and so on below
+    // end, where the host passes the first term of the progression in the slice as start.
+    // Thread tid tests terms tid, tid + num_threads and so on, counted by index so stepping
+    // can't wrap around near 2^64.
+    __kernel void search_progression(ulong start, ulong end, uint algorithm, ulong step, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        if (start >= end) return;
+        ulong last = (end - 1 - start) / step;
+        for (ulong k = tid; k <= last; k += num_threads) {
+            ulong n = start + k * step;
+            if (n >= *result || wait_if_paused(pause, cancel)) return;
+            status[tid] = n;
+            if (is_prime(n, algorithm)) {
+                atom_min(result, n);
+                return;
+            }
+            if (last - k < num_threads) return;
+        }
+    }
+
+    // Like search_for_large_prime from the top of [start, end) down, for the largest prime.
+    // The result starts at 0, below every prime, and only rises.
+    __kernel void search_for_largest_prime(ulong start, ulong end, uint algorithm, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        if (end <= start || end - start <= tid) return;
+        for (ulong n = end - 1 - tid; ; n -= num_threads) {
+            if (n <= *result || wait_if_paused(pause, cancel)) return;
+            status[tid] = n;
+            if (is_prime(n, algorithm)) {
+                atom_max(result, n);
+                return;
+            }
+            // Stop before n - num_threads can pass start or wrap around
+            if (n - start < num_threads) return;
+        }
+    }
+
+    // Like search_for_large_prime for the smallest n with n and n + 2 both prime and n + 2 < end.
+    __kernel void search_twin_primes(ulong start, ulong end, uint algorithm, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        if (end <= start || end - start <= 2 || end - 2 - start <= tid) return;
+        for (ulong n = start + tid; ; n += num_threads) {
+            if (n >= *result || wait_if_paused(pause, cancel)) return;
+            status[tid] = n;
+            if (is_prime(n, algorithm) && is_prime(n + 2, algorithm)) {
+                atom_min(result, n);
+                return;
+            }
+            if (end - 2 - n <= num_threads) return;
+        }
+    }
+
+    // Searches start + [0, len) in two-word arithmetic. The smallest prime is reported as its
+    // offset so the result stays a single word, ULONG_MAX meaning none since offsets are below
+    // len; status slots only hold the low word.
+    __kernel void search_for_large_prime_wide(ulong start_hi, ulong start_lo, ulong len, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        wide start = wide_make(start_hi, start_lo);
+        for (ulong offset = tid; offset < len; offset += num_threads) {
+            if (offset >= *result || wait_if_paused(pause, cancel)) return;
+            wide n = wide_add(start, wide_make(0, offset));
+            status[tid] = n.lo;
+            if (is_prime_wide(n)) {
+                atom_min(result, offset);
+                return;
+            }
+            // Stop before offset + num_threads can wrap around
+            if (len - offset <= num_threads) return;
+        }
+    }
+
+    // Like search_for_large_prime_wide, testing with is_prime_bpsw_wide
+    __kernel void search_for_large_prime_bpsw_wide(ulong start_hi, ulong start_lo, ulong len, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        wide start = wide_make(start_hi, start_lo);
+        for (ulong offset = tid; offset < len; offset += num_threads) {
+            if (offset >= *result || wait_if_paused(pause, cancel)) return;
+            wide n = wide_add(start, wide_make(0, offset));
+            status[tid] = n.lo;
+            if (is_prime_bpsw_wide(n)) {
+                atom_min(result, offset);
+                return;
+            }
+            if (len - offset <= num_threads) return;
+        }
+    }
+
+    // Miller-Rabin with `rounds` of the host's random witnesses for n past 2^64, where every
+    // 64-bit witness is below n - 1; smaller n get the deterministic 64-bit test
+    int is_probable_prime_wide(wide n, __global const ulong* witnesses, uint rounds) {
+        const ulong small_primes[13] = {2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41};
+        if (n.hi == 0) return is_prime_miller_rabin(n.lo);
+        for (int i = 0; i < 13; i++) {
+            if (wide_mod_small(n, small_primes[i]) == 0) return 0;
+        }
+        wide_mr m = wide_mr_setup(n);
+        for (uint i = 0; i < rounds; i++) {
+            if (!wide_mr_round(&m, witnesses[i])) return 0;
+        }
+        return 1;
+    }
+
+    // Like search_for_large_prime_wide, testing with is_probable_prime_wide
+    __kernel void search_for_large_prime_probabilistic(ulong start_hi, ulong start_lo, ulong len, __global const ulong* witnesses, uint rounds, volatile __global ulong* result, __global ulong* status, volatile __global const int* cancel, volatile __global const int* pause) {
+        ulong tid = get_global_id(0);
+        ulong num_threads = get_global_size(0);
+        wide start = wide_make(start_hi, start_lo);
+        for (ulong offset = tid; offset < len; offset += num_threads) {
+            if (offset >= *result || wait_if_paused(pause, cancel)) return;
+            wide n = wide_add(start, wide_make(0, offset));
+            status[tid] = n.lo;
+            if (is_probable_prime_wide(n, witnesses, rounds)) {
+                atom_min(result, offset);
+                return;
+            }
+            if (len - offset <= num_threads) return;
+        }
+    }
+    // 2^p - 1 in two words, for 1 <= p <= 127
+    wide wide_mersenne(uint p) {
+        return p < 64 ? wide_make(0, (1UL << p) - 1) : wide_make((1UL << (p - 64)) - 1, ULONG_MAX);
+    }
+
+    // (hi * 2^128 + lo) mod 2^p - 1 for products below 2^2p: since 2^p = 1 (mod m), the bits
+    // above p fold back onto the low p bits
+    wide mersenne_mod(wide hi, wide lo, uint p, wide m) {
+        wide low = wide_make(lo.hi & m.hi, lo.lo & m.lo);
+        wide top = p < 64
+            ? wide_make((lo.hi >> p) | (hi.lo << (64 - p)), (lo.lo >> p) | (lo.hi << (64 - p)))
+            : p == 64
+                ? wide_make(hi.lo, lo.hi)
+                : wide_make((hi.lo >> (p - 64)) | (hi.hi << (128 - p)), (lo.hi >> (p - 64)) | (hi.lo << (128 - p)));
+        wide r = wide_add(low, top);
+        return wide_lt(r, m) ? r : wide_sub(r, m);
+    }
+
+    // Lucas-Lehmer: 2^p - 1 is prime exactly when s(p - 2) = 0 with s(0) = 4 and
+    // s(i + 1) = s(i)^2 - 2 mod 2^p - 1. Two words bound p to 127.
+    int lucas_lehmer(uint p) {
+        if (p < 2) return 0;
+        if (p == 2) return 1;
+        wide m = wide_mersenne(p);
+        wide two = wide_make(0, 2);
+        wide s = wide_make(0, 4);
+        for (uint i = 0; i < p - 2; i++) {
+            s = mersenne_mod(wide_mul_hi(s, s), wide_mul_lo(s, s), p, m);
+            s = wide_lt(s, two) ? wide_sub(wide_add(s, m), two) : wide_sub(s, two);
+        }
+        return s.hi == 0 && s.lo == 0;
+    }
+
+    // results[i] is 1 when 2^exponents[i] - 1 is prime
+    __kernel void test_mersenne(__global const uint* exponents, uint count, __global uchar* results) {
+        for (uint i = get_global_id(0); i < count; i += get_global_size(0)) {
+            results[i] = lucas_lehmer(exponents[i]);
+        }
+    }
+"#;
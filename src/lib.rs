@@ -0,0 +1,2319 @@
+extern crate ocl;
+extern crate nvml_wrapper as nvml;
+#[macro_use]
+extern crate log;
+extern crate num_bigint;
+extern crate num_traits;
+extern crate prometheus;
+extern crate tiny_http;
+
+use ocl::{ProQue, Buffer, MemFlags, Platform, Device, Context, Event, Queue, Program, SpatialDims, CommandQueueProperties};
+use ocl::enums::{DeviceInfo as DeviceInfoKind, DeviceInfoResult, KernelInfo, KernelInfoResult, KernelWorkGroupInfo, KernelWorkGroupInfoResult, ProgramBuildInfo, ProgramBuildInfoResult, ProgramInfo, ProgramInfoResult, ProfilingInfo, ProfilingInfoResult};
+use nvml::Nvml;
+use cancel::KernelHalt;
+use monitor::NoMonitor;
+use pci::PciAddress;
+use checkpoint::CheckpointWriter;
+use report::ReportTracker;
+use completion::Completion;
+use status::{PrimeHook, Reading, StatusRead, StatusSink, StatusUpdate};
+use watchdog::{Finished, Watchdog};
+use throttle::PowerGovernor;
+use std::{ffi::CString, fmt, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}, ops::Range, path::PathBuf, sync::{Arc, Mutex, OnceLock, mpsc}};
+
+pub mod backend;
+pub mod bench;
+pub mod builder;
+pub mod cache;
+pub mod cancel;
+pub mod checkpoint;
+pub mod clocks;
+mod completion;
+pub mod cpu;
+pub mod distributed;
+mod dynamic;
+pub mod error;
+pub mod eta;
+mod future;
+pub mod kernel;
+pub mod metrics;
+pub mod monitor;
+mod order;
+pub mod partition;
+pub mod primality;
+pub mod progression;
+mod pci;
+pub mod report;
+pub mod retry;
+mod self_check;
+mod sieve;
+mod status;
+pub mod stream;
+pub mod telemetry;
+pub mod verify;
+mod watchdog;
+pub mod throttle;
+
+pub use backend::Backend;
+pub use bench::{BenchResult, TuneResult};
+pub use builder::PrimeSearcherBuilder;
+pub use cache::KernelCache;
+pub use cancel::CancelHandle;
+pub use checkpoint::{Checkpoint, DeviceProgress, LastPrime};
+pub use clocks::{ClockSettings, LockedClocks};
+pub use cpu::CpuSearcher;
+pub use distributed::{Coordinator, run_worker};
+pub use error::PrimeError;
+pub use eta::Eta;
+pub use kernel::KERNEL_SRC;
+pub use metrics::{Metrics, MetricsServer};
+pub use monitor::Monitor;
+pub use partition::{PartitionStrategy, partition_chunks, partition_pieces, partition_range, partition_weighted};
+pub use progression::Progression;
+pub use report::{DeviceReport, SearchReport};
+pub use retry::RetryPolicy;
+pub use sieve::base_primes_up_to;
+pub use stream::{StreamFormat, read_primes_bin, write_primes_bin};
+pub use telemetry::TelemetryLog;
+pub use throttle::{PowerBudget, PowerStep, ThermalLimit};
+
+pub type Result<T> = std::result::Result<T, PrimeError>;
+
+const MAX_THREADS: usize = 1024;
+
+// Work-groups resident per compute unit that ThreadCount::Auto aims for, so memory latency
+// in one group can be hidden behind the others
+const AUTO_OCCUPANCY: usize = 8;
+
+const DEFAULT_SEGMENT_SIZE: usize = 32 << 20;
+
+// Most numbers find_gap, find_all_streaming and find_n list the primes of at a time, bounding
+// the memory a long scan needs
+const LIST_WINDOW: u64 = 1 << 24;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MONITOR_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Number of kernel threads (global work size) launched on each device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadCount {
+    /// `max_compute_units * preferred work-group size multiple * 8`, queried per device.
+    Auto,
+    /// The same count on every device.
+    Explicit(usize),
+}
+
+impl Default for ThreadCount {
+    fn default() -> Self {
+        ThreadCount::Explicit(MAX_THREADS)
+    }
+}
+
+/// How many threads' statuses each device's progress shows, see
+/// [`PrimeSearcher::with_status_threads`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusThreads {
+    All,
+    /// The device's first threads, none with 0.
+    First(usize),
+}
+
+impl Default for StatusThreads {
+    fn default() -> Self {
+        StatusThreads::First(10)
+    }
+}
+
+impl StatusThreads {
+    /// The statuses to show out of `statuses`.
+    pub fn of<'a>(&self, statuses: &'a [u64]) -> &'a [u64] {
+        match *self {
+            StatusThreads::All => statuses,
+            StatusThreads::First(count) => &statuses[..count.min(statuses.len())],
+        }
+    }
+}
+
+/// Which end of the range [`find_first`](PrimeSearcher::find_first) searches from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// From the start up, for the smallest prime.
+    #[default]
+    Up,
+    /// From the end down, for the largest prime.
+    Down,
+}
+
+impl Direction {
+    // What the search kernel's result holds until it finds something: the kernels keep the
+    // smallest hit with atom_min, or searching down the largest with atom_max
+    fn no_result(self) -> u64 {
+        match self {
+            Direction::Up => u64::MAX,
+            Direction::Down => 0,
+        }
+    }
+}
+
+/// Primality test run by the kernel on each candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// Trial division by 6k ± 1 up to the square root; simple but slow for large candidates.
+    #[default]
+    TrialDivision,
+    /// Deterministic Miller-Rabin with the witnesses 2..37, valid for every `u64`.
+    MillerRabin,
+    /// Miller-Rabin over two-word (128-bit) arithmetic, used by [`PrimeSearcher::new_u128`].
+    ///
+    /// Every modular product costs several 64-bit multiplies plus a reduction, so expect it
+    /// to be several times slower than `MillerRabin` at the same magnitude. It is
+    /// deterministic below 3.3 * 10^24 and a strong probable-prime test above that.
+    Wide128,
+    /// Miller-Rabin with `rounds` random witnesses, for searchers created with
+    /// [`PrimeSearcher::new_u128`] whose candidates go past `u64`, where no small deterministic
+    /// witness set is known.
+    ///
+    /// A composite survives each round with probability at most 1/4, so a reported prime is
+    /// composite with probability at most 4^-rounds (see
+    /// [`error_probability`](Self::error_probability)). The witnesses come from the searcher's
+    /// [seed](PrimeSearcher::with_seed); candidates below 2^64 still get the deterministic test.
+    MillerRabinProbabilistic { rounds: u32 },
+    /// Baillie-PSW: one Miller-Rabin round to base 2, then a strong Lucas test.
+    ///
+    /// No composite is known to pass both, and none below 2^64 does, so it is deterministic
+    /// for `u64` candidates and usually faster than the twelve rounds of `MillerRabin`. In
+    /// searchers created with [`PrimeSearcher::new_u128`] it is the practical test past
+    /// 2^64, where no small deterministic witness set is known.
+    Bpsw,
+    /// Segmented sieve of Eratosthenes, only available through [`PrimeSearcher::find_all`].
+    ///
+    /// Each device sieves its slice one segment at a time with base primes up to the square
+    /// root of the range end, generated on the CPU. See
+    /// [`with_segment_size`](PrimeSearcher::with_segment_size) for the memory tradeoff.
+    SegmentedSieve,
+    /// Lucas-Lehmer test of Mersenne numbers 2^p - 1, only available through
+    /// [`PrimeSearcher::test_mersenne`], which runs it whatever algorithm is selected.
+    LucasLehmer,
+}
+
+impl Algorithm {
+    // The per-candidate kernels never see SegmentedSieve; searches reject it beforehand
+    fn kernel_id(self) -> Result<u32> {
+        match self {
+            Algorithm::TrialDivision => Ok(0),
+            Algorithm::MillerRabin | Algorithm::SegmentedSieve => Ok(1),
+            Algorithm::Wide128 => Ok(2),
+            Algorithm::Bpsw => Ok(3),
+            Algorithm::LucasLehmer => {
+                Err(PrimeError::Unsupported("the Lucas-Lehmer test only checks Mersenne numbers through test_mersenne".into()))
+            }
+            Algorithm::MillerRabinProbabilistic { .. } => Err(PrimeError::Unsupported(
+                "probabilistic Miller-Rabin only runs in searchers created with new_u128; u64 ranges have the deterministic MillerRabin".into(),
+            )),
+        }
+    }
+
+    /// The most a prime this algorithm reports can be wrong by, 4^-rounds for
+    /// [`MillerRabinProbabilistic`](Self::MillerRabinProbabilistic), or `None` for the
+    /// deterministic algorithms.
+    pub fn error_probability(self) -> Option<f64> {
+        match self {
+            Algorithm::MillerRabinProbabilistic { rounds } => Some(0.25f64.powi(rounds.min(i32::MAX as u32) as i32)),
+            _ => None,
+        }
+    }
+}
+
+/// Largest exponent [`PrimeSearcher::test_mersenne`] accepts: the kernel squares modulo
+/// 2^p - 1 in two 64-bit words.
+pub const MAX_MERSENNE_EXPONENT: u32 = 127;
+
+/// Options that have to be fixed when a [`PrimeSearcher`] is created because they affect how
+/// its devices are set up.
+#[derive(Debug, Clone, Default)]
+pub struct SearcherConfig {
+    /// Reuse compiled kernels from this cache, adding to it for devices it has none for
+    pub kernel_cache: Option<KernelCache>,
+    /// Devices to search on; every device by default
+    pub devices: DeviceFilter,
+    /// OpenCL C to build instead of [`KERNEL_SRC`]. It must define `search_for_large_prime`
+    /// with the same parameters as the built-in one.
+    pub kernel_source: Option<String>,
+    /// Create the command queues with profiling enabled so each kernel's run time is measured
+    /// on the device and added up in [`DeviceReport::kernel_time`]. Off by default, as some
+    /// drivers run profiled queues slower.
+    pub queue_profiling: bool,
+    /// Create the compute queues with out-of-order execution, so the driver may overlap the
+    /// buffer transfers of a search with its kernels. Dependencies are then ordered with
+    /// events: each kernel waits for the fills before it and each read of its results for
+    /// the kernel. Devices that don't support it keep in-order queues, as does everyone by
+    /// default.
+    pub out_of_order: bool,
+    /// Allocate the status buffers in host-accessible memory and read them by mapping them
+    /// rather than copying them out on every poll. With memory the driver pins or shares with
+    /// the device, a mapping involves no copy. Off by default, as not every driver handles such
+    /// buffers well.
+    pub mapped_status: bool,
+}
+
+/// Selects devices by their position in the enumeration across all platforms and by name.
+/// A device must pass both checks; an empty filter selects every device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    pub indices: Vec<usize>,
+    /// Case-insensitive substring of the device name
+    pub name: Option<String>,
+}
+
+impl DeviceFilter {
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        (self.indices.is_empty() || self.indices.contains(&index))
+            && self.name.as_ref().is_none_or(|part| name.to_lowercase().contains(&part.to_lowercase()))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.indices.is_empty() && self.name.is_none()
+    }
+}
+
+/// Description of one OpenCL device taking part in a search.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    /// Position in the enumeration across all platforms, as matched by [`DeviceFilter`].
+    /// Devices are numbered by PCI address, then those without one by name, so the numbers
+    /// don't depend on the order the platforms and drivers happen to list them in.
+    pub index: usize,
+    pub platform: String,
+    pub name: String,
+    pub vendor: String,
+    pub compute_units: u32,
+    /// Global memory in bytes
+    pub global_memory: u64,
+    /// Most work-items in one work group
+    pub max_work_group_size: usize,
+    /// The version string the device reports, like `OpenCL 3.0 CUDA`
+    pub opencl_version: String,
+    /// Why the search kernels can't run on the device, which is then never selected; see
+    /// [`int64_incompatibility`]
+    pub unsupported: Option<String>,
+    /// Where the device sits on the PCI bus as domain:bus:device in hex, like `0000:03:00`.
+    /// Only NVIDIA and AMD devices report it.
+    pub pci_address: Option<String>,
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} on {} (device {})", self.name, self.platform, self.index)
+    }
+}
+
+/// The most recent monitoring reading for a device.
+#[derive(Debug, Clone, Copy)]
+pub struct GpuStats {
+    /// GPU utilization in percent, if the driver reports it
+    pub utilization: Option<u32>,
+    /// GPU temperature in °C
+    pub temperature: u32,
+    /// Power draw in milliwatts, if the card reports it
+    pub power_usage: Option<u32>,
+    /// Graphics clock in MHz, if the card reports it
+    pub graphics_clock: Option<u32>,
+    /// Memory clock in MHz, if the card reports it
+    pub memory_clock: Option<u32>,
+}
+
+impl fmt::Display for GpuStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(utilization) = self.utilization {
+            write!(f, "Utilization: {}%, ", utilization)?;
+        }
+        write!(f, "Temperature: {}°C", self.temperature)?;
+        if let Some(power) = self.power_usage {
+            write!(f, ", Power: {:.1} W", power as f64 / 1000.0)?;
+        }
+        if let Some(clock) = self.graphics_clock {
+            write!(f, ", Graphics clock: {} MHz", clock)?;
+        }
+        if let Some(clock) = self.memory_clock {
+            write!(f, ", Memory clock: {} MHz", clock)?;
+        }
+        Ok(())
+    }
+}
+
+// Launches a device's next chunk in a dynamically partitioned search, once its last one is done
+type Relaunch = Arc<dyn Fn(usize) -> Result<Option<(Event, Range<u64>)>> + Send + Sync>;
+
+// What search_first looks for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Prime,
+    TwinPrime,
+    // A prime among the terms of the progression alone
+    Progression(Progression),
+}
+
+// A value reported by one device's search kernel. `next` is where the device resumes if the
+// value fails verification: the new start of its slice just past it, or searching down, the
+// new end of its slice at it.
+#[derive(Clone, Copy)]
+struct Candidate {
+    value: u128,
+    verified: bool,
+    next: u64,
+}
+
+// Records the verified candidate of the device searched first in `direction` in `best` and
+// moves each slice past its unverified one, leaving empty the slices that are done or can no
+// longer beat `best`. Returns whether any slice is left to search.
+fn next_round(slices: &mut [Range<u64>], candidates: &[Option<Candidate>], best: &mut Option<(u128, usize)>, direction: Direction) -> bool {
+    // Slices ascend with the device index
+    let beats = |i: usize, device: usize| match direction {
+        Direction::Up => i < device,
+        Direction::Down => i > device,
+    };
+    for (i, candidate) in candidates.iter().enumerate() {
+        if let Some(candidate) = candidate.filter(|c| c.verified) {
+            if best.is_none_or(|(_, device)| beats(i, device)) {
+                *best = Some((candidate.value, i));
+            }
+        }
+    }
+
+    let mut pending = false;
+    for (i, (slice, candidate)) in slices.iter_mut().zip(candidates).enumerate() {
+        let next = match candidate {
+            Some(candidate) if !candidate.verified && best.is_none_or(|(_, device)| beats(i, device)) => {
+                pending = true;
+                Some(candidate.next)
+            }
+            _ => None,
+        };
+        match direction {
+            Direction::Up => slice.start = next.unwrap_or(slice.end),
+            Direction::Down => slice.end = next.unwrap_or(slice.start),
+        }
+    }
+    pending
+}
+
+// Reads and verifies what each device's search kernel reported
+#[derive(Clone)]
+struct CandidateReader {
+    target: Target,
+    direction: Direction,
+    retry: RetryPolicy,
+    result_buffers: Vec<Arc<Buffer<u64>>>,
+    slices: Vec<Range<u64>>,
+    wide_start: Option<u128>,
+    verify: bool,
+    print_status: bool,
+    devices: Vec<DeviceInfo>,
+    // Set for probabilistic tests, whose primes may be wrong by this much
+    error_probability: Option<f64>,
+    prime_hook: PrimeHook,
+}
+
+impl CandidateReader {
+    fn read(&self, i: usize) -> Result<Option<Candidate>> {
+        let mut result = vec![0u64; 1];
+        self.retry.run(i, "result read", || Ok(self.result_buffers[i].read(&mut result).enq()?))?;
+        if result[0] == self.direction.no_result() {
+            return Ok(None);
+        }
+
+        // A result the kernel can't have found in its slice means it is broken, not that it
+        // found a prime somewhere else
+        let slice = &self.slices[i];
+        let inside = match self.wide_start {
+            None => slice.contains(&result[0]),
+            Some(_) => result[0] < slice.end - slice.start,
+        };
+        if !inside {
+            let (base, value) = match self.wide_start {
+                None => (0, result[0] as u128),
+                Some(base) => (base, base + slice.start as u128 + result[0] as u128),
+            };
+            return Err(PrimeError::ResultOutsideSlice {
+                device: format!("GPU {} ({})", i, self.devices[i]),
+                value,
+                start: base + slice.start as u128,
+                end: base + slice.end as u128,
+            });
+        }
+
+        // The wide kernel reports the offset into its slice
+        let (value, position) = match self.wide_start {
+            None => (result[0] as u128, result[0]),
+            Some(base) => {
+                let offset = self.slices[i].start + result[0];
+                (base + offset as u128, offset)
+            }
+        };
+        let verified = !self.verify || match self.target {
+            Target::Prime | Target::Progression(_) => verify::is_prime_u128(value),
+            Target::TwinPrime => verify::is_prime_u128(value) && verify::is_prime_u128(value + 2),
+        };
+        // Searching down, the slice resumes below the value by ending at it
+        let next = match self.direction {
+            Direction::Up => position + 1,
+            Direction::Down => position,
+        };
+        let candidate = Candidate { value, verified, next };
+        if !candidate.verified {
+            let slice = &self.slices[i];
+            let searched = match self.wide_start {
+                None => slice.start as u128..slice.end as u128,
+                Some(base) => base + slice.start as u128..base + slice.end as u128,
+            };
+            let values = match self.target {
+                Target::Prime | Target::Progression(_) => vec![value],
+                Target::TwinPrime => vec![value, value + 2],
+            };
+            warn!(
+                "GPU {} ({}) reported {} as prime but it failed CPU verification, continuing past it: {}",
+                i, self.devices[i], value, verify::diagnose(&values, searched),
+            );
+            return Ok(Some(candidate));
+        }
+        self.prime_hook.found(i, value);
+        if self.print_status {
+            match (self.target, self.error_probability) {
+                (Target::Prime, Some(error)) => info!("Probable prime found by GPU {}, {}: {} (composite with probability at most {:e})", i, self.devices[i], value, error),
+                (Target::Prime, None) => info!("Prime found by GPU {}, {}: {}", i, self.devices[i], value),
+                (Target::Progression(progression), _) => info!("Prime of the form {} found by GPU {}, {}: {}", progression, i, self.devices[i], value),
+                (Target::TwinPrime, _) => info!("Twin primes found by GPU {}, {}: ({}, {})", i, self.devices[i], value, value + 2),
+            }
+        }
+        Ok(Some(candidate))
+    }
+}
+
+/// Called after each status read with the device index and the last candidate tested by each
+/// of that device's threads, from the thread that displays the readings of every device.
+pub type StatusCallback = Arc<dyn Fn(usize, &[u64]) + Send + Sync>;
+
+/// How far a device has got, passed to the callback registered with
+/// [`PrimeSearcher::with_progress_callback`] after every status read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent {
+    /// Position of the device in [`devices`](PrimeSearcher::devices)
+    pub device: usize,
+    /// Candidates the device has tested so far in this search, as in [`DeviceReport::tested`]
+    pub tested: u64,
+    /// Candidates left ahead of its slowest thread, as in [`DeviceReport::remaining`]
+    pub remaining: u64,
+}
+
+/// Called with every device's progress, from the same thread as the [`StatusCallback`].
+pub type ProgressCallback = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// A prime a search found, passed to the callback registered with
+/// [`PrimeSearcher::with_prime_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimeEvent {
+    /// The prime, or for [`find_twin`](PrimeSearcher::find_twin) the smaller of the pair
+    pub value: u128,
+    /// Position of the device that found it in [`devices`](PrimeSearcher::devices)
+    pub device: usize,
+    /// When it passed verification
+    pub found_at: SystemTime,
+}
+
+/// Called with every prime a search finds, from the thread that displays the readings of
+/// every device.
+pub type PrimeCallback = Arc<Mutex<dyn FnMut(PrimeEvent) + Send>>;
+
+pub struct PrimeSearcher {
+    range: Range<u64>,
+    // Set by new_u128, in which case `range` holds offsets from this start
+    wide_start: Option<u128>,
+    // Start of the range as originally requested, before resuming from a checkpoint
+    origin: u64,
+    // Where each device picks up a resumed search, which may skip parts of `range`
+    resume_slices: Option<Vec<Range<u64>>>,
+    checkpoint: Option<Arc<CheckpointWriter>>,
+    algorithm: Algorithm,
+    // Where the random witnesses of probabilistic Miller-Rabin come from
+    seed: u64,
+    partition_strategy: PartitionStrategy,
+    direction: Direction,
+    retry: RetryPolicy,
+    monitor: bool,
+    poll_interval: Duration,
+    // Whether the queues were created with profiling, so kernel events carry run times
+    queue_profiling: bool,
+    mapped_status: bool,
+    monitor_interval: Duration,
+    // How long a monitor thread may take over one poll before it's abandoned
+    watchdog_timeout: Duration,
+    thermal_limit: Option<ThermalLimit>,
+    power_budget: Option<PowerBudget>,
+    verify: bool,
+    segment_size: usize,
+    print_status: bool,
+    status_threads: StatusThreads,
+    status_callback: Option<StatusCallback>,
+    progress_callback: Option<ProgressCallback>,
+    prime_hook: PrimeHook,
+    metrics: Option<Arc<Metrics>>,
+    telemetry: Option<Arc<TelemetryLog>>,
+    nvml: OnceLock<Option<Arc<Nvml>>>,
+    // Chosen for each device on the first search that monitors
+    monitors: OnceLock<Vec<Arc<dyn Monitor>>>,
+    vendor_ids: Vec<u32>,
+    pci_addresses: Vec<Option<PciAddress>>,
+    devices: Vec<DeviceInfo>,
+    pro_ques: Vec<Arc<ProQue>>,
+    result_buffers: Vec<Arc<Buffer<u64>>>,
+    status_buffers: Vec<Arc<Buffer<u64>>>,
+    thread_counts: Vec<usize>,
+    // Work-items per work group, or None to let the driver choose
+    local_size: Option<usize>,
+    // The most threads each device can run at once, which thread counts are clamped to
+    thread_limits: Vec<usize>,
+    cancel: CancelHandle,
+    progress: Arc<Mutex<Vec<u64>>>,
+    // Each device's status buffer at the latest poll
+    thread_statuses: Arc<Mutex<Vec<Vec<u64>>>>,
+    gpu_stats: Arc<Mutex<Vec<Option<GpuStats>>>>,
+    eta: Arc<Mutex<Eta>>,
+    report: Arc<ReportTracker>,
+}
+
+impl PrimeSearcher {
+    /// Starts a [`PrimeSearcherBuilder`], for setting several options before the devices are set up.
+    pub fn builder() -> PrimeSearcherBuilder {
+        PrimeSearcherBuilder::new()
+    }
+
+    /// Builds a `ProQue` and its buffers for every OpenCL device on every platform.
+    pub fn new(range: Range<u64>) -> Result<Self> {
+        Self::new_with_config(range, &SearcherConfig::default())
+    }
+
+    /// Like [`new`](Self::new), setting the devices up as `config` asks.
+    pub fn new_with_config(range: Range<u64>, config: &SearcherConfig) -> Result<Self> {
+        if range.start > range.end {
+            return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+        }
+        Self::create(range, None, config)
+    }
+
+    /// Builds a searcher for a range that may extend past `u64::MAX`, using
+    /// [`Algorithm::Wide128`]. Results come back from [`find_first_u128`](Self::find_first_u128).
+    ///
+    /// The range may hold at most `u64::MAX` numbers.
+    pub fn new_u128(range: Range<u128>) -> Result<Self> {
+        if range.start > range.end {
+            return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+        }
+        let len = u64::try_from(range.end - range.start)
+            .map_err(|_| PrimeError::InvalidRange(format!("[{}, {}) holds more than u64::MAX numbers", range.start, range.end)))?;
+        Ok(Self::create(0..len, Some(range.start), &SearcherConfig::default())?.with_algorithm(Algorithm::Wide128))
+    }
+
+    /// Builds a searcher that continues the search saved in `checkpoint`, from each device's
+    /// own position when the checkpoint has them (see [`Checkpoint::resume_slices`]).
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Result<Self> {
+        Self::from_checkpoint_with_config(checkpoint, &SearcherConfig::default())
+    }
+
+    /// Like [`from_checkpoint`](Self::from_checkpoint), setting the devices up as `config` asks.
+    pub fn from_checkpoint_with_config(checkpoint: &Checkpoint, config: &SearcherConfig) -> Result<Self> {
+        let mut searcher = Self::new_with_config(checkpoint.next..checkpoint.end, config)?;
+        searcher.origin = checkpoint.start;
+        if !checkpoint.devices.is_empty() {
+            searcher.resume_slices = Some(checkpoint.resume_slices(&searcher.devices));
+        }
+        Ok(searcher)
+    }
+
+    fn create(range: Range<u64>, wide_start: Option<u128>, config: &SearcherConfig) -> Result<Self> {
+        let mut devices = vec![];
+        let mut vendor_ids = vec![];
+        let mut pci_addresses = vec![];
+        let mut pro_ques: Vec<Arc<ProQue>> = vec![];
+        let mut control_queues = vec![];
+        let mut thread_limits = vec![];
+        let properties = if config.queue_profiling { CommandQueueProperties::new().profiling() } else { CommandQueueProperties::new() };
+        // A device whose driver rejects the kernels is left out while others remain
+        let mut build_failure = None;
+        for (info, platform, device) in select_devices(&config.devices)? {
+            // Create a context for the specific platform and device
+            let context = Context::builder()
+                .platform(platform)
+                .devices(device)
+                .build()?;
+            // Flag writes and status reads are meant to overlap the kernel, so the control
+            // queue never needs to run out of order
+            let control_queue = Queue::new(&context, device, Some(properties))?;
+            let compute_properties = match config.out_of_order {
+                true if supports_out_of_order(device)? => properties.out_of_order(),
+                true => {
+                    warn!("{} doesn't support out-of-order queues, keeping an in-order one", info);
+                    properties
+                }
+                false => properties,
+            };
+
+            let limit = thread_limit(device)?;
+            let threads = clamp_threads(pro_ques.len(), &info.name, MAX_THREADS, limit);
+
+            let src = config.kernel_source.as_deref().unwrap_or(KERNEL_SRC);
+            let pro_que = match build_pro_que(context, device, compute_properties, &info.name, src, threads, config.kernel_cache.as_ref()) {
+                Ok(pro_que) => pro_que,
+                Err(e @ PrimeError::KernelBuild { .. }) => {
+                    warn!("Skipping {}: {}", info, e);
+                    build_failure = Some(e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            control_queues.push(control_queue);
+            thread_limits.push(limit);
+            pro_ques.push(Arc::new(pro_que));
+            vendor_ids.push(match device.info(DeviceInfoKind::VendorId)? {
+                DeviceInfoResult::VendorId(id) => id,
+                _ => 0,
+            });
+            pci_addresses.push(PciAddress::of_opencl_device(device));
+            devices.push(info);
+        }
+        if let (true, Some(e)) = (pro_ques.is_empty(), build_failure) {
+            return Err(e);
+        }
+
+        let mut result_buffers = vec![];
+        let mut status_buffers = vec![];
+        let mut cancel_flags = vec![];
+        let mut pause_flags = vec![];
+        for pq in &pro_ques {
+            result_buffers.push(Arc::new(Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .fill_val(u64::MAX)
+                .build()?));
+
+            status_buffers.push(Arc::new(status_buffer(pq.queue(), pq.dims().to_len(), config.mapped_status)?));
+
+            cancel_flags.push(Buffer::<i32>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_only())
+                .len(1)
+                .fill_val(0i32)
+                .build()?);
+
+            pause_flags.push(Buffer::<i32>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_only())
+                .len(1)
+                .fill_val(0i32)
+                .build()?);
+        }
+        let num_devices = pro_ques.len();
+        let thread_counts = pro_ques.iter().map(|pq| pq.dims().to_len()).collect();
+        let report = ReportTracker::new(devices.clone());
+
+        Ok(PrimeSearcher {
+            origin: range.start,
+            resume_slices: None,
+            checkpoint: None,
+            range,
+            wide_start,
+            algorithm: Algorithm::default(),
+            seed: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64),
+            partition_strategy: PartitionStrategy::default(),
+            direction: Direction::default(),
+            retry: RetryPolicy::default(),
+            monitor: true,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            queue_profiling: config.queue_profiling,
+            mapped_status: config.mapped_status,
+            watchdog_timeout: DEFAULT_WATCHDOG_TIMEOUT,
+            monitor_interval: DEFAULT_MONITOR_INTERVAL,
+            thermal_limit: None,
+            power_budget: None,
+            verify: true,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            print_status: true,
+            status_threads: StatusThreads::default(),
+            status_callback: None,
+            progress_callback: None,
+            prime_hook: PrimeHook::default(),
+            metrics: None,
+            telemetry: None,
+            nvml: OnceLock::new(),
+            monitors: OnceLock::new(),
+            vendor_ids,
+            pci_addresses,
+            devices,
+            pro_ques,
+            result_buffers,
+            status_buffers,
+            thread_counts,
+            local_size: None,
+            thread_limits,
+            cancel: CancelHandle::new(KernelHalt::new(control_queues, cancel_flags, pause_flags)),
+            progress: Arc::new(Mutex::new(vec![0; num_devices])),
+            thread_statuses: Arc::new(Mutex::new(vec![vec![]; num_devices])),
+            gpu_stats: Arc::new(Mutex::new(vec![None; num_devices])),
+            eta: Arc::new(Mutex::new(Eta::estimating(num_devices))),
+            report: Arc::new(report),
+        })
+    }
+
+    /// Selects the primality test used by the kernels (trial division by default).
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Seeds the random witnesses of [`Algorithm::MillerRabinProbabilistic`], so a search can
+    /// be repeated with the same ones. Without it the seed comes from the clock.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The seed in use, for repeating a search that drew it from the clock.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Selects how the range is split between devices (even slices by default).
+    pub fn with_partition_strategy(mut self, strategy: PartitionStrategy) -> Self {
+        self.partition_strategy = strategy;
+        self
+    }
+
+    /// Selects which end of the range [`find_first`](Self::find_first) searches from (up from
+    /// the start by default). Searching down only finds single primes, in `u64` ranges split
+    /// into static slices, and isn't checkpointed.
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets how transient failures of buffer reads and kernel enqueues are retried (see
+    /// [`RetryPolicy`] for the default). With [dynamic partitioning](PartitionStrategy::Dynamic)
+    /// a device that still fails is given up on and its chunk handed to the other devices;
+    /// otherwise the search returns the error.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the number of kernel threads per device (1024 by default), resizing the status buffers.
+    ///
+    /// A device gets no more threads than fit in work-groups of its largest size on every one
+    /// of its compute units; the kernels loop over their slice, so any more would only wait for
+    /// the others. Clamped counts are logged as warnings.
+    pub fn with_thread_count(mut self, count: ThreadCount) -> Result<Self> {
+        for i in 0..self.pro_ques.len() {
+            let threads = match count {
+                ThreadCount::Explicit(threads) => threads.max(1),
+                ThreadCount::Auto => {
+                    let pq = &self.pro_ques[i];
+                    let kernel = ocl::core::create_kernel(pq.program(), "search_for_large_prime")?;
+                    let multiple = match ocl::core::get_kernel_work_group_info(&kernel, pq.queue().device(), KernelWorkGroupInfo::PreferredWorkGroupSizeMultiple)? {
+                        KernelWorkGroupInfoResult::PreferredWorkGroupSizeMultiple(multiple) => multiple.max(1),
+                        _ => 1,
+                    };
+                    self.devices[i].compute_units as usize * multiple * AUTO_OCCUPANCY
+                }
+            };
+            self.set_thread_count(i, threads)?;
+        }
+        Ok(self)
+    }
+
+    /// Launches the kernels in work groups of `local_size` work-items, or leaves the size to
+    /// the driver with `None` (the default). Thread counts are rounded down to whole work
+    /// groups, now and when they are set later. Fails if a device's work groups can't be that
+    /// large.
+    pub fn with_local_size(mut self, local_size: Option<usize>) -> Result<Self> {
+        if let Some(size) = local_size {
+            if size == 0 {
+                return Err(PrimeError::Unsupported("work groups need at least one work-item".into()));
+            }
+            if let Some(device) = self.devices.iter().find(|device| size > device.max_work_group_size) {
+                return Err(PrimeError::Unsupported(format!(
+                    "{} runs work groups of at most {} work-items, not {}", device, device.max_work_group_size, size,
+                )));
+            }
+        }
+        self.local_size = local_size;
+        for i in 0..self.pro_ques.len() {
+            self.set_thread_count(i, self.thread_counts[i])?;
+        }
+        Ok(self)
+    }
+
+    // Sizes a device's status buffer for `threads`, within what it can run and in whole work groups
+    fn set_thread_count(&mut self, i: usize, threads: usize) -> Result<()> {
+        let threads = clamp_threads(i, &self.devices[i].name, threads, self.thread_limits[i]);
+        let threads = match self.local_size {
+            Some(size) if !threads.is_multiple_of(size) => {
+                let rounded = (threads / size).max(1) * size;
+                warn!("GPU {} ({}): using {} threads instead of {}, a whole number of work groups of {}", i, self.devices[i].name, rounded, threads, size);
+                rounded
+            }
+            _ => threads,
+        };
+        self.status_buffers[i] = Arc::new(status_buffer(self.pro_ques[i].queue(), threads, self.mapped_status)?);
+        self.thread_counts[i] = threads;
+        Ok(())
+    }
+
+    /// Work-items per work group the kernels are launched with, if not left to the driver.
+    pub fn local_size(&self) -> Option<usize> {
+        self.local_size
+    }
+
+    /// Saves a [`Checkpoint`] to `path` every `interval` while a search runs, and once more
+    /// when it stops. Only `u64` searches are checkpointed.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        let devices = self.devices.iter().map(|device| device.to_string()).collect();
+        self.checkpoint = Some(Arc::new(CheckpointWriter::new(path.into(), interval, self.origin, self.range.end, devices)));
+        self
+    }
+
+    /// Sets how long a device's monitor thread may spend on one poll, beyond the poll interval,
+    /// before the search stops waiting for it (a minute by default). A driver call that never
+    /// returns would otherwise hang the search; the search fails with
+    /// [`PrimeError::Unresponsive`] instead, leaving the stuck thread behind.
+    pub fn with_watchdog_timeout(mut self, timeout: Duration) -> Self {
+        self.watchdog_timeout = timeout;
+        self
+    }
+
+    /// Enables or disables utilization, temperature, power and clock monitoring (enabled by
+    /// default), through NVML for NVIDIA devices and sysfs for AMD and Intel ones on Linux.
+    pub fn with_monitoring(mut self, monitor: bool) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Sets how often each device's status and result buffers are read while a search runs
+    /// (every second by default). Also the cadence of thermal limit checks. A kernel that
+    /// completes between polls is seen straight away on platforms with event callbacks.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Sets how often utilization, temperature, power and clocks are read (every 10
+    /// seconds by default). Readings are taken on a poll, so this is rounded up to a multiple
+    /// of the [poll interval](Self::with_poll_interval).
+    pub fn with_monitor_interval(mut self, interval: Duration) -> Self {
+        self.monitor_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Pauses a device's kernels while its monitored temperature exceeds the limit. Needs monitoring
+    /// to be enabled and a device that reports its temperature; otherwise devices run unthrottled.
+    pub fn with_thermal_limit(mut self, limit: ThermalLimit) -> Self {
+        self.thermal_limit = Some(limit);
+        self
+    }
+
+    /// Keeps the devices' combined power draw within the budget by pausing some of them in
+    /// turn. Paused kernels spin rather than idle, so the saving is what the primality tests
+    /// would have drawn. Needs monitoring to be enabled, and devices that don't report their
+    /// draw count as drawing nothing.
+    pub fn with_power_budget(mut self, budget: PowerBudget) -> Self {
+        self.power_budget = Some(budget);
+        self
+    }
+
+    /// Enables or disables re-checking every prime a kernel reports with [`verify::is_prime`]
+    /// on the CPU before it is returned (enabled by default). Rejected values are logged and
+    /// the search continues past them.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Sets how many numbers [`Algorithm::SegmentedSieve`] sieves per kernel launch (32 MiB by
+    /// default), capped by the device's largest allocation.
+    ///
+    /// Each segment takes one byte of device memory per number and is read back whole, while
+    /// every launch recomputes each base prime's first multiple, so larger segments trade
+    /// memory for fewer launches. Ranges larger than a segment are sieved in several passes.
+    pub fn with_segment_size(mut self, numbers: usize) -> Self {
+        self.segment_size = numbers.max(1);
+        self
+    }
+
+    /// Enables or disables logging thread status, GPU stats and found primes while a search
+    /// runs (enabled by default). Messages go through the `log` crate: primes and GPU stats
+    /// at info, each device's progress at debug and individual threads at trace.
+    pub fn with_status_output(mut self, print_status: bool) -> Self {
+        self.print_status = print_status;
+        self
+    }
+
+    /// How many of each device's threads the status output lists, at trace level, on every
+    /// poll; the first 10 by default.
+    pub fn with_status_threads(mut self, threads: StatusThreads) -> Self {
+        self.status_threads = threads;
+        self
+    }
+
+    /// Registers a callback that receives every status buffer read, e.g. to drive a progress bar.
+    pub fn with_status_callback(mut self, callback: impl Fn(usize, &[u64]) + Send + Sync + 'static) -> Self {
+        self.status_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Registers a callback that receives what each device has tested and has left after every
+    /// status read. Unlike the thread statuses these follow the chunks of a dynamically
+    /// partitioned search, so they suit a progress bar better.
+    pub fn with_progress_callback(mut self, callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Registers a callback that receives each prime [`find_first`](Self::find_first) and the
+    /// other single-result searches find, once it has passed verification, e.g. to store or
+    /// announce it. A device that finds a prime before a device on a lower slice finds a
+    /// smaller one reports it too. A range of one number is tested without the devices and
+    /// reports nothing.
+    pub fn with_prime_callback(mut self, callback: impl FnMut(PrimeEvent) + Send + 'static) -> Self {
+        self.prime_hook = PrimeHook::new(Arc::new(Mutex::new(callback)));
+        self
+    }
+
+    /// Feeds the monitoring readings and tested counts gathered while searching into `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Appends every GPU reading gathered while searching to `log`.
+    pub fn with_telemetry(mut self, log: Arc<TelemetryLog>) -> Self {
+        self.telemetry = Some(log);
+        self
+    }
+
+    /// Moves the searcher to `range`, so one searcher can search range after range without
+    /// setting the devices up again: the buffers are kept and zeroed before each search, and
+    /// the kernels are given the new bounds when they're launched. A checkpoint keeps its file
+    /// but now covers `range`, and the device positions a resumed search started from are
+    /// dropped.
+    pub fn set_range(&mut self, range: Range<u64>) -> Result<()> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("searchers created with new_u128 can't be moved to another range".into()));
+        }
+        if range.start > range.end {
+            return Err(PrimeError::InvalidRange(format!("start {} is greater than end {}", range.start, range.end)));
+        }
+        self.origin = range.start;
+        self.resume_slices = None;
+        self.checkpoint = self.checkpoint.as_ref().map(|checkpoint| Arc::new(checkpoint.for_range(range.start, range.end)));
+        self.range = range;
+        Ok(())
+    }
+
+    /// The range searched by [`find_first`](Self::find_first) and the other searches that
+    /// don't take one.
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    pub fn devices(&self) -> &[DeviceInfo] {
+        &self.devices
+    }
+
+    /// The slice of the range searched by each device, in the order of [`devices`](Self::devices).
+    ///
+    /// For searchers created with [`new_u128`](Self::new_u128) these are offsets from the range start.
+    /// A searcher resumed from a checkpoint gives the slices it resumes with, which leave out
+    /// what the devices already tested.
+    pub fn slices(&self) -> Vec<Range<u64>> {
+        self.slices_of(&self.range)
+    }
+
+    fn slices_of(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+        match &self.resume_slices {
+            Some(slices) if *range == self.range => slices.clone(),
+            _ => self.partition_strategy.split(range.clone(), &self.devices),
+        }
+    }
+
+    /// Number of kernel threads launched on each device.
+    pub fn thread_counts(&self) -> &[usize] {
+        &self.thread_counts
+    }
+
+    /// Latest monitoring reading for each device, or `None` where monitoring is unavailable.
+    pub fn gpu_stats(&self) -> Vec<Option<GpuStats>> {
+        self.gpu_stats.lock().unwrap().clone()
+    }
+
+    /// Handle that stops searches on this searcher from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Highest candidate each device had tested when the last search stopped, or searching
+    /// [down](Direction::Down) the lowest.
+    pub fn progress(&self) -> Vec<u64> {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// The last candidate each thread of each device had tested at the latest status poll of
+    /// the last monitored search, empty for devices not yet polled.
+    pub fn thread_statuses(&self) -> Vec<Vec<u64>> {
+        self.thread_statuses.lock().unwrap().clone()
+    }
+
+    /// Estimated time left for each device and for the whole search, updated on every status
+    /// poll of a monitored search.
+    pub fn eta(&self) -> Eta {
+        self.eta.lock().unwrap().clone()
+    }
+
+    // NVML is initialized on first use; without an NVIDIA driver NVIDIA devices run unmonitored
+    fn nvml(&self) -> Option<Arc<Nvml>> {
+        self.nvml.get_or_init(|| match Nvml::init() {
+            Ok(nvml) => Some(Arc::new(nvml)),
+            Err(e) => {
+                warn!("NVIDIA GPU monitoring disabled, failed to initialize NVML: {}", e);
+                None
+            }
+        }).clone()
+    }
+
+    // The monitor for each device, chosen by vendor
+    fn monitors(&self) -> Vec<Arc<dyn Monitor>> {
+        if !self.monitor {
+            return self.devices.iter().map(|_| Arc::new(NoMonitor) as Arc<dyn Monitor>).collect();
+        }
+        self.monitors.get_or_init(|| {
+            self.devices.iter().enumerate()
+                .map(|(i, device)| monitor::for_device(i, &device.name, self.vendor_ids[i], self.pci_addresses[i], || self.nvml()))
+                .collect()
+        }).clone()
+    }
+
+    /// Searches the range on every device and returns the smallest prime in it, or the largest
+    /// when searching [down](Direction::Down).
+    pub fn find_first(&self) -> Result<Option<u64>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("searchers created with new_u128 must use find_first_u128".into()));
+        }
+        // The range fits in u64, so any prime found does too
+        Ok(self.search_first(&self.range, Target::Prime)?.map(|prime| prime as u64))
+    }
+
+    /// Like [`find_first`](Self::find_first) but for searchers created with
+    /// [`new_u128`](Self::new_u128); also works on `u64` ranges.
+    pub fn find_first_u128(&self) -> Result<Option<u128>> {
+        self.search_first(&self.range, Target::Prime)
+    }
+
+    /// Searches the range for twin primes `(p, p + 2)` with both in the range and returns the
+    /// smallest pair.
+    pub fn find_twin(&self) -> Result<Option<(u64, u64)>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_twin is not available for searchers created with new_u128".into()));
+        }
+        Ok(self.search_first(&self.range, Target::TwinPrime)?.map(|p| (p as u64, p as u64 + 2)))
+    }
+
+    /// Like [`find_twin`](Self::find_twin), also returning what each device did.
+    pub fn find_twin_with_report(&self) -> Result<(Option<(u64, u64)>, SearchReport)> {
+        let twin = self.find_twin()?;
+        Ok((twin, self.report.report()))
+    }
+
+    // `range` is the searcher's own range unless the search came through Backend
+    fn search_first(&self, range: &Range<u64>, target: Target) -> Result<Option<u128>> {
+        if self.algorithm == Algorithm::SegmentedSieve {
+            return Err(PrimeError::Unsupported("the segmented sieve only lists primes through find_all".into()));
+        }
+        if self.direction == Direction::Down {
+            self.check_descending(target)?;
+        }
+        if self.cancel.is_cancelled() {
+            return Ok(None);
+        }
+        if let Some(primes) = self.search_degenerate(range) {
+            // A twin pair takes more than one number
+            return Ok(primes.first().copied().filter(|&prime| match target {
+                Target::Prime => true,
+                Target::TwinPrime => false,
+                Target::Progression(progression) => progression.contains(prime as u64),
+            }));
+        }
+        if let Target::Progression(progression) = target {
+            if !progression.check_meets(range) {
+                self.progress.lock().unwrap().fill(0);
+                self.report.reset();
+                return Ok(None);
+            }
+        }
+        if let (PartitionStrategy::Dynamic { chunk_size }, None) = (self.partition_strategy, self.wide_start) {
+            return self.search_first_dynamic(range, target, chunk_size);
+        }
+
+        let mut slices = self.slices_of(range);
+        let checkpoint = self.start_tracking(range, &slices);
+        if target == Target::TwinPrime {
+            // Overlap each slice with the next so a pair straddling the boundary is still seen
+            for slice in &mut slices {
+                slice.end = slice.end.saturating_add(2).min(range.end).max(slice.start);
+            }
+        }
+        for sb in &self.status_buffers {
+            sb.cmd().fill(0u64, None).enq()?;
+        }
+
+        // Slices ascend with the device index, so the lowest device with a verified hit holds
+        // the smallest one, or searching down the highest holds the largest. A device whose
+        // hit fails verification has tested everything before it and carries on from just
+        // past it; each round relaunches only those devices that could still beat the best
+        // hit so far
+        let mut best: Option<(u128, usize)> = None;
+        loop {
+            let candidates = self.search_slices(&slices, target, checkpoint.clone())?;
+            if !next_round(&mut slices, &candidates, &mut best, self.direction) || self.cancel.is_cancelled() {
+                break;
+            }
+        }
+
+        if let Some((_, device)) = best {
+            self.report.mark_found(device);
+        }
+        Ok(best.map(|(value, _)| value))
+    }
+
+    // Only the u64 kernel for single primes has a descending version
+    fn check_descending(&self, target: Target) -> Result<()> {
+        match target {
+            Target::Prime => {}
+            Target::TwinPrime => return Err(PrimeError::Unsupported("twin primes can only be searched for upwards".into())),
+            Target::Progression(_) => return Err(PrimeError::Unsupported("progressions can only be searched upwards".into())),
+        }
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("searchers created with new_u128 can only search upwards".into()));
+        }
+        if matches!(self.partition_strategy, PartitionStrategy::Dynamic { .. }) {
+            return Err(PrimeError::Unsupported("searching down needs static slices, not dynamic partitioning".into()));
+        }
+        Ok(())
+    }
+
+    fn search_slices(&self, slices: &[Range<u64>], target: Target, checkpoint: Option<Arc<CheckpointWriter>>) -> Result<Vec<Option<Candidate>>> {
+        let events = self.launch_first(slices, target)?;
+        let halt = self.cancel.halt_kernels().clone();
+        let reader = self.candidate_reader(slices, target);
+        let direction = self.direction;
+        // A read on the compute queue would wait for the kernel anyway, and the kernel can
+        // still improve on its result until it finishes
+        self.monitor(events, slices, checkpoint, None, move |i, finished| {
+            if !finished {
+                return Ok(None);
+            }
+            let candidate = reader.read(i)?;
+            if candidate.is_some_and(|candidate| candidate.verified) {
+                // Devices searching later slices can no longer find anything better
+                match direction {
+                    Direction::Up => halt.halt_above(i)?,
+                    Direction::Down => halt.halt_below(i)?,
+                }
+            }
+            Ok(candidate)
+        })
+    }
+
+    // Starts the search kernel for `target` over each device's slice
+    fn launch_first(&self, slices: &[Range<u64>], target: Target) -> Result<Vec<Event>> {
+        let halt = self.cancel.halt_kernels();
+        let witnesses = self.probabilistic_witnesses()?;
+        let mut events = vec![];
+        for (i, ((pq, rb), sb)) in self.pro_ques.iter().zip(self.result_buffers.iter()).zip(self.status_buffers.iter()).enumerate() {
+            let slice = &slices[i];
+            rb.cmd().fill(self.direction.no_result(), None).enq()?;
+            halt.flag(i).cmd().fill(0, None).enq()?;
+            halt.pause_flag(i).cmd().fill(0, None).enq()?;
+            // Released once the kernel using it finishes
+            let witness_buffer = witnesses.as_ref().map(|witnesses| Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_only())
+                .len(witnesses.len())
+                .copy_host_slice(witnesses)
+                .build()).transpose()?;
+
+            let mut builder = pq.kernel_builder(match (target, self.wide_start) {
+                (Target::Prime, None) if self.direction == Direction::Down => "search_for_largest_prime",
+                (Target::Prime, None) => "search_for_large_prime",
+                (Target::Prime, Some(_)) if witness_buffer.is_some() => "search_for_large_prime_probabilistic",
+                (Target::Prime, Some(_)) if self.algorithm == Algorithm::Bpsw => "search_for_large_prime_bpsw_wide",
+                (Target::Prime, Some(_)) => "search_for_large_prime_wide",
+                (Target::TwinPrime, _) => "search_twin_primes",
+                (Target::Progression(_), _) => "search_progression",
+            });
+            match (target, self.wide_start) {
+                // The kernel starts each slice at its first term
+                (Target::Progression(progression), _) => {
+                    let first = progression.next_term(slice.start).unwrap_or(slice.end);
+                    builder.arg(first).arg(slice.end).arg(self.algorithm.kernel_id()?).arg(progression.modulus())
+                }
+                (_, None) => builder.arg(slice.start).arg(slice.end).arg(self.algorithm.kernel_id()?),
+                (_, Some(base)) => {
+                    let start = base + slice.start as u128;
+                    builder.arg((start >> 64) as u64).arg(start as u64).arg(slice.end - slice.start);
+                    if let Some(buffer) = &witness_buffer {
+                        builder.arg(buffer).arg(buffer.len() as u32);
+                    }
+                    &mut builder
+                }
+            };
+            let kernel = builder
+                .global_work_size(status_work_size(i, sb, self.thread_counts[i])?)
+                .local_work_size(work_group(self.local_size))
+                .arg(&**rb) // Dereference Arc
+                .arg(&**sb) // Dereference Arc
+                .arg(halt.flag(i))
+                .arg(halt.pause_flag(i))
+                .build()?;
+
+            events.push(self.retry.enqueue(i, &kernel)?);
+        }
+        Ok(events)
+    }
+
+    // The witnesses for a wide search with probabilistic Miller-Rabin, drawn from the seed
+    fn probabilistic_witnesses(&self) -> Result<Option<Vec<u64>>> {
+        match self.algorithm {
+            Algorithm::MillerRabinProbabilistic { rounds } if self.wide_start.is_some() => {
+                if rounds == 0 {
+                    return Err(PrimeError::Unsupported("probabilistic Miller-Rabin needs at least one round".into()));
+                }
+                info!("Probabilistic Miller-Rabin with {} rounds from seed {}", rounds, self.seed);
+                Ok(Some(primality::random_witnesses(self.seed, rounds)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn candidate_reader(&self, slices: &[Range<u64>], target: Target) -> CandidateReader {
+        CandidateReader {
+            target,
+            direction: self.direction,
+            retry: self.retry,
+            result_buffers: self.result_buffers.clone(),
+            slices: slices.to_vec(),
+            wide_start: self.wide_start,
+            verify: self.verify,
+            print_status: self.print_status,
+            devices: self.devices.clone(),
+            error_probability: self.wide_start.and(self.algorithm.error_probability()),
+            prime_hook: self.prime_hook.clone(),
+        }
+    }
+
+    /// Like [`find_first`](Self::find_first), also returning what each device did.
+    pub fn find_first_with_report(&self) -> Result<(Option<u64>, SearchReport)> {
+        let prime = self.find_first()?;
+        Ok((prime, self.report.report()))
+    }
+
+    /// Same as [`find_first`](Self::find_first).
+    pub fn run(&self) -> Result<Option<u64>> {
+        self.find_first()
+    }
+
+    /// Returns every prime in the range, in ascending order.
+    ///
+    /// The output buffer is sized from the prime-counting estimate; if it turns out too
+    /// small, [`PrimeError::ResultOverflow`] reports how many primes were found so the
+    /// search can be retried with [`find_all_with_capacity`](Self::find_all_with_capacity).
+    pub fn find_all(&self) -> Result<Vec<u64>> {
+        self.find_all_in(&self.range)
+    }
+
+    fn find_all_in(&self, range: &Range<u64>) -> Result<Vec<u64>> {
+        let pieces = match self.partition_strategy {
+            PartitionStrategy::Dynamic { chunk_size } => partition_chunks(range.clone(), chunk_size),
+            _ => self.slices_of(range),
+        };
+        let capacity = pieces.iter().map(estimate_prime_count).max().unwrap_or(0);
+        self.find_all_in_with_capacity(range, capacity)
+    }
+
+    /// Like [`find_all`](Self::find_all), also returning what each device did.
+    pub fn find_all_with_report(&self) -> Result<(Vec<u64>, SearchReport)> {
+        let primes = self.find_all()?;
+        Ok((primes, self.report.report()))
+    }
+
+    /// Returns the first pair of consecutive primes `(p, q)` in the range with `q - p >= min_gap`.
+    ///
+    /// The range is listed window by window with [`find_all`](Self::find_all)'s kernels, and the
+    /// last prime of each window is carried into the next, so a gap crossing a window or device
+    /// slice boundary is still found. Both primes must lie in the range.
+    pub fn find_gap(&self, min_gap: u64) -> Result<Option<(u64, u64)>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_gap is not available for searchers created with new_u128".into()));
+        }
+        let mut previous: Option<u64> = None;
+        for window in partition_chunks(self.range.clone(), LIST_WINDOW) {
+            let primes = self.find_all_in(&window)?;
+            // A cancelled window is missing primes, which would show up as false gaps
+            if self.cancel.is_cancelled() {
+                return Ok(None);
+            }
+            for q in primes {
+                if let Some(p) = previous.filter(|&p| q - p >= min_gap) {
+                    return Ok(Some((p, q)));
+                }
+                previous = Some(q);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the first `k` primes in the range in ascending order, or all of them if the
+    /// range holds fewer.
+    ///
+    /// The range is searched in ascending windows sized from the prime density to hold about
+    /// the primes still wanted, stopping after the window that brings the total to `k`. Each
+    /// device writes its primes through an atomic index that is checked against the buffer's
+    /// capacity, so racing threads can't overrun it. A cancelled search returns the primes of
+    /// the windows that completed.
+    pub fn find_n(&self, k: usize) -> Result<Vec<u64>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_n is not available for searchers created with new_u128".into()));
+        }
+        let mut primes = vec![];
+        let mut start = self.range.start;
+        while primes.len() < k && start < self.range.end {
+            // Primes thin out along the range, so a window sized at its start holds a few extra
+            let wanted = (k - primes.len()) as f64 * 1.1 + 64.0;
+            let len = (wanted * (start.max(3) as f64).ln()) as u64;
+            let window = start..start.saturating_add(len.clamp(1 << 16, LIST_WINDOW)).min(self.range.end);
+            let found = self.find_all_in(&window)?;
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            primes.extend(found);
+            start = window.end;
+        }
+        primes.truncate(k);
+        Ok(primes)
+    }
+
+    /// Like [`find_all`](Self::find_all) but with an explicit output buffer size per device.
+    ///
+    /// [`Algorithm::SegmentedSieve`] collects primes segment by segment and ignores `capacity`.
+    /// Like [`find_all`](Self::find_all) with room for `capacity` primes per device, which
+    /// the kernels index with a `u32`; anything larger is [`PrimeError::InvalidRange`].
+    pub fn find_all_with_capacity(&self, capacity: usize) -> Result<Vec<u64>> {
+        self.find_all_in_with_capacity(&self.range, capacity)
+    }
+
+    fn find_all_in_with_capacity(&self, range: &Range<u64>, capacity: usize) -> Result<Vec<u64>> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_all is not available for searchers created with new_u128".into()));
+        }
+        if self.cancel.is_cancelled() {
+            return Ok(vec![]);
+        }
+        if let Some(primes) = self.search_degenerate(range) {
+            return Ok(primes.into_iter().map(|prime| prime as u64).collect());
+        }
+        if self.algorithm == Algorithm::SegmentedSieve {
+            return self.sieve_all(range);
+        }
+        // The kernels index the result buffer with a u32
+        let kernel_capacity = u32::try_from(capacity).map_err(|_| PrimeError::InvalidRange(format!(
+            "room for {} primes is more than the {} one result buffer can hold, search a smaller range", capacity, u32::MAX,
+        )))?;
+        if let PartitionStrategy::Dynamic { chunk_size } = self.partition_strategy {
+            return self.find_all_dynamic(range, chunk_size, capacity, kernel_capacity);
+        }
+
+        let halt = self.cancel.halt_kernels();
+        let mut events = vec![];
+        let mut prime_buffers = vec![];
+        let mut count_buffers = vec![];
+        let slices = self.slices_of(range);
+        let checkpoint = self.start_tracking(range, &slices);
+        for (i, (pq, sb)) in self.pro_ques.iter().zip(self.status_buffers.iter()).enumerate() {
+            let slice = &slices[i];
+            sb.cmd().fill(0u64, None).enq()?;
+            halt.flag(i).cmd().fill(0, None).enq()?;
+            halt.pause_flag(i).cmd().fill(0, None).enq()?;
+
+            let primes = Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().write_only())
+                .len(capacity.max(1))
+                .build()?;
+            let count = Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .fill_val(0u64)
+                .build()?;
+
+            let kernel = pq.kernel_builder("search_all_primes")
+                .global_work_size(status_work_size(i, sb, self.thread_counts[i])?)
+                .local_work_size(work_group(self.local_size))
+                .arg(slice.start)
+                .arg(slice.end)
+                .arg(self.algorithm.kernel_id()?)
+                .arg(&primes)
+                .arg(&count)
+                .arg(kernel_capacity)
+                .arg(&**sb) // Dereference Arc
+                .arg(halt.flag(i))
+                .arg(halt.pause_flag(i))
+                .build()?;
+
+            events.push(self.retry.enqueue(i, &kernel)?);
+            prime_buffers.push(primes);
+            count_buffers.push(count);
+        }
+
+        // Only cancellation stops the search early; each device is read once its kernel has finished
+        let retry = self.retry;
+        let results = self.monitor(events, &slices, checkpoint, None, move |i, finished| {
+            if !finished {
+                return Ok(None);
+            }
+            let mut count = vec![0u64; 1];
+            retry.run(i, "count read", || Ok(count_buffers[i].read(&mut count).enq()?))?;
+            let found = usize::try_from(count[0]).unwrap_or(usize::MAX);
+            if found > capacity {
+                return Err(PrimeError::ResultOverflow { found, capacity });
+            }
+            let mut primes = vec![0u64; found];
+            if found > 0 {
+                retry.run(i, "prime read", || Ok(prime_buffers[i].read(&mut primes).len(found).enq()?))?;
+            }
+            Ok(Some(primes))
+        })?;
+
+        // Threads append in whatever order they find primes, so restore ascending order
+        let mut primes: Vec<u64> = results.into_iter().flatten().flatten().collect();
+        primes.sort_unstable();
+        Ok(primes)
+    }
+
+    /// Counts the primes in the range without collecting them, summing one counter per device.
+    ///
+    /// A cancelled count only covers the candidates tested before the kernels stopped.
+    pub fn count(&self) -> Result<u64> {
+        self.count_in(&self.range)
+    }
+
+    fn count_in(&self, range: &Range<u64>) -> Result<u64> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("count is not available for searchers created with new_u128".into()));
+        }
+        if self.cancel.is_cancelled() {
+            return Ok(0);
+        }
+        if let Some(primes) = self.search_degenerate(range) {
+            return Ok(primes.len() as u64);
+        }
+        // The sieve has no per-candidate kernel to count with, so it lists the primes instead
+        if self.algorithm == Algorithm::SegmentedSieve {
+            return Ok(self.sieve_all(range)?.len() as u64);
+        }
+        if let PartitionStrategy::Dynamic { chunk_size } = self.partition_strategy {
+            return self.count_dynamic(range, chunk_size);
+        }
+
+        let halt = self.cancel.halt_kernels();
+        let mut events = vec![];
+        let mut count_buffers = vec![];
+        let slices = self.slices_of(range);
+        let checkpoint = self.start_tracking(range, &slices);
+        for (i, (pq, sb)) in self.pro_ques.iter().zip(self.status_buffers.iter()).enumerate() {
+            let slice = &slices[i];
+            sb.cmd().fill(0u64, None).enq()?;
+            halt.flag(i).cmd().fill(0, None).enq()?;
+            halt.pause_flag(i).cmd().fill(0, None).enq()?;
+
+            let count = Buffer::<u64>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1)
+                .fill_val(0u64)
+                .build()?;
+
+            let kernel = pq.kernel_builder("count_primes")
+                .global_work_size(status_work_size(i, sb, self.thread_counts[i])?)
+                .local_work_size(work_group(self.local_size))
+                .arg(slice.start)
+                .arg(slice.end)
+                .arg(self.algorithm.kernel_id()?)
+                .arg(&count)
+                .arg(&**sb) // Dereference Arc
+                .arg(halt.flag(i))
+                .arg(halt.pause_flag(i))
+                .build()?;
+
+            events.push(self.retry.enqueue(i, &kernel)?);
+            count_buffers.push(count);
+        }
+
+        // Threads add to the counter as they exit, so it is read once every kernel has stopped,
+        // which also picks up the partial counts of cancelled ones
+        self.monitor(events, &slices, checkpoint, None, |_, _| Ok(None::<()>))?;
+        let mut total = 0;
+        for (i, buffer) in count_buffers.iter().enumerate() {
+            let mut count = vec![0u64; 1];
+            self.retry.run(i, "count read", || Ok(buffer.read(&mut count).enq()?))?;
+            total += count[0];
+        }
+        Ok(total)
+    }
+
+    /// Runs the Lucas-Lehmer test on 2^p - 1 for each exponent p, splitting the exponents
+    /// between devices. Exponents up to [`MAX_MERSENNE_EXPONENT`] are supported; larger ones
+    /// would need a bignum representation on the device.
+    pub fn test_mersenne(&self, exponents: &[u32]) -> Result<Vec<(u32, bool)>> {
+        if let Some(&p) = exponents.iter().find(|&&p| p > MAX_MERSENNE_EXPONENT) {
+            return Err(PrimeError::Unsupported(format!("exponent {} is above the largest supported, {}", p, MAX_MERSENNE_EXPONENT)));
+        }
+
+        // Enqueue every device's share before reading any of them back
+        let chunks = partition_range(0..exponents.len() as u64, self.pro_ques.len());
+        let mut pending = vec![];
+        for (i, (pq, chunk)) in self.pro_ques.iter().zip(chunks).enumerate() {
+            let chunk = &exponents[chunk.start as usize..chunk.end as usize];
+            if chunk.is_empty() {
+                continue;
+            }
+            let input = Buffer::<u32>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().read_only())
+                .len(chunk.len())
+                .copy_host_slice(chunk)
+                .build()?;
+            let results = Buffer::<u8>::builder()
+                .queue(pq.queue().clone())
+                .flags(MemFlags::new().write_only())
+                .len(chunk.len())
+                .build()?;
+
+            let kernel = pq.kernel_builder("test_mersenne")
+                // Whole work groups, the extra work-items falling outside the chunk
+                .global_work_size(round_up(self.thread_counts[i].min(chunk.len()), self.local_size))
+                .local_work_size(work_group(self.local_size))
+                .arg(&input)
+                .arg(chunk.len() as u32)
+                .arg(&results)
+                .build()?;
+            let done = order::enqueue_kernel(&kernel)?;
+            pending.push((chunk, results, done));
+        }
+
+        let mut tested = vec![];
+        for (chunk, results, done) in pending {
+            // The read waits for the kernel to finish
+            let mut prime = vec![0u8; chunk.len()];
+            results.read(&mut prime).ewait(&done).enq()?;
+            tested.extend(chunk.iter().zip(prime).map(|(&p, prime)| (p, prime != 0)));
+        }
+        Ok(tested)
+    }
+
+    /// Tests `n` alone with the selected algorithm on the first device, for one-off checks
+    /// and to see that the devices work at all. The result is not verified on the CPU.
+    pub fn test_number(&self, n: u64) -> Result<bool> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("test_number is not available for searchers created with new_u128".into()));
+        }
+        self.test_number_on(0, n, self.algorithm)
+    }
+
+    // Tests `n` alone on device `i`
+    pub(crate) fn test_number_on(&self, i: usize, n: u64, algorithm: Algorithm) -> Result<bool> {
+        let algorithm = algorithm.kernel_id()?;
+        // u64::MAX is divisible by 3, and n..n + 1 would overflow for it anyway
+        let Some(end) = n.checked_add(1) else {
+            return Ok(false);
+        };
+        let pq = &self.pro_ques[i];
+        let halt = self.cancel.halt_kernels();
+
+        // Buffers of its own so a search running alongside keeps its results
+        let result = Buffer::<u64>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(u64::MAX)
+            .build()?;
+        let status = Buffer::<u64>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().write_only())
+            .len(1)
+            .build()?;
+        halt.flag(i).cmd().fill(0, None).enq()?;
+        halt.pause_flag(i).cmd().fill(0, None).enq()?;
+
+        let kernel = pq.kernel_builder("search_for_large_prime")
+            .global_work_size(1)
+            .arg(n)
+            .arg(end)
+            .arg(algorithm)
+            .arg(&result)
+            .arg(&status)
+            .arg(halt.flag(i))
+            .arg(halt.pause_flag(i))
+            .build()?;
+        let done = order::enqueue_kernel(&kernel)?;
+        // The read waits for the kernel to finish
+        let mut found = [0u64];
+        result.read(&mut found[..]).ewait(&done).enq()?;
+        Ok(found[0] == n)
+    }
+
+    // A range of at most one number is tested on the CPU instead of launching kernels and
+    // monitor threads on every device for it. None for longer ranges, which the devices search.
+    fn search_degenerate(&self, range: &Range<u64>) -> Option<Vec<u128>> {
+        if range.end - range.start > 1 {
+            return None;
+        }
+        self.progress.lock().unwrap().fill(0);
+        self.report.reset();
+        let base = self.wide_start.unwrap_or(0);
+        Some(range.clone().map(|n| base + n as u128).filter(|&n| verify::is_prime_u128(n)).collect())
+    }
+
+    // Clears the progress, report and checkpoint state left by a previous search, returning
+    // the checkpoint to record to. The checkpoint describes the searcher's own range, so a
+    // search over any other range, or in offsets for new_u128, records nothing.
+    fn start_tracking(&self, range: &Range<u64>, slices: &[Range<u64>]) -> Option<Arc<CheckpointWriter>> {
+        self.progress.lock().unwrap().fill(0);
+        self.thread_statuses.lock().unwrap().iter_mut().for_each(Vec::clear);
+        *self.eta.lock().unwrap() = Eta::estimating(self.devices.len());
+        self.report.reset();
+        let dynamic = matches!(self.partition_strategy, PartitionStrategy::Dynamic { .. });
+        let checkpoint = self.checkpoint.clone().filter(|_| *range == self.range && self.wide_start.is_none() && !dynamic && self.direction == Direction::Up)?;
+        checkpoint.reset(slices);
+        Some(checkpoint)
+    }
+
+    // Spawns one monitor thread per device that polls every poll interval until `poll` returns a
+    // value, the device's kernel completes, or the search is cancelled, printing thread status
+    // and GPU stats and applying the thermal limit along the way. Each thread only answers to
+    // its own device: a prime found elsewhere ends it only by way of the halt flags a
+    // find_first poll sets, so find_all and count wait for every slice to finish.
+    fn monitor<T, F>(&self, events: Vec<Event>, slices: &[Range<u64>], checkpoint: Option<Arc<CheckpointWriter>>, relaunch: Option<Relaunch>, poll: F) -> Result<Vec<Option<T>>>
+    where
+        T: Send + 'static,
+        F: Fn(usize, bool) -> Result<Option<T>> + Send + Sync + 'static,
+    {
+        let poll = Arc::new(poll);
+        let monitors = self.monitors();
+
+        // Readings go to a single thread that shows and forwards them
+        let (updates, received) = mpsc::channel();
+        let sink = StatusSink {
+            names: self.devices.iter().map(|device| device.name.clone()).collect(),
+            print_status: self.print_status,
+            status_threads: self.status_threads,
+            callback: self.status_callback.clone(),
+            on_progress: self.progress_callback.clone(),
+            latest: Arc::clone(&self.thread_statuses),
+            on_prime: self.prime_hook.callback(),
+            metrics: self.metrics.clone(),
+            telemetry: self.telemetry.clone(),
+            eta: Arc::clone(&self.eta),
+        };
+        let display = thread::spawn(move || sink.consume(received));
+        self.prime_hook.attach(updates.clone());
+
+        let power_governor = self.power_budget.map(|budget| Arc::new(PowerGovernor::new(budget, self.devices.len())));
+
+        let watchdog = Arc::new(Watchdog::new(self.devices.len(), self.watchdog_timeout + self.poll_interval));
+        let (finished, finished_read) = watchdog::finished_channel();
+
+        // Periodically read the status buffer to monitor thread status and GPU utilization
+        let mut threads = vec![];
+        for (i, (status_buffer, event)) in self.status_buffers.iter().zip(events).enumerate() {
+            let watchdog = Arc::clone(&watchdog);
+            let finished = Finished(i, finished.clone());
+            let cancel = self.cancel.clone();
+            let progress = Arc::clone(&self.progress);
+            let gpu_stats = Arc::clone(&self.gpu_stats);
+            let thermal_limit = self.thermal_limit;
+            let power_governor = power_governor.clone();
+            let poll_interval = self.poll_interval;
+            let monitor_interval = self.monitor_interval;
+            let updates = updates.clone();
+            let checkpoint = checkpoint.clone();
+            let relaunch = relaunch.clone();
+            let slice = slices[i].clone();
+            let poll = Arc::clone(&poll);
+            let monitor = Arc::clone(&monitors[i]);
+            let mut status_read = StatusRead::new(i, Arc::clone(status_buffer), self.cancel.halt_kernels().control_queue(i).clone(), self.retry, self.mapped_status);
+            let report = Arc::clone(&self.report);
+            let tested_before = report.tested(i);
+            // The wide kernel records the low word of each candidate
+            let first = match (self.wide_start, self.direction) {
+                (None, Direction::Up) => slice.start,
+                (None, Direction::Down) => slice.end.saturating_sub(1),
+                (Some(base), _) => (base + slice.start as u128) as u64,
+            };
+            let direction = self.direction;
+            let queue_profiling = self.queue_profiling;
+
+            threads.push(thread::spawn(move || -> Result<Option<T>> {
+                let _finished = finished;
+                let (mut event, mut slice, mut first, mut tested_before) = (event, slice, first, tested_before);
+                let mut completion = Completion::watch(i, &event);
+                status_read.follow(&event);
+                let mut last_stats: Option<Instant> = None;
+                // Paused for either reason, and for each
+                let (mut paused, mut hot, mut over_budget) = (false, false, false);
+                let power_seat = power_governor.as_ref().map(|governor| PowerGovernor::seat(governor, i));
+
+                let record_checkpoint = |lowest: u64| {
+                    if let Some(checkpoint) = &checkpoint {
+                        if let Err(e) = checkpoint.record(i, lowest) {
+                            warn!("Failed to write checkpoint: {}", e);
+                        }
+                    }
+                };
+
+                // Records what the status read shows, returning the update to display
+                let record_progress = |status: &[u64], slice: &Range<u64>, first: u64, tested_before: u64| {
+                    let mut progress = progress.lock().unwrap();
+                    let len = slice.end - slice.start;
+                    let (tested, remaining) = match direction {
+                        Direction::Up => {
+                            progress[i] = progress[i].max(status.iter().copied().max().unwrap_or(0));
+                            // Threads that have not started yet still read 0
+                            record_checkpoint(status.iter().copied().min().unwrap_or(0).max(slice.start));
+                            (tested_count(status, first, len), remaining_count(status, first, len))
+                        }
+                        Direction::Down => {
+                            // Searching down, progress is the lowest candidate reached
+                            if let Some(lowest) = status.iter().copied().filter(|&n| n != 0).min() {
+                                progress[i] = if progress[i] == 0 { lowest } else { progress[i].min(lowest) };
+                            }
+                            (tested_count_down(status, first, len), remaining_count(&mirrored_down(status, first), 0, len))
+                        }
+                    };
+                    let total_tested = tested_before + tested;
+                    let tested = report.record_tested(i, total_tested);
+                    report.record_remaining(i, remaining);
+                    StatusUpdate { device: i, thread_statuses: status.to_vec(), tested, total_tested, remaining, gpu_stats: None, temperature: None }
+                };
+                let record_kernel_time = |event: &Event| {
+                    if queue_profiling {
+                        record_kernel_time(&report, i, event);
+                    }
+                };
+                // The display thread only hangs up once every monitor thread has returned
+                let send = |update: StatusUpdate| {
+                    let _ = updates.send(Reading::Status(update));
+                };
+                // Once the device stops, nothing is left for it whatever its threads last read
+                let send_last = |update: StatusUpdate| send(StatusUpdate { remaining: 0, ..update });
+
+                loop {
+                    watchdog.beat(i);
+                    if cancel.is_cancelled() {
+                        // One last read once the kernel stops, so the reported progress
+                        // includes the final candidates
+                        event.wait_for()?;
+                        record_kernel_time(&event);
+                        send_last(record_progress(status_read.wait(), &slice, first, tested_before));
+                        report.finish_device(i);
+                        return Ok(None);
+                    }
+
+                    // Check completion before reading so a finished kernel's final results are seen
+                    let finished = event.is_complete()?;
+
+                    if let Some(value) = poll(i, finished)? {
+                        record_kernel_time(&event);
+                        send_last(record_progress(status_read.wait(), &slice, first, tested_before));
+                        report.record_remaining(i, 0);
+                        report.finish_device(i);
+                        return Ok(Some(value));
+                    }
+
+                    let status = if finished { status_read.wait() } else { status_read.poll() };
+                    let mut update = record_progress(status, &slice, first, tested_before);
+
+                    if finished {
+                        record_kernel_time(&event);
+                        record_checkpoint(slice.end);
+                        send_last(update);
+                        // A dynamically partitioned search hands the device its next chunk
+                        if let Some((next_event, chunk)) = relaunch.as_ref().map(|relaunch| relaunch(i)).transpose()?.flatten() {
+                            tested_before = report.tested(i);
+                            first = chunk.start;
+                            slice = chunk;
+                            event = next_event;
+                            completion = Completion::watch(i, &event);
+                            status_read.follow(&event);
+                            continue;
+                        }
+                        report.record_remaining(i, 0);
+                        report.finish_device(i);
+                        return Ok(None);
+                    }
+
+                    // With a thermal limit the temperature is checked on every poll
+                    let temperature = match thermal_limit {
+                        Some(_) => monitor.temperature()?,
+                        None => None,
+                    };
+                    if let (Some(limit), Some(temperature)) = (thermal_limit, temperature) {
+                        report.record_temperature(i, temperature);
+                        update.temperature = Some(temperature);
+                        if limit.should_pause(hot, temperature) != hot {
+                            hot = !hot;
+                            if hot {
+                                warn!("GPU {} paused at {}°C (limit {}°C)", i, temperature, limit.max_temp);
+                            } else {
+                                info!("GPU {} resumed at {}°C (limit {}°C)", i, temperature, limit.max_temp);
+                            }
+                        }
+                    }
+                    // And with a power budget the draw, which the governor logs its decisions on
+                    if let Some(seat) = &power_seat {
+                        over_budget = seat.record(monitor.power()?);
+                    }
+                    if (hot || over_budget) != paused {
+                        paused = !paused;
+                        cancel.halt_kernels().set_paused(i, paused)?;
+                    }
+
+                    // Monitor GPU utilization, temperature, power and clocks every monitor interval
+                    if last_stats.is_none_or(|last| last.elapsed() >= monitor_interval) {
+                        last_stats = Some(Instant::now());
+                        if let Some(stats) = monitor.stats()? {
+                            gpu_stats.lock().unwrap()[i] = Some(stats);
+                            report.record_temperature(i, stats.temperature);
+                            update.gpu_stats = Some(stats);
+                        }
+                    }
+                    send(update);
+
+                    completion.wait(poll_interval);
+                }
+            }));
+        }
+        drop(updates);
+        drop(finished);
+
+        let (results, abandoned) = watchdog::join_all(threads, finished_read, &watchdog);
+        // The display thread finishes once the hook lets go of it too
+        self.prime_hook.detach();
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.flush()?;
+        }
+        if !abandoned.is_empty() {
+            for &i in &abandoned {
+                error!("GPU {} ({}) stopped responding, abandoning its monitor thread", i, self.devices[i]);
+            }
+            // The display thread waits for the abandoned threads too, so it's left behind with them
+            return Err(PrimeError::Unresponsive(abandoned.iter().map(|&i| self.devices[i].to_string()).collect()));
+        }
+        display.join().expect("the status display thread panicked");
+        results.into_iter().map(|result| result.expect("every thread that wasn't abandoned was joined")).collect()
+    }
+}
+
+/// Every OpenCL device on every platform, in the order [`DeviceFilter`] indices count them,
+/// without setting any of them up. Platforms whose devices can't be listed are skipped with a
+/// warning. Fails with [`PrimeError::NoDevices`] if no OpenCL platform is installed, or none
+/// of those installed could list its devices.
+pub fn enumerate_devices() -> Result<Vec<DeviceInfo>> {
+    Ok(all_devices()?.into_iter().map(|(info, _, _)| info).collect())
+}
+
+/// What NVML reports about an NVIDIA device beyond what OpenCL does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvmlInfo {
+    pub driver_version: String,
+    /// Total VRAM in bytes
+    pub memory_total: u64,
+}
+
+/// NVML's description of each device [`enumerate_devices`] lists, in the same order. Entries
+/// are `None` for devices NVML doesn't manage, and all of them are without an NVIDIA driver.
+pub fn nvml_info() -> Result<Vec<Option<NvmlInfo>>> {
+    let devices = all_devices()?;
+    let is_nvidia = |device: &Device| matches!(device.info(DeviceInfoKind::VendorId), Ok(DeviceInfoResult::VendorId(monitor::VENDOR_NVIDIA)));
+    // Initializing NVML is slow enough to skip when no device could use it
+    if !devices.iter().any(|(_, _, device)| is_nvidia(device)) {
+        return Ok(vec![None; devices.len()]);
+    }
+    let nvml = match Nvml::init() {
+        Ok(nvml) => nvml,
+        Err(e) => {
+            debug!("No NVML details, failed to initialize NVML: {}", e);
+            return Ok(vec![None; devices.len()]);
+        }
+    };
+    let driver_version = nvml.sys_driver_version()?;
+    Ok(devices.iter().map(|(_, _, device)| {
+        let index = PciAddress::of_opencl_device(*device).filter(|_| is_nvidia(device))?.nvml_index(&nvml)?;
+        let memory = nvml.device_by_index(index).and_then(|device| device.memory_info()).ok()?;
+        Some(NvmlInfo { driver_version: driver_version.clone(), memory_total: memory.total })
+    }).collect())
+}
+
+/// Lists the devices `filter` selects without setting any of them up.
+pub fn list_devices(filter: &DeviceFilter) -> Result<Vec<DeviceInfo>> {
+    Ok(select_devices(filter)?.into_iter().map(|(info, _, _)| info).collect())
+}
+
+fn all_devices() -> Result<Vec<(DeviceInfo, Platform, Device)>> {
+    // A loader without any installed ICD reports an error rather than an empty list
+    let platforms = ocl::core::get_platform_ids().map_err(|_| PrimeError::NoDevices)?;
+
+    // A broken ICD fails only its own platform, so its error is logged and the devices of the
+    // platforms that work are still searched
+    let mut devices = vec![];
+    let mut failed = 0;
+    for platform in Platform::list_from_core(platforms) {
+        let listed = Device::list_all(platform).map_err(PrimeError::from).and_then(|listed| {
+            listed.into_iter().map(|device| Ok((describe_device(0, &platform, &device)?, platform, device))).collect::<Result<Vec<_>>>()
+        });
+        match listed {
+            Ok(listed) => devices.extend(listed),
+            Err(e) => {
+                let name = platform.name().unwrap_or_else(|_| "unnamed platform".into());
+                warn!("Skipping OpenCL platform {}, its devices could not be listed: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    if devices.is_empty() && failed > 0 {
+        return Err(PrimeError::NoDevices);
+    }
+    // Devices without an address go last. The sort is stable, so identical devices that
+    // can't be told apart keep the order they were listed in.
+    devices.sort_by_cached_key(|(info, _, device)| {
+        let address = PciAddress::of_opencl_device(*device);
+        (address.is_none(), address, info.name.clone())
+    });
+    for (index, (info, _, _)) in devices.iter_mut().enumerate() {
+        info.index = index;
+    }
+    Ok(devices)
+}
+
+fn describe_device(index: usize, platform: &Platform, device: &Device) -> Result<DeviceInfo> {
+    let compute_units = match device.info(DeviceInfoKind::MaxComputeUnits)? {
+        DeviceInfoResult::MaxComputeUnits(units) => units,
+        _ => 1,
+    };
+    let global_memory = match device.info(DeviceInfoKind::GlobalMemSize)? {
+        DeviceInfoResult::GlobalMemSize(bytes) => bytes,
+        _ => 0,
+    };
+    let max_work_group_size = match device.info(DeviceInfoKind::MaxWorkGroupSize)? {
+        DeviceInfoResult::MaxWorkGroupSize(size) => size,
+        _ => 1,
+    };
+    let preferred_long_width = match device.info(DeviceInfoKind::PreferredVectorWidthLong)? {
+        DeviceInfoResult::PreferredVectorWidthLong(width) => width,
+        _ => 1,
+    };
+    let extensions = device.info(DeviceInfoKind::Extensions)?.to_string();
+    let profile = device.info(DeviceInfoKind::Profile)?.to_string();
+    Ok(DeviceInfo {
+        index,
+        platform: platform.name()?,
+        name: device.name()?,
+        vendor: device.vendor()?,
+        compute_units,
+        global_memory,
+        max_work_group_size,
+        opencl_version: device.info(DeviceInfoKind::Version)?.to_string(),
+        unsupported: int64_incompatibility(&extensions, &profile, preferred_long_width),
+        pci_address: PciAddress::of_opencl_device(*device).map(|address| address.to_string()),
+    })
+}
+
+/// Why a device can't run the search kernels, which work in `ulong` throughout and keep their
+/// results with 64-bit atomics, or None if it can. `extensions` and `profile` are what the
+/// device reports as `CL_DEVICE_EXTENSIONS` and `CL_DEVICE_PROFILE`. Only embedded-profile
+/// devices may lack 64-bit integers, and say so with a preferred `long` vector width of 0.
+pub fn int64_incompatibility(extensions: &str, profile: &str, preferred_long_width: u32) -> Option<String> {
+    let extensions: Vec<&str> = extensions.split_whitespace().collect();
+    if preferred_long_width == 0 || (profile.trim() == "EMBEDDED_PROFILE" && !extensions.contains(&"cles_khr_int64")) {
+        return Some("no 64-bit integer support".into());
+    }
+    let missing: Vec<&str> = ["cl_khr_int64_base_atomics", "cl_khr_int64_extended_atomics"].into_iter()
+        .filter(|extension| !extensions.contains(extension))
+        .collect();
+    if !missing.is_empty() {
+        return Some(format!("no 64-bit atomics ({} missing)", missing.join(", ")));
+    }
+    None
+}
+
+// Enumerates every device on every platform, logging each one, and keeps those the filter selects
+fn select_devices(filter: &DeviceFilter) -> Result<Vec<(DeviceInfo, Platform, Device)>> {
+    let devices = all_devices()?;
+    let enumerated = devices.len();
+    let mut selected = vec![];
+    let mut skipped = vec![];
+    for (info, platform, device) in devices {
+        let matches = filter.matches(info.index, &info.name);
+        let address = info.pci_address.as_ref().map_or(String::new(), |address| format!(", PCI {}", address));
+        info!("Device {}: {} ({}, {}{}){}", info.index, info.name, info.platform, info.opencl_version, address, if matches { "" } else { ", excluded" });
+        match &info.unsupported {
+            Some(reason) if matches => {
+                warn!("Skipping device {}, {}: {}", info.index, info.name, reason);
+                skipped.push(format!("{} ({})", info, reason));
+            }
+            _ if matches => selected.push((info, platform, device)),
+            _ => {}
+        }
+    }
+    if selected.is_empty() {
+        return Err(if !skipped.is_empty() {
+            PrimeError::UnsupportedDevices(skipped)
+        } else if enumerated > 0 && !filter.is_empty() {
+            PrimeError::NoMatchingDevices(enumerated)
+        } else {
+            PrimeError::NoDevices
+        });
+    }
+    Ok(selected)
+}
+
+// Builds the kernels for one device, from the cached binary when there is one. A binary the
+// driver rejects is rebuilt from source, and a fresh build is written back to the cache.
+fn build_pro_que(context: Context, device: Device, properties: CommandQueueProperties, name: &str, src: &str, threads: usize, cache: Option<&KernelCache>) -> Result<ProQue> {
+    let driver = device.info(DeviceInfoKind::DriverVersion)?.to_string();
+    if let Some(binary) = cache.and_then(|cache| cache.load(name, &driver, src)) {
+        let binaries = [&binary[..]];
+        let mut program = Program::builder();
+        program.binaries(&binaries);
+        let mut builder = ProQue::builder();
+        builder.context(context.clone()).prog_bldr(program).dims(threads).device(device).queue_properties(properties);
+        match builder.build() {
+            Ok(pro_que) => return Ok(pro_que),
+            Err(e) => warn!("Ignoring the cached kernel for {}: {}", name, e),
+        }
+    }
+
+    let pro_que = build_from_source(context, device, properties, name, src, threads)?;
+    check_entry_point(&pro_que)?;
+    if let Some(cache) = cache {
+        if let ProgramInfoResult::Binaries(binaries) = pro_que.program().info(ProgramInfo::Binaries)? {
+            if let Some(binary) = binaries.first().filter(|binary| !binary.is_empty()) {
+                if let Err(e) = cache.store(name, &driver, src, binary) {
+                    warn!("Failed to cache the kernel for {}: {}", name, e);
+                }
+            }
+        }
+    }
+    Ok(pro_que)
+}
+
+// Builds the program by hand rather than through ProQue::builder so a failed build can be
+// reported with the compiler's own log for the device
+fn build_from_source(context: Context, device: Device, properties: CommandQueueProperties, name: &str, src: &str, threads: usize) -> Result<ProQue> {
+    let src = CString::new(src).map_err(|_| PrimeError::KernelSource("the source contains a NUL byte".into()))?;
+    let program = ocl::core::create_program_with_source(context.as_core(), &[src])?;
+    if let Err(e) = ocl::core::build_program(&program, Some(&[device]), &CString::default(), None, None) {
+        let log = match ocl::core::get_program_build_info(&program, device, ProgramBuildInfo::BuildLog) {
+            Ok(ProgramBuildInfoResult::BuildLog(log)) if !log.trim().is_empty() => log,
+            _ => e.to_string(),
+        };
+        return Err(PrimeError::KernelBuild { device: name.to_string(), log });
+    }
+    let queue = Queue::new(&context, device, Some(properties))?;
+    Ok(ProQue::new(context, queue, Program::from(program), Some(threads)))
+}
+
+fn supports_out_of_order(device: Device) -> Result<bool> {
+    Ok(match device.info(DeviceInfoKind::QueueProperties)? {
+        DeviceInfoResult::QueueProperties(properties) => properties.contains(CommandQueueProperties::OUT_OF_ORDER_EXEC_MODE_ENABLE),
+        _ => false,
+    })
+}
+
+// Adds a completed kernel's profiled run time to the device's total, logging each run
+pub(crate) fn record_kernel_time(report: &ReportTracker, device: usize, event: &Event) {
+    match kernel_time(event) {
+        Ok(Some(time)) => {
+            debug!("GPU {}: kernel ran for {:.3} ms on the device", device, time.as_secs_f64() * 1000.0);
+            report.add_kernel_time(device, time);
+        }
+        Ok(None) => {}
+        Err(e) => debug!("GPU {}: no profiling information: {}", device, e),
+    }
+}
+
+// How long a kernel ran on the device, from its event on a profiled queue. Nothing until it
+// has completed.
+fn kernel_time(event: &Event) -> Result<Option<Duration>> {
+    if !event.is_complete()? {
+        return Ok(None);
+    }
+    let start = match event.profiling_info(ProfilingInfo::Start)? {
+        ProfilingInfoResult::Start(start) => start,
+        _ => return Ok(None),
+    };
+    let end = match event.profiling_info(ProfilingInfo::End)? {
+        ProfilingInfoResult::End(end) => end,
+        _ => return Ok(None),
+    };
+    Ok(Some(Duration::from_nanos(end.saturating_sub(start))))
+}
+
+// Work-groups of the device's largest size on each of its compute units
+fn thread_limit(device: Device) -> Result<usize> {
+    let units = match device.info(DeviceInfoKind::MaxComputeUnits)? {
+        DeviceInfoResult::MaxComputeUnits(units) => units as usize,
+        _ => 1,
+    };
+    let group_size = match device.info(DeviceInfoKind::MaxWorkGroupSize)? {
+        DeviceInfoResult::MaxWorkGroupSize(size) => size,
+        _ => 1,
+    };
+    Ok(units.max(1).saturating_mul(group_size.max(1)))
+}
+
+// A launch's work group size as ocl takes it, unspecified to let the driver choose
+pub(crate) fn work_group(local_size: Option<usize>) -> SpatialDims {
+    local_size.map_or(SpatialDims::Unspecified, SpatialDims::One)
+}
+
+// One slot per thread holding the last candidate it tested, in memory the host can map when
+// `mapped` is set
+fn status_buffer(queue: &Queue, threads: usize, mapped: bool) -> Result<Buffer<u64>> {
+    let flags = if mapped { MemFlags::new().read_write().alloc_host_ptr() } else { MemFlags::new().read_write() };
+    Ok(Buffer::<u64>::builder().queue(queue.clone()).flags(flags).len(threads).fill_val(0u64).build()?)
+}
+
+// The global work size a kernel writing to `status_buffer` is launched with. The kernels
+// index it by thread, so a buffer of any other length would be written past its end or
+// leave slots the monitor reads as stale; it's resized with the thread count, and this
+// catches a launch where the two have come apart.
+pub(crate) fn status_work_size(i: usize, status_buffer: &Buffer<u64>, threads: usize) -> Result<usize> {
+    if status_buffer.len() != threads {
+        return Err(PrimeError::StatusBufferMismatch { device: i, len: status_buffer.len(), threads });
+    }
+    Ok(threads)
+}
+
+// Rounds a global size up to whole work groups
+fn round_up(threads: usize, local_size: Option<usize>) -> usize {
+    match local_size {
+        Some(size) => threads.div_ceil(size) * size,
+        None => threads,
+    }
+}
+
+fn clamp_threads(i: usize, name: &str, threads: usize, limit: usize) -> usize {
+    if threads <= limit {
+        return threads;
+    }
+    warn!("GPU {} ({}) runs at most {} threads at once, using that many instead of {}", i, name, limit, threads);
+    limit
+}
+
+// A custom source has to provide the kernel every search launches, taking the arguments
+// search_slices passes it. The argument types can't be queried without
+// CL_PROGRAM_BUILD_OPTIONS -cl-kernel-arg-info, so only their number is checked.
+fn check_entry_point(pro_que: &ProQue) -> Result<()> {
+    const ENTRY_POINT: &str = "search_for_large_prime";
+    const ENTRY_POINT_ARGS: u32 = 7;
+
+    let kernel = ocl::core::create_kernel(pro_que.program(), ENTRY_POINT)
+        .map_err(|_| PrimeError::KernelSource(format!("no `{}` kernel is defined", ENTRY_POINT)))?;
+    match ocl::core::get_kernel_info(&kernel, KernelInfo::NumArgs)? {
+        KernelInfoResult::NumArgs(ENTRY_POINT_ARGS) => Ok(()),
+        KernelInfoResult::NumArgs(args) => Err(PrimeError::KernelSource(format!(
+            "`{}` takes {} arguments instead of {} (start, end, algorithm, result, status, cancel, pause)",
+            ENTRY_POINT, args, ENTRY_POINT_ARGS,
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Candidates covered by threads that test first + t, first + t + threads, ... for each thread
+// t, given the last value each one recorded. Comparing offsets from `first` also works for the
+// low words the wide kernel records; threads that have not started read 0 and drop out, even
+// when the slice starts at 0.
+pub(crate) fn tested_count(status: &[u64], first: u64, len: u64) -> u64 {
+    let offsets: Vec<u64> = status.iter().map(|&last| if last == 0 { u64::MAX } else { last.wrapping_sub(first) }).collect();
+    tested_at_offsets(&offsets, len)
+}
+
+// Like tested_count for the descending kernel, whose thread t tests first - t, then
+// first - t - threads and so on
+pub(crate) fn tested_count_down(status: &[u64], first: u64, len: u64) -> u64 {
+    tested_at_offsets(&mirrored_down(status, first), len)
+}
+
+// The descending kernel's statuses as offsets below `first`. Threads that have not started
+// read 0, which would otherwise mirror to a whole stride tested when the slice starts at 0,
+// so they map past every slice instead
+fn mirrored_down(status: &[u64], first: u64) -> Vec<u64> {
+    status.iter().map(|&last| if last == 0 { u64::MAX } else { first.wrapping_sub(last) }).collect()
+}
+
+// Candidates covered given how far into the slice each thread has got, where thread t starts
+// at offset t and strides by the thread count; offsets past the slice haven't started
+fn tested_at_offsets(offsets: &[u64], len: u64) -> u64 {
+    let threads = offsets.len() as u64;
+    offsets.iter().enumerate()
+        .filter_map(|(t, &offset)| {
+            let t = t as u64;
+            (offset < len && offset >= t).then(|| (offset - t) / threads + 1)
+        })
+        .sum()
+}
+
+// Candidates left in the slice ahead of its slowest thread, which bounds how long the device
+// has to go; threads that have not started yet count as at the start. Threads t >= len have
+// nothing in the slice and never start, so they are left out
+pub(crate) fn remaining_count(status: &[u64], first: u64, len: u64) -> u64 {
+    let slowest = status.iter().enumerate()
+        .filter(|&(t, _)| (t as u64) < len)
+        .map(|(_, &last)| last.wrapping_sub(first))
+        .map(|offset| if offset < len { offset } else { 0 })
+        .min()
+        .unwrap_or(0);
+    len - slowest
+}
+
+/// Upper bound for the number of primes in `range`: `(end - start) / ln(start)` plus headroom.
+pub fn estimate_prime_count(range: &Range<u64>) -> usize {
+    let len = range.end.saturating_sub(range.start) as f64;
+    let density = 1.0 / (range.start.max(3) as f64).ln();
+    (len * density * 1.25) as usize + 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unstarted_threads_count_as_nothing_tested() {
+        // Four threads over [0, 8): thread 1 has tested 1 and 5, the others haven't started
+        assert_eq!(tested_count(&[0, 5, 0, 0], 0, 8), 2);
+        assert_eq!(tested_count(&[0; 4], 0, 8), 0);
+        // The same slice searched down from 7: thread 1 has tested 6 and 2
+        assert_eq!(tested_count_down(&[0, 2, 0, 0], 7, 8), 2);
+        assert_eq!(tested_count_down(&[0; 4], 7, 8), 0);
+        // Away from 0 every thread's progress counts
+        assert_eq!(tested_count(&[104, 101, 102, 103], 100, 8), 5);
+    }
+}
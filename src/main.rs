@@ -1,187 +1,1827 @@
-extern crate ocl;
-extern crate nvml_wrapper as nvml;
-extern crate openssl;
+extern crate clap;
+extern crate ctrlc;
+extern crate env_logger;
+extern crate indicatif;
+#[macro_use]
+extern crate log;
+extern crate opencl_primes;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
 
-use ocl::{ProQue, Buffer, MemFlags, Platform, Device, Context};
-use nvml::Nvml;
-use nvml::enum_wrappers::device::TemperatureSensor;
-use openssl::bn::BigNum;
-use std::{thread, time::Duration, sync::{Arc, Mutex}};
+#[cfg(feature = "tui")]
+mod tui;
 
-const KERNEL_SRC: &str = r#"
-    int is_prime(ulong n) {
-        if (n <= 1) return 0;
-        if (n <= 3) return 1;
-        if (n % 2 == 0 || n % 3 == 0) return 0;
-        for (ulong i = 5; i * i <= n; i += 6) {
-            if (n % i == 0 || n % (i + 2) == 0) return 0;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, error::ErrorKind, parser::ValueSource};
+use opencl_primes::{Algorithm, Backend, CancelHandle, Checkpoint, ClockSettings, Coordinator, CpuSearcher, DeviceFilter, Direction, KernelCache, LastPrime, Metrics, MetricsServer, PartitionStrategy, PowerBudget, PrimeError, PrimeSearcher, Progression, ProgressEvent, RetryPolicy, SearchReport, SearcherConfig, StatusThreads, TelemetryLog, ThermalLimit, ThreadCount, TuneResult, run_worker, write_primes_bin};
+use opencl_primes::eta::{RateEstimate, format_time_left};
+use opencl_primes::primality;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use env_logger::WriteStyle;
+use log::LevelFilter;
+use std::{fs::{self, File}, io::{self, IsTerminal, Write}, ops::Range, path::{Path, PathBuf}, process, sync::{Arc, Mutex, mpsc}, thread, time::{Duration, Instant}};
+
+const DEFAULT_START: u64 = 10_000_000_000_000;
+const DEFAULT_END: u64 = DEFAULT_START + 1_000_000_000;
+// As timeout(1) exits with when the command times out
+const EXIT_TIMEOUT: i32 = 124;
+// A device stopped responding and its monitor thread was left behind
+const EXIT_UNRESPONSIVE: i32 = 3;
+// With --quiet, when no prime was found
+const EXIT_NOT_FOUND: i32 = 4;
+
+// The bar being drawn while a search runs, which log records have to print around
+static PROGRESS_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum AlgorithmArg {
+    TrialDivision,
+    MillerRabin,
+    Wide128,
+    Bpsw,
+}
+
+impl From<AlgorithmArg> for Algorithm {
+    fn from(arg: AlgorithmArg) -> Self {
+        match arg {
+            AlgorithmArg::TrialDivision => Algorithm::TrialDivision,
+            AlgorithmArg::MillerRabin => Algorithm::MillerRabin,
+            AlgorithmArg::Wide128 => Algorithm::Wide128,
+            AlgorithmArg::Bpsw => Algorithm::Bpsw,
         }
-        return 1;
     }
+}
 
-    __kernel void test_prime_candidates(__global ulong* candidates, __global int* results, ulong num_candidates) {
-        ulong tid = get_global_id(0);
-        if (tid < num_candidates) {
-            results[tid] = is_prime(candidates[tid]);
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum PartitionArg {
+    Even,
+    Weighted,
+    /// Chunks of --chunk-size handed to whichever device is free
+    Dynamic,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DirectionArg {
+    /// From --start up, for the smallest prime
+    Up,
+    /// From --end down, for the largest prime
+    Down,
+}
+
+impl From<DirectionArg> for Direction {
+    fn from(arg: DirectionArg) -> Self {
+        match arg {
+            DirectionArg::Up => Direction::Up,
+            DirectionArg::Down => Direction::Down,
         }
     }
-"#;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum BackendArg {
+    /// OpenCL, or the CPU when no OpenCL device is found
+    Auto,
+    Opencl,
+    Cpu,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    Text,
+    Json,
+    Bin,
+}
+
+#[derive(Serialize)]
+struct JsonRange {
+    start: u64,
+    end: u64,
+}
+
+#[derive(Serialize)]
+struct JsonGpu {
+    index: usize,
+    platform: String,
+    name: String,
+    utilization: Option<u32>,
+    temperature: Option<u32>,
+    power_usage_mw: Option<u32>,
+    graphics_clock_mhz: Option<u32>,
+    memory_clock_mhz: Option<u32>,
+    tested: u64,
+    /// Candidates left in the device's slice, 0 once it finished
+    remaining: u64,
+    wall_time_secs: f64,
+    /// Run time measured on the device, with --queue-profiling
+    kernel_time_secs: Option<f64>,
+    /// Kernel runs timed into kernel_time_secs
+    kernel_launches: u64,
+    peak_temperature: Option<u32>,
+    found_prime: bool,
+    /// The last candidate tested by each of the first --status-threads threads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_statuses: Option<Vec<u64>>,
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    range: JsonRange,
+    primes: Vec<u64>,
+    elapsed_secs: f64,
+    found_by: Option<JsonDevice>,
+    timed_out: bool,
+    gpus: Vec<JsonGpu>,
+    /// With --certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificates: Option<Vec<JsonCertificate>>,
+}
+
+#[derive(Serialize)]
+struct JsonRangeResult {
+    range: JsonRange,
+    primes: Vec<u64>,
+    elapsed_secs: f64,
+    found_by: Option<JsonDevice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificates: Option<Vec<JsonCertificate>>,
+}
+
+// n - 1 = d * 2^s, and each witness a passed with a^d = 1 (squarings null) or
+// a^(d * 2^squarings) = n - 1, all mod n
+#[derive(Serialize)]
+struct JsonCertificate {
+    n: u64,
+    d: u64,
+    s: u32,
+    witnesses: Vec<JsonWitness>,
+    /// Why passing these witnesses proves n prime
+    proof: &'static str,
+}
+
+#[derive(Serialize)]
+struct JsonWitness {
+    witness: u64,
+    squarings: Option<u32>,
+}
+
+// --range given more than once
+#[derive(Serialize)]
+struct JsonRangesReport {
+    ranges: Vec<JsonRangeResult>,
+    elapsed_secs: f64,
+    timed_out: bool,
+    gpus: Vec<JsonGpu>,
+}
+
+#[derive(Serialize)]
+struct JsonIsPrime {
+    n: u64,
+    prime: bool,
+    device: JsonDevice,
+    elapsed_secs: f64,
+}
+
+#[derive(Serialize)]
+struct JsonDevice {
+    index: usize,
+    platform: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct JsonInventoryDevice {
+    index: usize,
+    platform: String,
+    name: String,
+    vendor: String,
+    opencl_version: String,
+    compute_units: u32,
+    global_memory_bytes: u64,
+    max_work_group_size: usize,
+    pci_address: Option<String>,
+    // Why the kernels can't run on the device, which searches skip
+    unsupported: Option<String>,
+    // From NVML, for NVIDIA devices
+    driver_version: Option<String>,
+    vram_bytes: Option<u64>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Measure candidates tested per second on each device without searching for a prime
+    Bench {
+        /// Seconds to time each device for
+        #[arg(long, default_value_t = 10)]
+        duration: u64,
+
+        /// Lock NVIDIA devices' graphics and memory clocks while benchmarking, as
+        /// <graphics_mhz>,<mem_mhz>; usually needs root
+        #[arg(long, value_name = "GRAPHICS,MEM", value_parser = parse_clocks)]
+        lock_clocks: Option<ClockSettings>,
+    },
+    /// List every OpenCL device, with --format json for a machine-readable inventory
+    Devices,
+    /// Benchmark each device at 256, 512, 1024 and so on threads up to its limit, and report
+    /// the fastest
+    Tune {
+        /// Seconds to time each thread count for
+        #[arg(long, default_value_t = 2)]
+        duration: u64,
+
+        /// Write the fastest --threads for all the devices together to the --config file,
+        /// replacing its threads line or adding one
+        #[arg(long)]
+        save: bool,
+    },
+    /// Test a single number on the first selected device with --algorithm
+    IsPrime {
+        /// In the same notation as --start
+        #[arg(value_parser = parse_number)]
+        n: u64,
+    },
+}
+
+/// Search a range of numbers for a prime on every available OpenCL device.
+#[derive(Parser)]
+#[command(name = "opencl-primes")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Read options from this TOML file; its keys are the long flag names, and flags given on
+    /// the command line take precedence over it
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print the options in effect, merged from --config and the command line, as TOML and exit
+    #[arg(long)]
+    dump_config: bool,
+
+    #[command(flatten)]
+    options: Config,
+}
+
+// Every option of a search, from the command line and optionally a --config file
+#[derive(clap::Args, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    /// Log more: -v for per-device progress, -vv for individual threads. RUST_LOG overrides this
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print only the primes found, one per line, and nothing but errors on stderr. Exits with
+    /// 4 when there's none
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// First number to test, like 10000000000000, 10_000_000_000_000, 1e13 or 2^43 [default: 10000000000000]
+    #[arg(long, value_parser = parse_number)]
+    #[serde(default, with = "number")]
+    start: Option<u64>,
+
+    /// End of the range (exclusive), in the same notation as --start [default: 10001000000000]
+    #[arg(long, value_parser = parse_number)]
+    #[serde(default, with = "number")]
+    end: Option<u64>,
+
+    /// Search START:END instead of --start and --end; repeat it to search several ranges one
+    /// after another on the same devices
+    #[arg(long, value_name = "START:END", value_parser = parse_range)]
+    #[serde(default, with = "range_list")]
+    range: Vec<Range<u64>>,
+
+    /// Where to search; the CPU backend always uses Miller-Rabin and ignores the device options
+    #[arg(long, value_enum, default_value_t = BackendArg::Auto)]
+    backend: BackendArg,
 
-fn generate_large_prime(bits: u32) -> BigNum {
-    let mut prime = BigNum::new().unwrap();
-    prime.generate_prime(bits as i32, false, None, None).unwrap();
-    prime
+    /// Primality test run on each candidate
+    #[arg(long, value_enum, default_value_t = AlgorithmArg::TrialDivision)]
+    algorithm: AlgorithmArg,
+
+    /// Kernel threads per device: a number, or auto to size from the device's compute units
+    #[arg(long, default_value = "1024", value_parser = parse_thread_count)]
+    #[serde(with = "thread_count")]
+    threads: ThreadCount,
+
+    /// Work-items per work group, up to the device's maximum; the driver chooses by default
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    local_size: Option<usize>,
+
+    /// How the range is split between devices
+    #[arg(long, value_enum, default_value_t = PartitionArg::Even)]
+    partition: PartitionArg,
+
+    /// Numbers per chunk with --partition dynamic, and per chunk handed to each worker with
+    /// --coordinate
+    #[arg(long, default_value_t = 1 << 24, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+
+    /// Don't search here, but hand the range out in chunks over TCP at this address, like
+    /// 0.0.0.0:7878, to processes run with --worker. There is no authentication
+    #[arg(long, value_name = "ADDR")]
+    coordinate: Option<String>,
+
+    /// Search the chunks handed out by the --coordinate process at this address, until it has
+    /// none left; --start and --end are ignored
+    #[arg(long, value_name = "ADDR")]
+    worker: Option<String>,
+
+    /// Search for twin primes (p, p + 2) instead of a single prime
+    #[arg(long)]
+    twin: bool,
+
+    /// Once a prime is found, also list this many primes after it, up to --end
+    #[arg(long)]
+    find_next: Option<usize>,
+
+    /// Seed for anything random in the search, such as the witnesses of probabilistic tests;
+    /// taken from the clock and logged without it
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Only test the numbers A*k + B, for the smallest prime of that form; A and B must be
+    /// coprime
+    #[arg(long, value_name = "A:B", value_parser = parse_progression)]
+    #[serde(default, with = "progression")]
+    progression: Option<Progression>,
+
+    /// Which end of the range to search from; down finds the largest prime below --end
+    #[arg(long, value_enum, default_value_t = DirectionArg::Up)]
+    direction: DirectionArg,
+
+    /// Only search on the device at this position in the enumeration; repeatable
+    #[arg(long = "device")]
+    devices: Vec<usize>,
+
+    /// Only search on devices whose name contains this (case-insensitive)
+    #[arg(long)]
+    device_name: Option<String>,
+
+    /// List the selected devices and the slice each would search, then exit without searching
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Build the kernels from the OpenCL C in this file instead of the built-in source
+    #[arg(long)]
+    kernel: Option<PathBuf>,
+
+    /// Directory for compiled kernel binaries. Without it, $OPENCL_PRIMES_CACHE_DIR, then on
+    /// Linux $XDG_CACHE_HOME/opencl-primes, then the platform's cache directory, such as
+    /// ~/.cache/opencl-primes
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Always compile the kernels from source, without reading or writing the cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Delete the cached kernel binaries before starting
+    #[arg(long)]
+    clear_cache: bool,
+
+    /// Disable GPU utilization, temperature, power and clock monitoring
+    #[arg(long)]
+    no_monitor: bool,
+
+    /// Milliseconds between reads of each device's status and result buffers
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    poll_interval: u64,
+
+    /// Seconds between GPU utilization, temperature, power and clock readings
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    monitor_interval: u64,
+
+    /// Create the command queues with profiling enabled and report how long the kernels ran
+    /// on each device
+    #[arg(long)]
+    queue_profiling: bool,
+
+    /// Run the kernels on out-of-order command queues, ordering them against their buffer
+    /// transfers with events; compare the two with bench. In-order queues are the default
+    #[arg(long)]
+    out_of_order: bool,
+
+    /// Keep the thread status buffers in host-accessible memory and map them on each poll
+    /// instead of copying them; bench shows the time each status read takes
+    #[arg(long)]
+    mapped_status: bool,
+
+    /// Seconds a device may go without answering a status poll before the search gives up on it
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    watchdog_timeout: u64,
+
+    /// Attempts at a buffer read or kernel launch that fails for lack of resources, with
+    /// doubling waits in between; 1 disables retries
+    #[arg(long, default_value_t = RetryPolicy::default().max_attempts, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Serve Prometheus metrics at http://0.0.0.0:<port>/metrics while searching
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    /// Append each GPU reading, every --monitor-interval, to this CSV file
+    #[arg(long, value_name = "PATH")]
+    telemetry_csv: Option<PathBuf>,
+
+    /// Pause a device while its temperature is above this many °C
+    #[arg(long)]
+    max_temp: Option<u32>,
+
+    /// How many °C below --max-temp a paused device must cool before it resumes
+    #[arg(long, default_value_t = ThermalLimit::DEFAULT_HYSTERESIS)]
+    temp_hysteresis: u32,
+
+    /// Pause devices in turn while together they draw more than this many watts
+    #[arg(long)]
+    power_budget: Option<u32>,
+
+    /// How many watts below --power-budget the draw must fall before a paused device resumes
+    #[arg(long, default_value_t = PowerBudget::DEFAULT_HYSTERESIS)]
+    power_hysteresis: u32,
+
+    /// Re-check every prime found on the CPU before reporting it (the default)
+    #[arg(long, overrides_with = "no_verify")]
+    verify: bool,
+
+    /// Report primes without re-checking them on the CPU
+    #[arg(long, overrides_with = "verify")]
+    no_verify: bool,
+
+    /// Check each device's kernel against known primes and composites before searching (the default)
+    #[arg(long, overrides_with = "no_self_check")]
+    self_check: bool,
+
+    /// Start searching without checking the kernels first
+    #[arg(long, overrides_with = "self_check")]
+    no_self_check: bool,
+
+    /// Output format; json prints a single report and bin writes each prime as a little-endian
+    /// u64 with nothing else, and both suppress the status output
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Write the result to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Add a primality certificate for each prime found to the --format json report: the
+    /// Miller-Rabin round every deterministic witness passed
+    #[arg(long)]
+    certificate: bool,
+
+    /// Print per-thread status instead of a progress bar
+    #[arg(long)]
+    no_progress: bool,
+
+    /// How many of each device's threads the status lists, shown with -vv: a number, all, or
+    /// 0 for none. --format json reports as many in each GPU's thread_statuses
+    #[arg(long, default_value = "10", value_parser = parse_status_threads)]
+    #[serde(with = "status_threads")]
+    status_threads: StatusThreads,
+
+    /// Show a live dashboard of every device instead of the progress bar; q stops the search.
+    /// Needs a build with the tui feature
+    #[arg(long)]
+    tui: bool,
+
+    /// Periodically save the search position to this file
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Seconds between checkpoint writes
+    #[arg(long, default_value_t = 60)]
+    checkpoint_interval: u64,
+
+    /// Continue the search saved in this checkpoint, which keeps being updated unless
+    /// --checkpoint points elsewhere
+    #[arg(long)]
+    resume: Option<PathBuf>,
+
+    /// Keep finding bigger primes: start just after the last prime saved in this file and
+    /// search to --end or the end saved with it, then save the largest prime found back to it.
+    /// Without the file the search covers --start to --end and creates it
+    #[arg(long, value_name = "FILE")]
+    r#continue: Option<PathBuf>,
+
+    /// Stop searching after this many seconds, exiting with status 124 if nothing was found
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+}
+
+// How a search came to an end
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Finished,
+    Interrupted,
+    TimedOut,
+    // Only with --quiet, which reports an empty result through the exit code
+    NotFound,
 }
 
 fn main() {
-    // Initialize NVML for GPU monitoring
-    let nvml = Arc::new(Nvml::init().expect("Failed to initialize NVML"));
-
-    // List available platforms and devices
-    let platforms = Platform::list();
-    println!("Available platforms:");
-    for platform in &platforms {
-        println!("Platform: {}", platform.name().unwrap());
-        let devices = Device::list_all(&*platform).unwrap();
-        for device in &devices {
-            println!("  Device: {}", device.name().unwrap());
-        }
-    }
-
-    // Create ProQue for each device
-    let mut pro_ques: Vec<Arc<ProQue>> = vec![];
-    for platform in &platforms {
-        let devices = Device::list_all(&*platform).unwrap();
-        for device in devices {
-            let max_threads = 64; // Further reduce the number of threads to avoid resource exhaustion
-
-            // Create a context for the specific platform and device
-            let context = Context::builder()
-                .platform(*platform)
-                .devices(device.clone())
-                .build()
-                .expect("Failed to create context");
-
-            let pro_que = ProQue::builder()
-                .context(context)
-                .src(KERNEL_SRC)
-                .dims(max_threads) // Use the further reduced number of threads
-                .device(device)
-                .build()
-                .expect("Failed to create ProQue");
-            pro_ques.push(Arc::new(pro_que));
-        }
-    }
-
-    let num_candidates = 100; // Number of prime candidates to generate and test
-    let prime_candidates: Vec<u64> = (0..num_candidates)
-        .map(|_| generate_large_prime(64).to_dec_str().unwrap().parse::<u64>().unwrap())
-        .collect(); // Generate 64-bit prime candidates
-    let prime_candidates = Arc::new(prime_candidates);
-
-    let candidates_buffers: Vec<_> = pro_ques.iter().map(|pq| {
-        Arc::new(Buffer::<u64>::builder()
-            .queue(pq.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(num_candidates as usize) // Use usize instead of u64
-            .copy_host_slice(&prime_candidates)
-            .build()
-            .expect("Failed to create candidates buffer"))
-    }).collect();
-
-    let results_buffers: Vec<_> = pro_ques.iter().map(|pq| {
-        Arc::new(Buffer::<i32>::builder()
-            .queue(pq.queue().clone())
-            .flags(MemFlags::new().write_only())
-            .len(num_candidates as usize) // Use usize instead of u64
-            .build()
-            .expect("Failed to create results buffer"))
-    }).collect();
-
-    let kernels: Vec<_> = pro_ques.iter().zip(candidates_buffers.iter()).zip(results_buffers.iter()).map(|((pq, cb), rb)| {
-        pq.kernel_builder("test_prime_candidates")
-            .arg(&**cb) // Dereference Arc
-            .arg(&**rb) // Dereference Arc
-            .arg(num_candidates as u64)
-            .build()
-            .expect("Failed to create kernel")
-    }).collect();
-
-    // Execute the kernels
-    println!("Starting computation...");
-
-    for kernel in &kernels {
-        unsafe {
-            kernel.enq().expect("Failed to execute kernel");
-        }
-    }
-
-    let prime_found = Arc::new(Mutex::new(false));
-
-    // Periodically read the status buffer to monitor thread status and GPU utilization
-    let mut threads = vec![];
-    for (i, (_pq, result_buffer)) in pro_ques.iter().zip(results_buffers.iter()).enumerate() {
-        let prime_found = Arc::clone(&prime_found);
-        let nvml = Arc::clone(&nvml);
-        let result_buffer: Arc<Buffer<i32>> = Arc::clone(result_buffer); // Explicitly specify the type
-        let prime_candidates = Arc::clone(&prime_candidates); // Clone the Arc for each thread
-
-        threads.push(thread::spawn(move || {
-            let sleep_duration = Duration::from_secs(1);
-            let mut elapsed_time = 0;
-            let mut results = vec![0i32; num_candidates as usize]; // Number of threads
-
-            loop {
-                if *prime_found.lock().unwrap() {
-                    break;
-                }
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = match &cli.config {
+        Some(path) => merge_config_file(path, cli.options, &matches),
+        None => cli.options,
+    };
+    if cli.dump_config {
+        print!("{}", toml::to_string(&config).expect("the options serialize as TOML"));
+        return;
+    }
 
-                // Read the results buffer
-                match result_buffer.read(&mut results).enq() {
-                    Ok(_) => {
-                        // Print the results of a few threads
-                        println!("Prime test results for GPU {}:", i);
-                        for j in 0..10.min(results.len()) {
-                            println!("  Candidate {}: {}", j, results[j]);
-                        }
-
-                        // Check if a prime was found
-                        for (j, &res) in results.iter().enumerate() {
-                            if res == 1 {
-                                println!("Prime found by GPU {}: {}", i, prime_candidates[j]);
-                                *prime_found.lock().unwrap() = true;
-                                break;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        println!("Failed to read result buffer: {:?}", e);
-                        break;
-                    }
-                }
+    let level = match config.verbose {
+        _ if config.quiet => LevelFilter::Error,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    // Records are piped through LogWriter, which env_logger can't tell is a terminal
+    let style = if io::stderr().is_terminal() { WriteStyle::Always } else { WriteStyle::Never };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .write_style(style)
+        .parse_default_env()
+        .target(env_logger::Target::Pipe(Box::new(LogWriter)))
+        .init();
 
-                // Monitor GPU utilization and temperature every 10 seconds
-                if elapsed_time % 10 == 0 {
-                    let device = nvml.device_by_index(i as u32).expect("Failed to get device");
-                    let utilization = device.utilization_rates().expect("Failed to get utilization rates");
-                    let temperature = device.temperature(TemperatureSensor::Gpu).expect("Failed to get temperature");
+    match run(cli.command, config, cli.config.as_deref()) {
+        Ok(Outcome::TimedOut) => process::exit(EXIT_TIMEOUT),
+        Ok(Outcome::NotFound) => process::exit(EXIT_NOT_FOUND),
+        Ok(_) => {}
+        Err(e) => {
+            error!("{}", e);
+            process::exit(if matches!(e, PrimeError::Unresponsive(_)) { EXIT_UNRESPONSIVE } else { 1 });
+        }
+    }
+}
 
-                    println!("GPU {}: Utilization: {}%, Temperature: {}°C", i, utilization.gpu, temperature);
-                }
+fn run(command: Option<Command>, config: Config, config_path: Option<&Path>) -> Result<Outcome, PrimeError> {
+    if command.is_some() && config.format == Format::Bin {
+        Cli::command().error(ErrorKind::InvalidValue, "--format bin is only for searches").exit();
+    }
+    match command {
+        Some(Command::Bench { duration, lock_clocks }) => {
+            bench(&config, Duration::from_secs(duration), lock_clocks)?;
+            return Ok(Outcome::Finished);
+        }
+        Some(Command::Devices) => {
+            devices(&config)?;
+            return Ok(Outcome::Finished);
+        }
+        Some(Command::Tune { duration, save }) => {
+            let save = save.then(|| config_path.unwrap_or_else(|| {
+                Cli::command().error(ErrorKind::MissingRequiredArgument, "tune --save writes to the --config file, so it needs one").exit()
+            }));
+            tune(&config, Duration::from_secs(duration), save)?;
+            return Ok(Outcome::Finished);
+        }
+        Some(Command::IsPrime { n }) => return is_prime(&config, n),
+        None => {}
+    }
+
+    if config.direction == DirectionArg::Down {
+        check_descending(&config);
+    }
+    if config.twin && config.find_next.is_some() {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--find-next can't be used with --twin").exit();
+    }
+    if config.progression.is_some() {
+        check_progression(&config);
+    }
+    if config.certificate && config.format != Format::Json {
+        warn!("--certificate only adds to the --format json report");
+    }
+    if !config.range.is_empty() {
+        check_ranges(&config);
+    }
+    if config.r#continue.is_some() {
+        check_continue(&config);
+    }
+    if config.coordinate.is_some() {
+        check_distributed(&config, "--coordinate");
+    }
+    if config.worker.is_some() {
+        check_distributed(&config, "--worker");
+    }
+    if let Some(addr) = &config.worker {
+        return work(&config, addr);
+    }
+    if config.range.len() > 1 {
+        return search_ranges(&config);
+    }
+    let resume = config.resume.as_deref().map(Checkpoint::load).transpose()?;
+    let last_prime = config.r#continue.as_deref().map(LastPrime::load).transpose()?.flatten();
+    let range = match (&resume, &last_prime) {
+        _ if config.range.len() == 1 => config.range[0].clone(),
+        (_, Some(last)) => last.next_range(config.start, config.end)?,
+        // Without explicit bounds a resumed run continues the checkpointed range
+        (Some(checkpoint), _) if config.start.is_none() && config.end.is_none() => checkpoint.start..checkpoint.end,
+        _ => config.start.unwrap_or(DEFAULT_START)..config.end.unwrap_or(DEFAULT_END),
+    };
+    if let Some(msg) = range_error(&range) {
+        Cli::command().error(ErrorKind::ValueValidation, msg).exit();
+    }
+    if let Some(checkpoint) = &resume {
+        checkpoint.check_range(&range)?;
+    }
+    // The part of the range still to be searched
+    let remaining = resume.as_ref().map_or(range.start, |checkpoint| checkpoint.next)..range.end;
+
+    let text = config.format == Format::Text && !config.quiet;
+    if text {
+        println!("Searching range [{}, {})", range.start, range.end);
+        if let Some(progression) = config.progression {
+            println!("Only testing numbers of the form {}", progression);
+        }
+        if let Some(last) = &last_prime {
+            println!("Continuing after {}", last.prime);
+        }
+        if remaining.start != range.start {
+            println!("Resuming from {}", remaining.start);
+        }
+    }
 
-                thread::sleep(sleep_duration);
-                elapsed_time += 1;
+    if config.dry_run {
+        dry_run(&config, remaining)?;
+        return Ok(Outcome::Finished);
+    }
+    if config.tui && !cfg!(feature = "tui") {
+        Cli::command().error(ErrorKind::InvalidValue, "--tui needs a build with the tui feature").exit();
+    }
+    if let Some(addr) = &config.coordinate {
+        return coordinate(&config, addr, &range);
+    }
+
+    let (backend, bar, metrics_server) = session(&config, resume.as_ref(), &range, &remaining)?;
+
+    cancel_on_ctrlc(backend.cancel_handle());
+
+    if text {
+        println!("Starting computation...");
+    }
+
+    let timer = config.timeout.map(|secs| Timer::cancel_after(backend.cancel_handle(), Duration::from_secs(secs)));
+    let search = || search_range(&config, &*backend, &remaining);
+    #[cfg(feature = "tui")]
+    let (primes, search_report) = if config.tui { tui::show_while(&*backend, &remaining, search)?? } else { search()? };
+    #[cfg(not(feature = "tui"))]
+    let (primes, search_report) = search()?;
+    let timed_out = timer.is_some_and(Timer::stop);
+    if let Some(bar) = &bar {
+        bar.finish();
+    }
+    if let Some(server) = metrics_server {
+        server.shutdown();
+    }
+    // The timer may fire just as a prime is found, which then still counts
+    let outcome = if timed_out && primes.is_empty() {
+        Outcome::TimedOut
+    } else if backend.cancel_handle().is_cancelled() {
+        Outcome::Interrupted
+    } else {
+        Outcome::Finished
+    };
+
+    if let (Some(path), Some(&prime)) = (&config.r#continue, primes.iter().max()) {
+        let start = last_prime.as_ref().map_or(range.start, |last| last.start);
+        LastPrime { prime, start, end: range.end }.save(path)?;
+        info!("Saved {} to {} for the next --continue", prime, path.display());
+    }
+
+    let devices = backend.devices();
+    print_result(&config, &range, &primes, outcome, &search_report, json_gpus(&config, &*backend, &search_report))?;
+    if !text {
+        return Ok(quiet_outcome(&config, primes.is_empty(), outcome));
+    }
+
+    if outcome != Outcome::Finished {
+        let progress = backend.progress();
+        for (device, highest) in devices.iter().zip(&progress) {
+            println!("  {} reached {}", device.name, highest);
+        }
+        match config.direction {
+            _ if outcome != Outcome::TimedOut => {}
+            DirectionArg::Up => println!("Highest tested: {}", progress.iter().max().unwrap_or(&remaining.start)),
+            DirectionArg::Down => println!("Lowest tested: {}", progress.iter().filter(|&&n| n != 0).min().unwrap_or(&remaining.end)),
+        }
+    }
+
+    print_summary(&search_report);
+    println!("Computation finished.");
+    Ok(outcome)
+}
+
+// --continue carries one search on from its last prime, so neither a checkpoint's position
+// nor several ranges or searching down fit in
+fn check_continue(config: &Config) {
+    let conflict = if !config.range.is_empty() {
+        Some("--range")
+    } else if config.resume.is_some() {
+        Some("--resume")
+    } else if config.twin {
+        Some("--twin")
+    } else if config.direction == DirectionArg::Down {
+        Some("--direction down")
+    } else {
+        None
+    };
+    if let Some(option) = conflict {
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("--continue can't be used with {}", option)).exit();
+    }
+}
+
+// The coordinator and its workers look for the smallest prime, chunk by chunk and without
+// checkpoints
+fn check_distributed(config: &Config, option: &str) {
+    let conflict = if config.coordinate.is_some() && config.worker.is_some() {
+        Some("--worker")
+    } else if config.twin {
+        Some("--twin")
+    } else if config.find_next.is_some() {
+        Some("--find-next")
+    } else if config.progression.is_some() {
+        Some("--progression")
+    } else if config.direction == DirectionArg::Down {
+        Some("--direction down")
+    } else if config.range.len() > 1 {
+        Some("more than one --range")
+    } else if config.checkpoint.is_some() {
+        Some("--checkpoint")
+    } else if config.resume.is_some() {
+        Some("--resume")
+    } else if config.r#continue.is_some() {
+        Some("--continue")
+    } else if config.tui {
+        Some("--tui")
+    } else {
+        None
+    };
+    if let Some(conflict) = conflict {
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("{} can't be used with {}", option, conflict)).exit();
+    }
+}
+
+// --coordinate: the workers search the range, and the smallest prime they find is the result
+fn coordinate(config: &Config, addr: &str, range: &Range<u64>) -> Result<Outcome, PrimeError> {
+    let coordinator = Coordinator::bind(addr, range.clone(), config.chunk_size)?;
+    let text = config.format == Format::Text && !config.quiet;
+    if text {
+        println!("Handing out chunks of {} to workers at {}", config.chunk_size, coordinator.local_addr()?);
+    }
+    let cancel = CancelHandle::without_kernels();
+    cancel_on_ctrlc(cancel.clone());
+    let timer = config.timeout.map(|secs| Timer::cancel_after(cancel.clone(), Duration::from_secs(secs)));
+    let started = Instant::now();
+    let primes: Vec<u64> = coordinator.run(&cancel)?.into_iter().collect();
+    let timed_out = timer.is_some_and(Timer::stop);
+    let outcome = if timed_out && primes.is_empty() {
+        Outcome::TimedOut
+    } else if cancel.is_cancelled() && primes.is_empty() {
+        Outcome::Interrupted
+    } else {
+        Outcome::Finished
+    };
+    if outcome != Outcome::Finished && text {
+        println!("{} chunk(s) were not searched", coordinator.chunks_left());
+    }
+    let report = SearchReport { devices: vec![], elapsed: started.elapsed(), found_by: None };
+    print_result(config, range, &primes, outcome, &report, vec![])?;
+    if text {
+        println!("Computation finished.");
+    }
+    Ok(quiet_outcome(config, primes.is_empty(), outcome))
+}
+
+// --worker: the devices search whatever chunks the coordinator hands out
+fn work(config: &Config, addr: &str) -> Result<Outcome, PrimeError> {
+    // The searcher's own range only sets out the device slices, which each chunk is split into
+    let range = config.start.unwrap_or(DEFAULT_START)..config.end.unwrap_or(DEFAULT_END);
+    let (backend, _, metrics_server) = session(config, None, &range, &range)?;
+    cancel_on_ctrlc(backend.cancel_handle());
+    let text = config.format == Format::Text && !config.quiet;
+    if text {
+        println!("Working for the coordinator at {}", addr);
+    }
+    let searched = run_worker(addr, &*backend)?;
+    if let Some(server) = metrics_server {
+        server.shutdown();
+    }
+    if text {
+        println!("Searched {} chunk(s)", searched);
+    }
+    Ok(if backend.cancel_handle().is_cancelled() { Outcome::Interrupted } else { Outcome::Finished })
+}
+
+// A progression search finds one prime upwards, among its terms only
+fn check_progression(config: &Config) {
+    let conflict = if config.twin {
+        Some("--twin")
+    } else if config.find_next.is_some() {
+        Some("--find-next")
+    } else if config.direction == DirectionArg::Down {
+        Some("--direction down")
+    } else {
+        None
+    };
+    if let Some(option) = conflict {
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("--progression can't be used with {}", option)).exit();
+    }
+}
+
+// Only the OpenCL backend searches down, one prime at a time over static slices, without
+// checkpoints
+fn check_descending(config: &Config) {
+    let conflict = if config.twin {
+        Some("--twin")
+    } else if config.backend == BackendArg::Cpu {
+        Some("--backend cpu")
+    } else if matches!(config.partition, PartitionArg::Dynamic) {
+        Some("--partition dynamic")
+    } else if config.checkpoint.is_some() {
+        Some("--checkpoint")
+    } else if config.resume.is_some() {
+        Some("--resume")
+    } else if config.find_next.is_some() {
+        Some("--find-next")
+    } else {
+        None
+    };
+    if let Some(option) = conflict {
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("--direction down can't be used with {}", option)).exit();
+    }
+}
+
+// Cancels a search once its time is up, from a thread of its own so the deadline doesn't
+// depend on the poll interval
+struct Timer {
+    done: mpsc::Sender<()>,
+    thread: thread::JoinHandle<bool>,
+}
+
+impl Timer {
+    fn cancel_after(cancel: CancelHandle, timeout: Duration) -> Self {
+        let (done, finished) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            // The search hangs up once it finishes
+            if finished.recv_timeout(timeout) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return false;
             }
-        }));
+            warn!("No result after {} s, stopping search...", timeout.as_secs());
+            if let Err(e) = cancel.cancel() {
+                error!("Failed to stop kernels: {}", e);
+            }
+            true
+        });
+        Timer { done, thread }
+    }
+
+    // Whether the search was cancelled by the timer
+    fn stop(self) -> bool {
+        drop(self.done);
+        self.thread.join().unwrap()
     }
+}
 
-    for t in threads {
-        t.join().unwrap();
+// --range given more than once. The backend is set up for the first range and searches the
+// others through the same devices, so the kernels are only built once.
+fn search_ranges(config: &Config) -> Result<Outcome, PrimeError> {
+    let text = config.format == Format::Text && !config.quiet;
+    if text {
+        println!("Searching {} ranges", config.range.len());
+    }
+    let first = &config.range[0];
+    let (backend, _, metrics_server) = session(config, None, first, first)?;
+    cancel_on_ctrlc(backend.cancel_handle());
+    if text {
+        println!("Starting computation...");
     }
 
-    if !*prime_found.lock().unwrap() {
-        println!("No prime found in the range.");
+    let timer = config.timeout.map(|secs| Timer::cancel_after(backend.cancel_handle(), Duration::from_secs(secs)));
+    let mut results = vec![];
+    let mut total: Option<SearchReport> = None;
+    for range in &config.range {
+        if backend.cancel_handle().is_cancelled() {
+            break;
+        }
+        let (primes, report) = search_range(config, &*backend, range)?;
+        match &mut total {
+            Some(total) => total.merge(&report),
+            None => total = Some(report.clone()),
+        }
+        results.push((range.clone(), primes, report));
+    }
+    let timed_out = timer.is_some_and(Timer::stop);
+    if let Some(server) = metrics_server {
+        server.shutdown();
     }
+    let outcome = if timed_out {
+        Outcome::TimedOut
+    } else if backend.cancel_handle().is_cancelled() {
+        Outcome::Interrupted
+    } else {
+        Outcome::Finished
+    };
+    let total = total.unwrap_or_else(|| backend.report());
+    let found = results.iter().any(|(_, primes, _)| !primes.is_empty());
 
+    let mut out = output(config)?;
+    if config.format == Format::Json {
+        let report = JsonRangesReport {
+            ranges: results.iter().map(|(range, primes, report)| JsonRangeResult {
+                range: JsonRange { start: range.start, end: range.end },
+                primes: primes.clone(),
+                elapsed_secs: report.elapsed.as_secs_f64(),
+                found_by: report.found_by.as_ref().map(|device| JsonDevice {
+                    index: device.index,
+                    platform: device.platform.clone(),
+                    name: device.name.clone(),
+                }),
+                certificates: config.certificate.then(|| json_certificates(primes)),
+            }).collect(),
+            elapsed_secs: total.elapsed.as_secs_f64(),
+            timed_out: outcome == Outcome::TimedOut,
+            gpus: json_gpus(config, &*backend, &total),
+        };
+        serde_json::to_writer_pretty(&mut out, &report).map_err(io::Error::from)?;
+        writeln!(out)?;
+        return Ok(quiet_outcome(config, !found, outcome));
+    }
+    if config.format == Format::Bin {
+        for (_, primes, _) in &results {
+            write_primes_bin(&mut out, primes)?;
+        }
+        return Ok(quiet_outcome(config, !found, outcome));
+    }
+    if config.quiet {
+        for (_, primes, _) in &results {
+            print_bare(&mut *out, primes)?;
+        }
+        return Ok(quiet_outcome(config, !found, outcome));
+    }
+    for (i, (range, primes, _)) in results.iter().enumerate() {
+        writeln!(out, "Range [{}, {}):", range.start, range.end)?;
+        // Only the last range searched can have been cut short
+        let outcome = if i + 1 == results.len() { outcome } else { Outcome::Finished };
+        print_primes(&mut *out, config, range, primes, outcome)?;
+    }
+    for range in &config.range[results.len()..] {
+        writeln!(out, "Range [{}, {}): not searched", range.start, range.end)?;
+    }
+    print_summary(&total);
     println!("Computation finished.");
+    Ok(outcome)
+}
+
+// One range's search, with --twin or --find-next applied. The report describes the search
+// for the first prime, which --find-next's searches would replace.
+fn search_range(config: &Config, backend: &dyn Backend, range: &Range<u64>) -> Result<(Vec<u64>, SearchReport), PrimeError> {
+    if config.twin {
+        let primes = backend.find_twin(range.clone())?.map_or(vec![], |(p, q)| vec![p, q]);
+        return Ok((primes, backend.report()));
+    }
+    if let Some(progression) = config.progression {
+        let primes = backend.find_in_progression(range.clone(), progression)?.into_iter().collect();
+        return Ok((primes, backend.report()));
+    }
+    let mut primes: Vec<u64> = backend.find_first(range.clone())?.into_iter().collect();
+    let report = backend.report();
+    if let (Some(count), Some(&prime)) = (config.find_next, primes.first()) {
+        primes.extend(backend.find_next(prime, count, range.end)?);
+    }
+    Ok((primes, report))
+}
+
+// Several ranges run one after another, so options that follow a single range don't apply
+fn check_ranges(config: &Config) {
+    let conflict = if config.start.is_some() {
+        Some("--start")
+    } else if config.end.is_some() {
+        Some("--end")
+    } else if config.range.len() == 1 {
+        None
+    } else if config.checkpoint.is_some() {
+        Some("--checkpoint")
+    } else if config.resume.is_some() {
+        Some("--resume")
+    } else if config.tui {
+        Some("--tui")
+    } else if config.dry_run {
+        Some("--dry-run")
+    } else {
+        None
+    };
+    if let Some(option) = conflict {
+        let ranges = if config.range.len() == 1 { "--range" } else { "more than one --range" };
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("{} can't be used with {}", ranges, option)).exit();
+    }
+}
+
+// The backend a search runs on, with the progress bar and metrics server attached to it
+type Session = (Box<dyn Backend>, Option<ProgressBar>, Option<MetricsServer>);
+
+// The backend --backend asks for, falling back to the CPU with auto
+fn session(config: &Config, resume: Option<&Checkpoint>, range: &Range<u64>, remaining: &Range<u64>) -> Result<Session, PrimeError> {
+    match config.backend {
+        BackendArg::Cpu => cpu_backend(config, remaining),
+        _ => match opencl_backend(config, resume, range, remaining) {
+            Err(PrimeError::NoDevices) if config.backend == BackendArg::Auto && config.direction == DirectionArg::Down => {
+                Err(PrimeError::Unsupported("no OpenCL devices found, and the CPU backend can't search down".into()))
+            }
+            Err(PrimeError::NoDevices) if config.backend == BackendArg::Auto => {
+                warn!("No OpenCL devices found, searching on the CPU instead");
+                cpu_backend(config, remaining)
+            }
+            Err(e @ PrimeError::UnsupportedDevices(_)) if config.backend == BackendArg::Auto && config.direction == DirectionArg::Up => {
+                warn!("{}, searching on the CPU instead", e);
+                cpu_backend(config, remaining)
+            }
+            backend => backend,
+        },
+    }
+}
+
+fn json_gpus(config: &Config, backend: &dyn Backend, report: &SearchReport) -> Vec<JsonGpu> {
+    let statuses = backend.thread_statuses();
+    backend.devices().iter().zip(backend.gpu_stats()).zip(&report.devices).zip(&statuses).map(|(((device, stats), done), statuses)| JsonGpu {
+        index: device.index,
+        platform: device.platform.clone(),
+        name: device.name.clone(),
+        utilization: stats.and_then(|s| s.utilization),
+        temperature: stats.map(|s| s.temperature),
+        power_usage_mw: stats.and_then(|s| s.power_usage),
+        graphics_clock_mhz: stats.and_then(|s| s.graphics_clock),
+        memory_clock_mhz: stats.and_then(|s| s.memory_clock),
+        tested: done.tested,
+        remaining: done.remaining,
+        wall_time_secs: done.wall_time.as_secs_f64(),
+        kernel_time_secs: done.kernel_time.map(|time| time.as_secs_f64()),
+        kernel_launches: done.kernel_launches,
+        peak_temperature: done.peak_temperature,
+        found_prime: done.found_prime,
+        thread_statuses: Some(config.status_threads.of(statuses).to_vec()).filter(|statuses| !statuses.is_empty()),
+    }).collect()
+}
+
+// Sets up every OpenCL device the filter selects to search `remaining`
+fn opencl_backend(config: &Config, resume: Option<&Checkpoint>, range: &Range<u64>, remaining: &Range<u64>) -> Result<Session, PrimeError> {
+    let text = config.format == Format::Text && !config.quiet;
+    let setup = searcher_config(config)?;
+    let mut searcher = match resume {
+        Some(checkpoint) => PrimeSearcher::from_checkpoint_with_config(checkpoint, &setup)?,
+        None => PrimeSearcher::new_with_config(range.clone(), &setup)?,
+    };
+    if let Some(path) = config.checkpoint.as_ref().or(config.resume.as_ref()) {
+        searcher = searcher.with_checkpoint(path, Duration::from_secs(config.checkpoint_interval));
+    }
+    if let Some(seed) = config.seed {
+        searcher = searcher.with_seed(seed);
+    }
+    info!("Seed: {}", searcher.seed());
+    let mut searcher = searcher
+        .with_algorithm(config.algorithm.into())
+        .with_partition_strategy(partition_strategy(config))
+        .with_direction(config.direction.into())
+        .with_thread_count(config.threads)?
+        .with_local_size(config.local_size)?
+        .with_monitoring(!config.no_monitor)
+        .with_poll_interval(Duration::from_millis(config.poll_interval))
+        .with_monitor_interval(Duration::from_secs(config.monitor_interval))
+        .with_watchdog_timeout(Duration::from_secs(config.watchdog_timeout))
+        .with_retry_policy(RetryPolicy::new(config.max_attempts))
+        .with_verification(config.verify || !config.no_verify)
+        .with_status_output(text && !config.tui)
+        .with_status_threads(config.status_threads);
+    if let Some(max_temp) = config.max_temp {
+        searcher = searcher.with_thermal_limit(ThermalLimit::new(max_temp).with_hysteresis(config.temp_hysteresis));
+    }
+    if let Some(watts) = config.power_budget {
+        searcher = searcher.with_power_budget(PowerBudget::new(watts).with_hysteresis(config.power_hysteresis));
+    }
+    if config.self_check || !config.no_self_check {
+        searcher.self_check()?;
+        info!("Self-check passed on {} devices", searcher.devices().len());
+    }
+
+    // Stopped when the search finishes
+    let metrics_server = match config.metrics_port {
+        Some(port) => {
+            let metrics = Arc::new(Metrics::new());
+            searcher = searcher.with_metrics(Arc::clone(&metrics));
+            let server = MetricsServer::start(port, metrics)?;
+            info!("Serving metrics on port {}", port);
+            Some(server)
+        }
+        None => None,
+    };
+    if let Some(path) = &config.telemetry_csv {
+        if config.no_monitor {
+            warn!("--telemetry-csv records nothing with --no-monitor");
+        }
+        searcher = searcher.with_telemetry(Arc::new(TelemetryLog::create(path)?));
+    }
+
+    // The bar replaces the per-thread status lines, which would otherwise tear it
+    let bar = (text && !config.no_progress && !config.tui && config.range.len() < 2 && config.worker.is_none() && io::stderr().is_terminal()).then(|| progress_bar(remaining.end - remaining.start));
+    if let Some(bar) = &bar {
+        let on_progress = track_progress(bar.clone(), searcher.devices().len(), remaining.start);
+        searcher = searcher.with_status_output(false).with_progress_callback(on_progress);
+    }
+
+    // List the devices taking part in the search
+    if text {
+        println!("Searching on:");
+        for ((device, slice), threads) in searcher.devices().iter().zip(searcher.slices()).zip(searcher.thread_counts()) {
+            println!("  Device {}: {} ({}), {} threads, searching [{}, {})", device.index, device.name, device.platform, threads, slice.start, slice.end);
+        }
+    }
+    Ok((Box::new(searcher), bar, metrics_server))
+}
+
+// For --backend cpu, or when no OpenCL device is found. Checkpoints are read to find where to
+// resume but not written.
+fn cpu_backend(config: &Config, remaining: &Range<u64>) -> Result<Session, PrimeError> {
+    if config.checkpoint.is_some() {
+        warn!("The CPU backend does not write checkpoints");
+    }
+    if config.telemetry_csv.is_some() {
+        warn!("The CPU backend has no GPU readings to record");
+    }
+    let searcher = CpuSearcher::new(remaining.clone())?;
+    if config.format == Format::Text && !config.quiet {
+        println!("Searching on:");
+        let device = searcher.device();
+        println!("  {} ({}), searching [{}, {})", device.name, device.platform, remaining.start, remaining.end);
+    }
+    Ok((Box::new(searcher), None, None))
+}
+
+// Writes the primes found to --output or stdout, as a JSON report with --format json and as
+// bare little-endian u64s with --format bin
+fn print_result(config: &Config, range: &Range<u64>, primes: &[u64], outcome: Outcome, report: &SearchReport, gpus: Vec<JsonGpu>) -> Result<(), PrimeError> {
+    let mut out = output(config)?;
+
+    if config.format == Format::Json {
+        let report = JsonReport {
+            range: JsonRange { start: range.start, end: range.end },
+            primes: primes.to_vec(),
+            elapsed_secs: report.elapsed.as_secs_f64(),
+            found_by: report.found_by.as_ref().map(|device| JsonDevice {
+                index: device.index,
+                platform: device.platform.clone(),
+                name: device.name.clone(),
+            }),
+            timed_out: outcome == Outcome::TimedOut,
+            gpus,
+            certificates: config.certificate.then(|| json_certificates(primes)),
+        };
+        serde_json::to_writer_pretty(&mut out, &report).map_err(io::Error::from)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    if config.format == Format::Bin {
+        return Ok(write_primes_bin(&mut out, primes)?);
+    }
+
+    if config.quiet {
+        return Ok(print_bare(&mut *out, primes)?);
+    }
+    print_primes(&mut *out, config, range, primes, outcome)?;
+    if let (Some(device), false) = (&report.found_by, primes.is_empty()) {
+        writeln!(out, "Found by {}", device)?;
+    }
+    Ok(())
+}
+
+// --certificate's evidence for each prime. Every prime reported has passed the same test, so
+// there is always a certificate to give.
+fn json_certificates(primes: &[u64]) -> Vec<JsonCertificate> {
+    primes.iter().filter_map(|&prime| primality::certificate(prime)).map(|certificate| JsonCertificate {
+        n: certificate.n,
+        d: certificate.d,
+        s: certificate.s,
+        witnesses: certificate.rounds.iter().map(|round| JsonWitness { witness: round.witness, squarings: round.squarings }).collect(),
+        proof: "no composite below 3.18e23, so no u64, passes Miller-Rabin for all of the first 12 primes (Sorenson and Webster, 2015)",
+    }).collect()
+}
+
+// --quiet's result: just the primes
+fn print_bare(out: &mut dyn Write, primes: &[u64]) -> io::Result<()> {
+    for prime in primes {
+        writeln!(out, "{}", prime)?;
+    }
+    Ok(())
+}
+
+// With --quiet an empty result that wasn't cut short by the timeout goes into the exit code
+fn quiet_outcome(config: &Config, none_found: bool, outcome: Outcome) -> Outcome {
+    if config.quiet && none_found && outcome != Outcome::TimedOut { Outcome::NotFound } else { outcome }
+}
+
+// Where the result goes: --output, or stdout
+fn output(config: &Config) -> io::Result<Box<dyn Write>> {
+    Ok(match &config.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    })
+}
+
+fn print_primes(out: &mut dyn Write, config: &Config, range: &Range<u64>, primes: &[u64], outcome: Outcome) -> io::Result<()> {
+    match (config.twin, primes) {
+        (true, &[p, q]) => writeln!(out, "Twin primes found: ({}, {})", p, q)?,
+        (false, &[prime, ref next @ ..]) => {
+            writeln!(out, "Prime found: {}", prime)?;
+            if let Some(count) = config.find_next {
+                if next.is_empty() && count > 0 && outcome == Outcome::Finished {
+                    writeln!(out, "No more primes below {}", range.end)?;
+                } else if next.len() < count && outcome == Outcome::Finished {
+                    writeln!(out, "Only {} of the next {} primes are below {}", next.len(), count, range.end)?;
+                }
+                if !next.is_empty() {
+                    let listed: Vec<String> = next.iter().map(u64::to_string).collect();
+                    writeln!(out, "Next primes: {}", listed.join(", "))?;
+                }
+            }
+        }
+        (true, _) if outcome == Outcome::TimedOut => println!("No twin primes found within the timeout."),
+        (true, _) if outcome == Outcome::Interrupted => println!("Search interrupted before twin primes were found."),
+        (true, _) => println!("No twin primes found in the range."),
+        (false, _) if outcome == Outcome::TimedOut => println!("No prime found within the timeout."),
+        (false, _) if outcome == Outcome::Interrupted => println!("Search interrupted before a prime was found."),
+        (false, _) => println!("No prime found in the range."),
+    }
+    Ok(())
+}
+
+// Ctrl-C stops the search cleanly so the progress made so far can be reported
+fn cancel_on_ctrlc(cancel: CancelHandle) {
+    if let Err(e) = ctrlc::set_handler(move || {
+        warn!("Interrupted, stopping search...");
+        if let Err(e) = cancel.cancel() {
+            error!("Failed to stop kernels: {}", e);
+        }
+    }) {
+        warn!("Failed to install Ctrl-C handler: {}", e);
+    }
+}
+
+// The cache --cache-dir or the environment points to. One that can't be created only costs
+// the time to build the kernels, so the search goes ahead without it.
+fn kernel_cache(config: &Config) -> Result<Option<KernelCache>, PrimeError> {
+    let Some(dir) = config.cache_dir.clone().or_else(KernelCache::default_dir) else {
+        warn!("No home directory to keep the kernel cache in, compiling the kernels from source");
+        return Ok(None);
+    };
+    if config.clear_cache {
+        KernelCache::new(&dir).clear()?;
+    }
+    if config.no_cache {
+        return Ok(None);
+    }
+    match KernelCache::open(&dir) {
+        Ok(cache) => {
+            debug!("Caching kernels in {}", dir.display());
+            Ok(Some(cache))
+        }
+        Err(e) => {
+            warn!("Can't use {} for the kernel cache, compiling the kernels from source: {}", dir.display(), e);
+            Ok(None)
+        }
+    }
+}
+
+fn searcher_config(config: &Config) -> Result<SearcherConfig, PrimeError> {
+    Ok(SearcherConfig {
+        kernel_cache: kernel_cache(config)?,
+        devices: device_filter(config),
+        kernel_source: config.kernel.as_deref().map(fs::read_to_string).transpose()?,
+        queue_profiling: config.queue_profiling,
+        out_of_order: config.out_of_order,
+        mapped_status: config.mapped_status,
+    })
+}
+
+fn device_filter(config: &Config) -> DeviceFilter {
+    DeviceFilter { indices: config.devices.clone(), name: config.device_name.clone() }
+}
+
+// Only enumerates devices, so it works without building kernels or initializing NVML
+fn dry_run(config: &Config, range: Range<u64>) -> Result<(), PrimeError> {
+    let devices = match config.backend {
+        BackendArg::Cpu => Err(PrimeError::NoDevices),
+        _ => opencl_primes::list_devices(&device_filter(config)),
+    };
+    let devices = match devices {
+        Err(PrimeError::NoDevices) if config.backend != BackendArg::Opencl => {
+            let device = CpuSearcher::new(range.clone())?.device();
+            println!("Dry run, the search would use:");
+            println!("  {} ({}), searching [{}, {})", device.name, device.platform, range.start, range.end);
+            return Ok(());
+        }
+        devices => devices?,
+    };
+    let strategy = partition_strategy(config);
+    let slices = strategy.split(range, &devices);
+    let threads = match config.threads {
+        ThreadCount::Auto => "auto".to_string(),
+        ThreadCount::Explicit(threads) => threads.to_string(),
+    };
+
+    println!("Dry run, the search would use:");
+    for (device, slice) in devices.iter().zip(slices) {
+        println!("  Device {}: {} ({}), {} compute units, {} MiB, {} threads, searching [{}, {})",
+            device.index, device.name, device.platform, device.compute_units, device.global_memory >> 20, threads, slice.start, slice.end);
+    }
+    if let PartitionStrategy::Dynamic { chunk_size } = strategy {
+        println!("  The slices are only a starting point; the range is handed out in chunks of {}", chunk_size);
+    }
+    Ok(())
+}
+
+fn partition_strategy(config: &Config) -> PartitionStrategy {
+    match config.partition {
+        PartitionArg::Even => PartitionStrategy::Even,
+        PartitionArg::Weighted => PartitionStrategy::Weighted,
+        PartitionArg::Dynamic => PartitionStrategy::Dynamic { chunk_size: config.chunk_size },
+    }
+}
+
+fn print_summary(report: &SearchReport) {
+    println!("Summary after {:.1} s:", report.elapsed.as_secs_f64());
+    println!("  {:<4} {:<32} {:>16} {:>16} {:>10} {:>10} {:>10} {:>6}", "GPU", "Name", "Tested", "Remaining", "Time", "Kernel", "Peak temp", "Found");
+    for (i, device) in report.devices.iter().enumerate() {
+        let kernel = device.kernel_time.map_or("-".to_string(), |time| format!("{:.1} s", time.as_secs_f64()));
+        let peak = device.peak_temperature.map_or("-".to_string(), |t| format!("{}°C", t));
+        println!("  {:<4} {:<32} {:>16} {:>16} {:>8.1} s {:>10} {:>10} {:>6}",
+            i, device.name, device.tested, device.remaining, device.wall_time.as_secs_f64(), kernel, peak, if device.found_prime { "yes" } else { "" });
+    }
+}
+
+fn bench(config: &Config, duration: Duration, lock_clocks: Option<ClockSettings>) -> Result<(), PrimeError> {
+    let searcher = PrimeSearcher::new_with_config(0..0, &searcher_config(config)?)?
+        .with_algorithm(config.algorithm.into())
+        .with_thread_count(config.threads)?
+        .with_local_size(config.local_size)?;
+    // Ctrl-C ends the runs early rather than the process, so the clocks are still restored
+    let _locked = lock_clocks.map(|clocks| searcher.lock_clocks(clocks));
+    cancel_on_ctrlc(searcher.cancel_handle());
+
+    println!("Benchmarking each device for {} s...", duration.as_secs());
+    let groups = match searcher.local_size() {
+        Some(size) => format!("work groups of {}", size),
+        None => "work groups sized by the driver".to_string(),
+    };
+    for ((device, threads), result) in searcher.devices().iter().zip(searcher.thread_counts()).zip(searcher.bench(duration)?) {
+        println!("  Device: {} ({}), {} threads in {}: {:.0} candidates/s, {:.2} MB/s, {:.1} µs per status {}, {:.2}x from the small-prime precheck",
+            device.name, device.platform, threads, groups, result.candidates_per_sec(), result.mb_per_sec(), result.read_time_per_batch().as_secs_f64() * 1e6,
+            if config.mapped_status { "map" } else { "read" }, result.precheck_speedup());
+    }
+    Ok(())
+}
+
+fn tune(config: &Config, duration: Duration, save: Option<&Path>) -> Result<(), PrimeError> {
+    let mut searcher = PrimeSearcher::new_with_config(0..0, &searcher_config(config)?)?
+        .with_algorithm(config.algorithm.into())
+        .with_local_size(config.local_size)?;
+    cancel_on_ctrlc(searcher.cancel_handle());
+
+    println!("Timing each device for {} s per thread count...", duration.as_secs());
+    let results = searcher.tune(duration)?;
+    for (device, result) in searcher.devices().iter().zip(&results) {
+        println!("  Device: {} ({})", device.name, device.platform);
+        let best = result.best();
+        for (threads, run) in &result.runs {
+            let marker = if Some(*threads) == best { "*" } else { " " };
+            println!("  {} {:>8} threads: {:.0} candidates/s", marker, threads, run.candidates_per_sec());
+        }
+    }
+    let Some((threads, rate)) = TuneResult::best_shared(&results) else {
+        println!("No thread count was timed");
+        return Ok(());
+    };
+    println!("Fastest --threads for all devices together: {} ({:.0} candidates/s)", threads, rate);
+    if let Some(path) = save {
+        save_threads(path, threads)?;
+        println!("Saved threads = {} to {}", threads, path.display());
+    }
+    Ok(())
+}
+
+// Sets the top-level threads key of a config file by editing its text, so the rest of the file
+// and its comments are left as they were
+fn save_threads(path: &Path, threads: usize) -> io::Result<()> {
+    let text = fs::read_to_string(path)?;
+    let setting = format!("threads = {}", threads);
+    let mut lines: Vec<&str> = text.lines().collect();
+    let top_level = lines.iter().position(|line| line.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let existing = lines[..top_level].iter().position(|line| {
+        line.trim_start().strip_prefix("threads").is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+    match existing {
+        Some(i) => lines[i] = &setting,
+        None => lines.insert(top_level, &setting),
+    }
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+// Only enumerates devices, so it works without building kernels
+fn devices(config: &Config) -> Result<(), PrimeError> {
+    let (devices, nvml) = match opencl_primes::enumerate_devices() {
+        Ok(devices) => {
+            let nvml = opencl_primes::nvml_info()?;
+            (devices, nvml)
+        }
+        Err(PrimeError::NoDevices) => (vec![], vec![]),
+        Err(e) => return Err(e),
+    };
+
+    if config.format == Format::Json {
+        let inventory: Vec<JsonInventoryDevice> = devices.into_iter().zip(nvml).map(|(device, nvml)| JsonInventoryDevice {
+            index: device.index,
+            platform: device.platform,
+            name: device.name,
+            vendor: device.vendor,
+            opencl_version: device.opencl_version,
+            compute_units: device.compute_units,
+            global_memory_bytes: device.global_memory,
+            max_work_group_size: device.max_work_group_size,
+            pci_address: device.pci_address,
+            unsupported: device.unsupported,
+            driver_version: nvml.as_ref().map(|nvml| nvml.driver_version.clone()),
+            vram_bytes: nvml.map(|nvml| nvml.memory_total),
+        }).collect();
+        println!("{}", serde_json::to_string_pretty(&inventory).map_err(io::Error::from)?);
+        return Ok(());
+    }
+    if devices.is_empty() {
+        println!("No OpenCL devices found");
+    }
+    for (device, nvml) in devices.iter().zip(nvml) {
+        println!("Device {}: {} ({})", device.index, device.name, device.platform);
+        println!("  {}, {}, {} compute units, {} MiB, work groups of up to {}",
+            device.vendor, device.opencl_version, device.compute_units, device.global_memory >> 20, device.max_work_group_size);
+        if let Some(address) = &device.pci_address {
+            println!("  PCI {}", address);
+        }
+        if let Some(nvml) = nvml {
+            println!("  NVIDIA driver {}, {} MiB VRAM", nvml.driver_version, nvml.memory_total >> 20);
+        }
+        if let Some(reason) = &device.unsupported {
+            println!("  Skipped by searches: {}", reason);
+        }
+    }
+    Ok(())
+}
+
+fn is_prime(config: &Config, n: u64) -> Result<Outcome, PrimeError> {
+    let searcher = PrimeSearcher::new_with_config(n..n, &searcher_config(config)?)?
+        .with_algorithm(config.algorithm.into());
+    let device = &searcher.devices()[0];
+
+    let started = Instant::now();
+    let prime = searcher.test_number(n)?;
+    let elapsed = started.elapsed();
+    if (config.verify || !config.no_verify) && prime != opencl_primes::verify::is_prime(n) {
+        match opencl_primes::verify::find_factor(n as u128) {
+            Some(factor) => warn!("{} calls {} prime, but it's divisible by {}", device, n, factor),
+            None if prime => warn!("{} calls {} prime, but it isn't", device, n),
+            None => warn!("{} calls {} composite, but it's prime", device, n),
+        }
+    }
+
+    if config.format == Format::Json {
+        let report = JsonIsPrime {
+            n,
+            prime,
+            device: JsonDevice { index: device.index, platform: device.platform.clone(), name: device.name.clone() },
+            elapsed_secs: elapsed.as_secs_f64(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report).map_err(io::Error::from)?);
+        return Ok(quiet_outcome(config, !prime, Outcome::Finished));
+    }
+    if config.quiet {
+        let primes: &[u64] = if prime { &[n] } else { &[] };
+        print_bare(&mut io::stdout(), primes)?;
+        return Ok(quiet_outcome(config, !prime, Outcome::Finished));
+    }
+    let algorithm = config.algorithm.to_possible_value().expect("algorithms are all listed");
+    println!("{} is {}", n, if prime { "prime" } else { "not prime" });
+    println!("Tested with {} on {} in {:.3} ms", algorithm.get_name(), device, elapsed.as_secs_f64() * 1e3);
+    Ok(Outcome::Finished)
+}
+
+// Values from the file replace those that didn't come from the command line. Clap's
+// validation doesn't run on them, so a bad file is reported the way a bad flag would be.
+fn merge_config_file(path: &Path, options: Config, matches: &ArgMatches) -> Config {
+    let fail = |message: String| -> ! { Cli::command().error(ErrorKind::InvalidValue, message).exit() };
+    let text = fs::read_to_string(path).unwrap_or_else(|e| fail(format!("failed to read {}: {}", path.display(), e)));
+    let file: toml::Table = toml::from_str(&text).unwrap_or_else(|e| fail(format!("invalid config file {}: {}", path.display(), e)));
+
+    let command = Cli::command();
+    let mut merged = toml::Table::try_from(&options).expect("the options serialize as TOML");
+    for (key, value) in file {
+        let id = key.replace('-', "_");
+        // Unknown keys are left in for deserialization to reject
+        let known = command.get_arguments().any(|arg| arg.get_id() == id.as_str());
+        if !known || matches.value_source(&id) != Some(ValueSource::CommandLine) {
+            merged.insert(key, value);
+        }
+    }
+    merged.try_into().unwrap_or_else(|e| fail(format!("invalid config file {}: {}", path.display(), e)))
+}
+
+// --start and --end in a config file: an integer, a float holding one exactly, or a string in
+// any notation parse_number takes
+mod number {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(n: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match n {
+            Some(n) => serializer.serialize_some(n),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            // An unquoted 1e13 is a TOML float
+            Float(f64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => Ok(Some(n)),
+            // Above 2^53 a float may not hold the number that was written
+            Raw::Float(n) if n >= 0.0 && n.fract() == 0.0 && n <= (1u64 << 53) as f64 => Ok(Some(n as u64)),
+            Raw::Float(n) => Err(D::Error::custom(format!("{} is not a whole number that a float holds exactly, quote it to write it in full", n))),
+            Raw::Text(text) => super::parse_number(&text).map(Some).map_err(D::Error::custom),
+        }
+    }
+}
+
+// --range in a config file: a list of "START:END" strings
+mod range_list {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error, ser::SerializeSeq};
+    use std::ops::Range;
+
+    pub fn serialize<S: Serializer>(ranges: &[Range<u64>], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(ranges.len()))?;
+        for range in ranges {
+            seq.serialize_element(&format!("{}:{}", range.start, range.end))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Range<u64>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter().map(|range| super::parse_range(range).map_err(D::Error::custom)).collect()
+    }
+}
+
+// --progression in a config file: an "A:B" string
+mod progression {
+    use opencl_primes::Progression;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(progression: &Option<Progression>, serializer: S) -> Result<S::Ok, S::Error> {
+        match progression {
+            Some(progression) => serializer.serialize_some(&format!("{}:{}", progression.modulus(), progression.residue())),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Progression>, D::Error> {
+        super::parse_progression(&String::deserialize(deserializer)?).map(Some).map_err(D::Error::custom)
+    }
+}
+
+// ThreadCount in a config file: a number, or "auto"
+mod thread_count {
+    use opencl_primes::ThreadCount;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(threads: &ThreadCount, serializer: S) -> Result<S::Ok, S::Error> {
+        match threads {
+            ThreadCount::Auto => serializer.serialize_str("auto"),
+            ThreadCount::Explicit(threads) => serializer.serialize_u64(*threads as u64),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ThreadCount, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(usize),
+            Text(String),
+        }
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Number(threads) => threads.to_string(),
+            Raw::Text(text) => text,
+        };
+        super::parse_thread_count(&text).map_err(D::Error::custom)
+    }
+}
+
+mod status_threads {
+    use opencl_primes::StatusThreads;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(threads: &StatusThreads, serializer: S) -> Result<S::Ok, S::Error> {
+        match threads {
+            StatusThreads::All => serializer.serialize_str("all"),
+            StatusThreads::First(count) => serializer.serialize_u64(*count as u64),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StatusThreads, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(usize),
+            Text(String),
+        }
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Number(count) => count.to_string(),
+            Raw::Text(text) => text,
+        };
+        super::parse_status_threads(&text).map_err(D::Error::custom)
+    }
+}
+
+fn parse_status_threads(arg: &str) -> Result<StatusThreads, String> {
+    match arg {
+        "all" => Ok(StatusThreads::All),
+        _ => arg.parse::<usize>().map(StatusThreads::First).map_err(|e| format!("expected a number or all: {}", e)),
+    }
+}
+
+fn parse_thread_count(arg: &str) -> Result<ThreadCount, String> {
+    match arg {
+        "auto" => Ok(ThreadCount::Auto),
+        _ => match arg.parse::<usize>() {
+            Ok(0) => Err("thread count must be positive".into()),
+            Ok(threads) => Ok(ThreadCount::Explicit(threads)),
+            Err(e) => Err(format!("expected a number or auto: {}", e)),
+        },
+    }
+}
+
+// --range as START:END, each in the notation of --start
+fn parse_range(arg: &str) -> Result<Range<u64>, String> {
+    let Some((start, end)) = arg.split_once(':') else {
+        return Err(format!("{} is not START:END", arg));
+    };
+    let (start, end) = (parse_number(start.trim())?, parse_number(end.trim())?);
+    if start > end {
+        return Err(format!("the start ({}) must not be greater than the end ({})", start, end));
+    }
+    if start == end {
+        return Err(format!("{}:{} is empty", start, end));
+    }
+    Ok(start..end)
+}
+
+// Why --start and --end can't be searched, if they can't
+fn range_error(range: &Range<u64>) -> Option<String> {
+    if range.start > range.end {
+        Some(format!("--start ({}) must not be greater than --end ({})", range.start, range.end))
+    } else if range.is_empty() {
+        Some(format!("--start and --end are both {}, which leaves nothing to search", range.start))
+    } else {
+        None
+    }
+}
+
+// --progression as A:B, for the numbers A*k + B
+fn parse_progression(arg: &str) -> Result<Progression, String> {
+    let Some((modulus, residue)) = arg.split_once(':') else {
+        return Err(format!("{} is not A:B", arg));
+    };
+    let (modulus, residue) = (parse_number(modulus.trim())?, parse_number(residue.trim())?);
+    Progression::new(modulus, residue).map_err(|e| match e {
+        PrimeError::InvalidProgression(msg) => msg,
+        e => e.to_string(),
+    })
+}
+
+// --lock-clocks as <graphics_mhz>,<mem_mhz>
+fn parse_clocks(arg: &str) -> Result<ClockSettings, String> {
+    let Some((graphics, memory)) = arg.split_once(',') else {
+        return Err(format!("{} is not <graphics_mhz>,<mem_mhz>", arg));
+    };
+    let mhz = |clock: &str| clock.trim().parse::<u32>().map_err(|_| format!("{} is not a clock in MHz", clock.trim()));
+    Ok(ClockSettings { graphics_mhz: mhz(graphics)?, memory_mhz: mhz(memory)? })
+}
+
+// A number as a plain integer, a power like 2^43, or scientific notation like 1e13 or 1.5e12,
+// with underscores allowed between digits
+fn parse_number(arg: &str) -> Result<u64, String> {
+    let digits = |part: &str| -> Result<String, String> {
+        let bytes = part.as_bytes();
+        let separated = bytes.iter().enumerate().all(|(i, &b)| {
+            b != b'_' || (i > 0 && i + 1 < bytes.len() && bytes[i - 1].is_ascii_digit() && bytes[i + 1].is_ascii_digit())
+        });
+        if part.is_empty() || !separated || !part.bytes().all(|b| b.is_ascii_digit() || b == b'_') {
+            return Err(format!("expected a number like 10_000_000_000_000, 1e13 or 2^43, got {:?}", arg));
+        }
+        Ok(part.replace('_', ""))
+    };
+    let too_large = || format!("{} is larger than the maximum of {}", arg, u64::MAX);
+    let integer = |part: &str| -> Result<u64, String> { digits(part)?.parse().map_err(|_| too_large()) };
+
+    if let Some((base, exponent)) = arg.split_once('^') {
+        let (base, exponent) = (integer(base)?, u32::try_from(integer(exponent)?).map_err(|_| too_large())?);
+        if base == 0 && exponent == 0 {
+            return Err("0^0 is ambiguous".into());
+        }
+        return base.checked_pow(exponent).ok_or_else(too_large);
+    }
+    if let Some((mantissa, exponent)) = arg.split_once(['e', 'E']) {
+        let exponent = u32::try_from(integer(exponent)?).map_err(|_| too_large())?;
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let fraction = if fraction.is_empty() { String::new() } else { digits(fraction)?.trim_end_matches('0').to_string() };
+        // 1.5e3 is 15 shifted by one digit fewer
+        let shift = exponent.checked_sub(fraction.len() as u32)
+            .ok_or_else(|| format!("{} is not a whole number", arg))?;
+        let significand: u64 = format!("{}{}", digits(whole)?, fraction).parse().map_err(|_| too_large())?;
+        return 10u64.checked_pow(shift).and_then(|scale| significand.checked_mul(scale)).ok_or_else(too_large);
+    }
+    integer(arg)
+}
+
+fn progress_bar(len: u64) -> ProgressBar {
+    let bar = ProgressBar::new(len);
+    bar.set_style(ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} {percent}% {per_sec} {msg}").unwrap());
+    *PROGRESS_BAR.lock().unwrap() = Some(bar.clone());
+    bar
+}
+
+// Every log record goes through here to stderr in a single write, so records from different
+// threads never mix, and a record logged while the progress bar is drawn prints above it
+// instead of through it
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, record: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "tui")]
+        if tui::capture_log(record) {
+            return Ok(record.len());
+        }
+        let bar = PROGRESS_BAR.lock().unwrap().clone();
+        match bar.filter(|bar| !bar.is_finished()) {
+            Some(bar) => bar.suspend(|| io::stderr().lock().write_all(record))?,
+            None => io::stderr().lock().write_all(record)?,
+        }
+        Ok(record.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+// Counts what each device has tested as covered, and turns the candidate rate into the primes
+// per second expected at this magnitude.
+fn track_progress(bar: ProgressBar, devices: usize, start: u64) -> impl Fn(ProgressEvent) + Send + Sync {
+    let covered = Mutex::new((vec![0u64; devices], RateEstimate::new()));
+    let density = 1.0 / (start.max(3) as f64).ln();
+    move |event| {
+        let (covered, rate) = &mut *covered.lock().unwrap();
+        covered[event.device] = event.tested;
+        bar.set_position(covered.iter().sum());
+        let remaining = bar.length().unwrap_or(0).saturating_sub(bar.position());
+        rate.record(Instant::now(), remaining);
+        bar.set_message(format!("~{:.1} primes/s, {} left", bar.per_sec() * density, format_time_left(rate.time_left(remaining))));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn ranges_need_at_least_one_number() {
+        assert_eq!(parse_range("7:8"), Ok(7..8));
+        assert_eq!(parse_range("0:1"), Ok(0..1));
+        assert!(parse_range("5:5").is_err());
+        assert!(parse_range("0:0").is_err());
+        assert!(parse_range("9:3").is_err());
+
+        assert_eq!(range_error(&(7..8)), None);
+        assert_eq!(range_error(&(u64::MAX - 1..u64::MAX)), None);
+        assert!(range_error(&(5..5)).is_some());
+        assert!(range_error(&Range { start: 9, end: 3 }).is_some());
+    }
 }
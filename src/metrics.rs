@@ -0,0 +1,120 @@
+use prometheus::{Encoder, GaugeVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::{io, sync::Arc, thread::{self, JoinHandle}};
+use tiny_http::{Header, Response, Server};
+
+use crate::GpuStats;
+
+const LABELS: [&str; 2] = ["gpu", "name"];
+
+/// Prometheus gauges and counters fed by the search monitor, labelled with the device's
+/// position in [`PrimeSearcher::devices`](crate::PrimeSearcher::devices) and its name.
+pub struct Metrics {
+    registry: Registry,
+    temperature: IntGaugeVec,
+    utilization: IntGaugeVec,
+    power: GaugeVec,
+    tested: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let temperature = IntGaugeVec::new(Opts::new("opencl_primes_gpu_temperature_celsius", "GPU temperature"), &LABELS).unwrap();
+        let utilization = IntGaugeVec::new(Opts::new("opencl_primes_gpu_utilization_percent", "GPU utilization"), &LABELS).unwrap();
+        let power = GaugeVec::new(Opts::new("opencl_primes_gpu_power_watts", "GPU power draw"), &LABELS).unwrap();
+        let tested = IntCounterVec::new(Opts::new("opencl_primes_candidates_tested_total", "Candidates tested"), &LABELS).unwrap();
+
+        // Registering only fails for duplicate names, which these are not
+        let registry = Registry::new();
+        registry.register(Box::new(temperature.clone())).unwrap();
+        registry.register(Box::new(utilization.clone())).unwrap();
+        registry.register(Box::new(power.clone())).unwrap();
+        registry.register(Box::new(tested.clone())).unwrap();
+        Metrics { registry, temperature, utilization, power, tested }
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        TextEncoder::new().encode_utf8(&self.registry.gather(), &mut text).unwrap();
+        text
+    }
+
+    pub(crate) fn record_stats(&self, gpu: usize, name: &str, stats: &GpuStats) {
+        let labels = [&gpu.to_string(), name];
+        self.temperature.with_label_values(&labels).set(stats.temperature as i64);
+        if let Some(utilization) = stats.utilization {
+            self.utilization.with_label_values(&labels).set(utilization as i64);
+        }
+        if let Some(power) = stats.power_usage {
+            self.power.with_label_values(&labels).set(power as f64 / 1000.0);
+        }
+    }
+
+    pub(crate) fn record_temperature(&self, gpu: usize, name: &str, temperature: u32) {
+        self.temperature.with_label_values(&[&gpu.to_string(), name]).set(temperature as i64);
+    }
+
+    pub(crate) fn add_tested(&self, gpu: usize, name: &str, tested: u64) {
+        self.tested.with_label_values(&[&gpu.to_string(), name]).inc_by(tested);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// HTTP server answering `GET /metrics` on its own thread. It stops when dropped.
+pub struct MetricsServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Listens on every interface at `port`; port 0 picks a free one.
+    pub fn start(port: u16, metrics: Arc<Metrics>) -> io::Result<Self> {
+        let server = Arc::new(Server::http(("0.0.0.0", port)).map_err(io::Error::other)?);
+        let thread = {
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                let content_type = Header::from_bytes("Content-Type", TextEncoder::new().format_type()).unwrap();
+                // Ends once shutdown unblocks the server
+                for request in server.incoming_requests() {
+                    let response = if request.url() == "/metrics" {
+                        Response::from_string(metrics.encode()).with_header(content_type.clone())
+                    } else {
+                        Response::from_string("Not found").with_status_code(404)
+                    };
+                    if let Err(e) = request.respond(response) {
+                        debug!("Failed to answer a metrics request: {}", e);
+                    }
+                }
+            })
+        };
+        Ok(MetricsServer { server, thread: Some(thread) })
+    }
+
+    /// The port the server ended up listening on.
+    pub fn port(&self) -> Option<u16> {
+        self.server.server_addr().to_ip().map(|addr| addr.port())
+    }
+
+    /// Stops accepting requests and waits for the server thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.server.unblock();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
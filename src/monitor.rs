@@ -0,0 +1,198 @@
+use nvml::Nvml;
+use nvml::enum_wrappers::device::{Clock, TemperatureSensor};
+use nvml::error::NvmlError;
+use std::{fs, path::{Path, PathBuf}, sync::Arc};
+
+use crate::{GpuStats, Result, pci::PciAddress};
+
+// PCI vendor IDs, as OpenCL reports them in CL_DEVICE_VENDOR_ID
+pub(crate) const VENDOR_NVIDIA: u32 = 0x10de;
+const VENDOR_AMD: u32 = 0x1002;
+const VENDOR_INTEL: u32 = 0x8086;
+
+const DRM_CLASS: &str = "/sys/class/drm";
+
+/// Readings from one GPU's driver, taken while a search runs to report stats and apply the
+/// thermal limit. `None` means the device doesn't report that reading.
+pub trait Monitor: Send + Sync {
+    /// Temperature in °C.
+    fn temperature(&self) -> Result<Option<u32>>;
+
+    /// Utilization in percent.
+    fn utilization(&self) -> Result<Option<u32>>;
+
+    /// Power draw in milliwatts.
+    fn power(&self) -> Result<Option<u32>>;
+
+    /// Graphics clock in MHz.
+    fn graphics_clock(&self) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    /// Memory clock in MHz.
+    fn memory_clock(&self) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    /// Every reading at once, or `None` if the device doesn't even report its temperature.
+    fn stats(&self) -> Result<Option<GpuStats>> {
+        let Some(temperature) = self.temperature()? else {
+            return Ok(None);
+        };
+        Ok(Some(GpuStats {
+            utilization: self.utilization()?,
+            temperature,
+            power_usage: self.power()?,
+            graphics_clock: self.graphics_clock()?,
+            memory_clock: self.memory_clock()?,
+        }))
+    }
+}
+
+// Picks the monitor for an OpenCL device by its vendor: NVML for NVIDIA, sysfs for AMD and
+// Intel, and nothing for the rest. NVML is only initialized if an NVIDIA device asks for it.
+pub(crate) fn for_device(i: usize, name: &str, vendor_id: u32, address: Option<PciAddress>, nvml: impl FnOnce() -> Option<Arc<Nvml>>) -> Arc<dyn Monitor> {
+    let monitor: Option<Arc<dyn Monitor>> = match vendor_id {
+        VENDOR_NVIDIA => nvml().and_then(|nvml| {
+            let index = address?.nvml_index(&nvml)?;
+            Some(Arc::new(NvmlMonitor { nvml, index }) as Arc<dyn Monitor>)
+        }),
+        VENDOR_AMD | VENDOR_INTEL => SysfsMonitor::find(vendor_id, address).map(|monitor| Arc::new(monitor) as Arc<dyn Monitor>),
+        _ => {
+            debug!("GPU {} ({}) is from vendor {:#06x}, which has no monitoring backend", i, name, vendor_id);
+            return Arc::new(NoMonitor);
+        }
+    };
+    monitor.unwrap_or_else(|| {
+        warn!("GPU {} ({}) has no matching monitoring device, it won't be monitored", i, name);
+        Arc::new(NoMonitor)
+    })
+}
+
+// Reports nothing, for devices without a monitoring backend or with monitoring disabled
+pub(crate) struct NoMonitor;
+
+impl Monitor for NoMonitor {
+    fn temperature(&self) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    fn utilization(&self) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    fn power(&self) -> Result<Option<u32>> {
+        Ok(None)
+    }
+}
+
+pub(crate) struct NvmlMonitor {
+    nvml: Arc<Nvml>,
+    index: u32,
+}
+
+impl Monitor for NvmlMonitor {
+    fn temperature(&self) -> Result<Option<u32>> {
+        Ok(Some(self.nvml.device_by_index(self.index)?.temperature(TemperatureSensor::Gpu)?))
+    }
+
+    fn utilization(&self) -> Result<Option<u32>> {
+        Ok(Some(self.nvml.device_by_index(self.index)?.utilization_rates()?.gpu))
+    }
+
+    fn power(&self) -> Result<Option<u32>> {
+        if_supported(self.nvml.device_by_index(self.index)?.power_usage())
+    }
+
+    fn graphics_clock(&self) -> Result<Option<u32>> {
+        if_supported(self.nvml.device_by_index(self.index)?.clock_info(Clock::Graphics))
+    }
+
+    fn memory_clock(&self) -> Result<Option<u32>> {
+        if_supported(self.nvml.device_by_index(self.index)?.clock_info(Clock::Memory))
+    }
+}
+
+// Older cards lack some sensors; treat those readings as absent instead of failing the search.
+fn if_supported<T>(reading: std::result::Result<T, NvmlError>) -> Result<Option<T>> {
+    match reading {
+        Ok(value) => Ok(Some(value)),
+        Err(NvmlError::NotSupported) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// The amdgpu and i915/xe drivers' files under /sys/class/drm/cardN. A file that is missing
+// or unreadable reads as None.
+pub(crate) struct SysfsMonitor {
+    card: PathBuf,
+    hwmon: Option<PathBuf>,
+}
+
+impl SysfsMonitor {
+    // The DRM card at `address`, or without one the first card from the vendor
+    fn find(vendor_id: u32, address: Option<PciAddress>) -> Option<Self> {
+        let mut cards: Vec<PathBuf> = fs::read_dir(DRM_CLASS).ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.starts_with("card") && !name.contains('-')))
+            .map(|entry| entry.path())
+            .collect();
+        cards.sort();
+
+        let card = cards.into_iter().find(|card| {
+            let device = card.join("device");
+            let vendor = read_value(&device.join("vendor"), |text| u32::from_str_radix(text.trim_start_matches("0x"), 16).ok());
+            // device links to the PCI device, named by its address
+            let at = fs::canonicalize(&device).ok()
+                .and_then(|path| path.file_name()?.to_str().and_then(PciAddress::parse));
+            vendor == Some(vendor_id) && address.is_none_or(|address| at == Some(address))
+        })?;
+        let hwmon = fs::read_dir(card.join("device/hwmon")).ok()
+            .and_then(|mut entries| entries.find_map(|entry| entry.ok()))
+            .map(|entry| entry.path());
+        Some(SysfsMonitor { card, hwmon })
+    }
+
+    fn hwmon_value(&self, file: &str) -> Option<u64> {
+        read_value(&self.hwmon.as_ref()?.join(file), |text| text.parse().ok())
+    }
+
+    // amdgpu's pp_dpm_* files list the clock levels, marking the current one with *
+    fn dpm_clock(&self, file: &str) -> Option<u32> {
+        read_value(&self.card.join("device").join(file), |text| {
+            let level = text.lines().find(|line| line.trim_end().ends_with('*'))?;
+            level.split_whitespace().nth(1)?.trim_end_matches("Mhz").trim_end_matches("MHz").parse().ok()
+        })
+    }
+}
+
+impl Monitor for SysfsMonitor {
+    fn temperature(&self) -> Result<Option<u32>> {
+        // Millidegrees
+        Ok(self.hwmon_value("temp1_input").map(|t| (t / 1000) as u32))
+    }
+
+    fn utilization(&self) -> Result<Option<u32>> {
+        // amdgpu only; i915 doesn't expose a busy percentage
+        Ok(read_value(&self.card.join("device/gpu_busy_percent"), |text| text.parse().ok()))
+    }
+
+    fn power(&self) -> Result<Option<u32>> {
+        // Microwatts
+        let power = self.hwmon_value("power1_average").or_else(|| self.hwmon_value("power1_input"));
+        Ok(power.map(|p| (p / 1000) as u32))
+    }
+
+    fn graphics_clock(&self) -> Result<Option<u32>> {
+        let i915 = read_value(&self.card.join("gt_cur_freq_mhz"), |text| text.parse().ok());
+        Ok(i915.or_else(|| self.dpm_clock("pp_dpm_sclk")))
+    }
+
+    fn memory_clock(&self) -> Result<Option<u32>> {
+        Ok(self.dpm_clock("pp_dpm_mclk"))
+    }
+}
+
+fn read_value<T>(path: &Path, parse: impl FnOnce(&str) -> Option<T>) -> Option<T> {
+    parse(fs::read_to_string(path).ok()?.trim())
+}
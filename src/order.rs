@@ -0,0 +1,35 @@
+use ocl::{Event, Kernel, Queue, CommandQueueProperties};
+use ocl::enums::{CommandQueueInfo, CommandQueueInfoResult};
+
+use crate::Result;
+
+// Keeps the commands on a compute queue in the order they were enqueued. An in-order queue
+// does that by itself. On an out-of-order one each kernel waits on a marker for everything
+// enqueued before it, such as the fills that reset its buffers, and the reads of its results
+// wait on the kernel's event.
+
+// Enqueues `kernel` on its queue after everything already there, returning its completion event
+pub(crate) fn enqueue_kernel(kernel: &Kernel) -> Result<Event> {
+    let queue = kernel.default_queue().expect("kernels are built with their device's queue");
+    let mut event = Event::empty();
+    let command = kernel.cmd().enew(&mut event);
+    unsafe {
+        match after_previous(queue)? {
+            Some(marker) => command.ewait(&marker).enq()?,
+            None => command.enq()?,
+        }
+    }
+    Ok(event)
+}
+
+// A marker for every command already on the queue, or None when the queue runs them in order
+fn after_previous(queue: &Queue) -> Result<Option<Event>> {
+    let out_of_order = match queue.info(CommandQueueInfo::Properties)? {
+        CommandQueueInfoResult::Properties(properties) => properties.contains(CommandQueueProperties::OUT_OF_ORDER_EXEC_MODE_ENABLE),
+        _ => false,
+    };
+    if !out_of_order {
+        return Ok(None);
+    }
+    Ok(Some(queue.enqueue_marker(None::<Event>)?))
+}
@@ -0,0 +1,99 @@
+use std::ops::Range;
+
+use crate::DeviceInfo;
+
+/// How the search range is split between devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionStrategy {
+    /// Equal slices, with any remainder going to the last device.
+    #[default]
+    Even,
+    /// Slices proportional to each device's `max_compute_units`.
+    Weighted,
+    /// Chunks of `chunk_size` numbers handed out in ascending order to whichever device
+    /// finishes first, so fast devices keep working while slow ones finish.
+    ///
+    /// Applies to `find_first`, `find_twin`, `find_all` and `count`; the segmented sieve and
+    /// anything else that needs fixed slices gets even ones. Checkpoints are not written,
+    /// since no single position per device describes the work done.
+    Dynamic { chunk_size: u64 },
+}
+
+impl PartitionStrategy {
+    /// Splits `range` between `devices`, in their order.
+    pub fn split(self, range: Range<u64>, devices: &[DeviceInfo]) -> Vec<Range<u64>> {
+        match self {
+            PartitionStrategy::Even | PartitionStrategy::Dynamic { .. } => partition_range(range, devices.len()),
+            PartitionStrategy::Weighted => {
+                let weights: Vec<u64> = devices.iter().map(|d| d.compute_units as u64).collect();
+                partition_weighted(range, &weights)
+            }
+        }
+    }
+}
+
+/// Splits `range` into `n` contiguous slices of equal length, the last one taking the remainder.
+pub fn partition_range(range: Range<u64>, n: usize) -> Vec<Range<u64>> {
+    partition_weighted(range, &vec![1; n])
+}
+
+/// Splits `range` into contiguous slices proportional to `weights`, the last one taking the
+/// remainder. A zero total weight falls back to equal slices.
+pub fn partition_weighted(range: Range<u64>, weights: &[u64]) -> Vec<Range<u64>> {
+    let total: u128 = weights.iter().map(|&w| w as u128).sum();
+    if total == 0 && !weights.is_empty() {
+        return partition_range(range, weights.len());
+    }
+
+    let len = range.end.saturating_sub(range.start) as u128;
+    let mut slices = Vec::with_capacity(weights.len());
+    let mut start = range.start;
+    for (i, &weight) in weights.iter().enumerate() {
+        let end = if i + 1 == weights.len() {
+            range.end.max(start)
+        } else {
+            start + (len * weight as u128 / total) as u64
+        };
+        slices.push(start..end);
+        start = end;
+    }
+    slices
+}
+
+/// Splits `range` into consecutive chunks of `chunk_size` numbers, the last one taking what
+/// is left.
+pub fn partition_chunks(range: Range<u64>, chunk_size: u64) -> Vec<Range<u64>> {
+    let chunk_size = chunk_size.max(1);
+    let mut chunks = vec![];
+    let mut start = range.start;
+    while start < range.end {
+        let end = start.saturating_add(chunk_size).min(range.end);
+        chunks.push(start..end);
+        start = end;
+    }
+    chunks
+}
+
+/// Splits the numbers in `pieces`, ascending ranges that don't overlap, into `n` contiguous
+/// slices holding equal counts of them, the last one taking the remainder. Each slice runs
+/// from its first number to its last, taking in any gaps between pieces.
+pub fn partition_pieces(pieces: &[Range<u64>], n: usize) -> Vec<Range<u64>> {
+    let total: u64 = pieces.iter().map(|piece| piece.end.saturating_sub(piece.start)).sum();
+    // The number `offset` places into the pieces, or the end of the last one past them all
+    let at = |mut offset: u64| {
+        for piece in pieces {
+            let len = piece.end.saturating_sub(piece.start);
+            if offset < len {
+                return piece.start + offset;
+            }
+            offset -= len;
+        }
+        pieces.last().map_or(0, |piece| piece.end)
+    };
+    partition_range(0..total, n).into_iter()
+        .map(|share| {
+            let start = at(share.start);
+            if share.is_empty() { start..start } else { start..at(share.end - 1) + 1 }
+        })
+        .collect()
+}
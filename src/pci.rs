@@ -0,0 +1,65 @@
+use nvml::Nvml;
+use ocl::Device;
+use std::fmt;
+
+// cl_nv_device_attribute_query
+const CL_DEVICE_PCI_BUS_ID_NV: u32 = 0x4008;
+const CL_DEVICE_PCI_SLOT_ID_NV: u32 = 0x4009;
+const CL_DEVICE_PCI_DOMAIN_ID_NV: u32 = 0x400A;
+// cl_amd_device_attribute_query
+const CL_DEVICE_TOPOLOGY_AMD: u32 = 0x4037;
+const CL_DEVICE_TOPOLOGY_TYPE_PCIE_AMD: u32 = 1;
+
+// Where a device sits on the PCI bus, which OpenCL and NVML both report but number
+// their devices independently of. Devices are listed in the order of their addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct PciAddress {
+    domain: u32,
+    bus: u32,
+    device: u32,
+}
+
+impl PciAddress {
+    // Through the NVIDIA or AMD attribute query extensions; other devices have no address
+    pub(crate) fn of_opencl_device(device: Device) -> Option<Self> {
+        let uint = |request| {
+            let bytes = ocl::core::get_device_info_raw(device, request).ok()?;
+            Some(u32::from_ne_bytes(bytes.get(..4)?.try_into().ok()?))
+        };
+        if let (Some(bus), Some(slot)) = (uint(CL_DEVICE_PCI_BUS_ID_NV), uint(CL_DEVICE_PCI_SLOT_ID_NV)) {
+            // Older drivers lack the domain query; single-domain machines are the norm
+            let domain = uint(CL_DEVICE_PCI_DOMAIN_ID_NV).unwrap_or(0);
+            return Some(PciAddress { domain, bus, device: slot });
+        }
+
+        // cl_device_topology_amd: a cl_uint type, 17 unused bytes, then bus, device and function
+        let topology = ocl::core::get_device_info_raw(device, CL_DEVICE_TOPOLOGY_AMD).ok()?;
+        if topology.len() < 24 || u32::from_ne_bytes(topology[..4].try_into().ok()?) != CL_DEVICE_TOPOLOGY_TYPE_PCIE_AMD {
+            return None;
+        }
+        Some(PciAddress { domain: 0, bus: topology[21] as u32, device: topology[22] as u32 })
+    }
+
+    // A sysfs PCI device name, domain:bus:device.function in hex
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        let mut parts = name.split([':', '.']).map(|part| u32::from_str_radix(part, 16).ok());
+        let (domain, bus, device) = (parts.next()??, parts.next()??, parts.next()??);
+        Some(PciAddress { domain, bus, device })
+    }
+
+    // NVML's index for the GPU at this address
+    pub(crate) fn nvml_index(self, nvml: &Nvml) -> Option<u32> {
+        (0..nvml.device_count().ok()?).find(|&index| {
+            nvml.device_by_index(index)
+                .and_then(|device| device.pci_info())
+                .is_ok_and(|pci| PciAddress { domain: pci.domain, bus: pci.bus, device: pci.device } == self)
+        })
+    }
+}
+
+// As in sysfs names, without the function
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04x}:{:02x}:{:02x}", self.domain, self.bus, self.device)
+    }
+}
@@ -0,0 +1,282 @@
+//! The primality test the kernels run, as a host reference.
+//!
+//! [`KERNEL_SRC`](crate::KERNEL_SRC) spells out the same witness sets and wheel as C literals;
+//! the tests check the source against the constants here, so a change to one that isn't made
+//! to the other fails CI rather than letting the CPU check and the GPU disagree.
+
+/// Miller-Rabin witnesses that make the test deterministic for every n < 2^64.
+pub const WITNESSES_U64: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// The wide kernel's witnesses: deterministic below 3.3 * 10^24, a strong probable-prime test
+/// above that.
+pub const WITNESSES_WIDE: [u64; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// The primes the kernel rules out as factors before the full test.
+pub const SMALL_PRIMES: [u64; 15] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
+
+/// Bit i is set when i is coprime to 2 * 3 * 5 * 7 = 210.
+pub const WHEEL_210: [u64; 4] = wheel_210();
+
+const fn wheel_210() -> [u64; 4] {
+    let mut wheel = [0; 4];
+    let mut i = 0;
+    while i < 210 {
+        if i % 2 != 0 && i % 3 != 0 && i % 5 != 0 && i % 7 != 0 {
+            wheel[i / 64] |= 1 << (i % 64);
+        }
+        i += 1;
+    }
+    wheel
+}
+
+/// Whether one of [`SMALL_PRIMES`] divides n, by the kernel's route: the wheel, then the
+/// remaining primes through two products that each fit in 32 bits.
+pub fn has_small_factor(n: u64) -> bool {
+    let w = (n % 210) as usize;
+    if (WHEEL_210[w / 64] >> (w % 64)) & 1 == 0 {
+        return true;
+    }
+    let a = n % (11 * 13 * 17 * 19 * 23);
+    let b = n % (29 * 31 * 37 * 41 * 43 * 47);
+    SMALL_PRIMES[4..9].iter().any(|&p| a.is_multiple_of(p)) || SMALL_PRIMES[9..].iter().any(|&p| b.is_multiple_of(p))
+}
+
+/// The kernel's test for [`Algorithm::TrialDivision`](crate::Algorithm::TrialDivision): 2, 3
+/// and then the pairs 6k - 1 and 6k + 1 for as long as [`trial_divisor_in_bound`] holds.
+pub fn is_prime_by_trial_division(n: u64) -> bool {
+    if n <= 1 {
+        return false;
+    }
+    if n <= 3 {
+        return true;
+    }
+    if n.is_multiple_of(2) || n.is_multiple_of(3) {
+        return false;
+    }
+    let mut i = 5;
+    while trial_divisor_in_bound(i, n) {
+        if n.is_multiple_of(i) || n.is_multiple_of(i + 2) {
+            return false;
+        }
+        i += 6;
+    }
+    true
+}
+
+/// Whether trial division of n goes on to try i, that is i <= sqrt(n). Written as
+/// `i <= n / i`, as in the kernel, because `i * i` wraps once i passes 2^32 and never ends the
+/// loop for n near 2^64.
+pub const fn trial_divisor_in_bound(i: u64, n: u64) -> bool {
+    i <= n / i
+}
+
+/// The kernel's Miller-Rabin test: the small-prime precheck, then every witness in
+/// [`WITNESSES_U64`].
+pub fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n <= 47 {
+        return SMALL_PRIMES.contains(&n);
+    }
+    if has_small_factor(n) {
+        return false;
+    }
+    WITNESSES_U64.iter().all(|&a| is_strong_probable_prime(n, a))
+}
+
+// The Miller-Rabin round: with n - 1 = d * 2^s and d odd, a^d = 1 or a^(d * 2^r) = n - 1 for
+// some r < s
+fn is_strong_probable_prime(n: u64, a: u64) -> bool {
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    let mut x = pow_mod(a, d, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    (1..s).any(|_| {
+        x = mul_mod(x, x, n);
+        x == n - 1
+    })
+}
+
+/// The kernel's Baillie-PSW test for [`Algorithm::Bpsw`](crate::Algorithm::Bpsw): the
+/// small-prime precheck, a Miller-Rabin round to base 2, then a strong Lucas probable-prime
+/// test with Selfridge's parameters. Feitsma and Galway's list of the base-2 pseudoprimes
+/// below 2^64 holds none that pass the Lucas test, so this agrees with [`is_prime_u64`].
+pub fn is_prime_bpsw(n: u64) -> bool {
+    if n <= 47 {
+        return SMALL_PRIMES.contains(&n);
+    }
+    !has_small_factor(n) && is_strong_probable_prime(n, 2) && is_strong_lucas_probable_prime(n)
+}
+
+// The Jacobi symbol (a / m) for odd m
+fn jacobi(mut a: u64, mut m: u64) -> i32 {
+    let mut t = 1;
+    a %= m;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if m % 8 == 3 || m % 8 == 5 {
+                t = -t;
+            }
+        }
+        (a, m) = (m, a);
+        if a % 4 == 3 && m % 4 == 3 {
+            t = -t;
+        }
+        a %= m;
+    }
+    if m == 1 { t } else { 0 }
+}
+
+// Selfridge's D: the first of 5, -7, 9, -11, ... with (D / n) = -1, or None when n is a square,
+// which has none, or shares a factor with a smaller |D|. Every D is 1 mod 4, so
+// (D / n) = (n mod |D| / |D|).
+fn selfridge_d(n: u64) -> Option<i64> {
+    for abs in (5..).step_by(2) {
+        if abs == 21 && n.isqrt() * n.isqrt() == n {
+            return None;
+        }
+        match jacobi(n % abs, abs) {
+            -1 => return Some(if abs % 4 == 3 { -(abs as i64) } else { abs as i64 }),
+            0 if abs < n => return None,
+            _ => {}
+        }
+    }
+    unreachable!("some D has (D / n) = -1 for every n that isn't a square")
+}
+
+// With P = 1, Q = (1 - D) / 4 and n + 1 = k * 2^s for odd k, U_k = 0 or V_(k * 2^r) = 0 for
+// some r < s
+fn is_strong_lucas_probable_prime(n: u64) -> bool {
+    let Some(d) = selfridge_d(n) else {
+        return false;
+    };
+    let reduce = |x: i64| (x as i128).rem_euclid(n as i128) as u64;
+    let add = |a: u64, b: u64| ((a as u128 + b as u128) % n as u128) as u64;
+    let sub = |a: u64, b: u64| add(a, n - b);
+    let half = |a: u64| ((a as u128 + if a.is_multiple_of(2) { 0 } else { n as u128 }) / 2) as u64;
+    let (d, q) = (reduce(d), reduce((1 - d) / 4));
+
+    // n + 1 can't wrap, since u64::MAX has the factor 3
+    let s = (n + 1).trailing_zeros();
+    let k = (n + 1) >> s;
+    let (mut u, mut v, mut qk) = (1, 1, q);
+    for bit in (0..63 - k.leading_zeros()).rev() {
+        (u, v, qk) = (mul_mod(u, v, n), sub(mul_mod(v, v, n), add(qk, qk)), mul_mod(qk, qk, n));
+        if (k >> bit) & 1 == 1 {
+            (u, v, qk) = (half(add(u, v)), half(add(mul_mod(d, u, n), v)), mul_mod(qk, q, n));
+        }
+    }
+    if u == 0 || v == 0 {
+        return true;
+    }
+    (1..s).any(|_| {
+        (v, qk) = (sub(mul_mod(v, v, n), add(qk, qk)), mul_mod(qk, qk, n));
+        v == 0
+    })
+}
+
+fn mul_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 * b as u128) % n as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, n: u64) -> u64 {
+    let mut result = 1 % n;
+    base %= n;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, n);
+        }
+        base = mul_mod(base, base, n);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Evidence that `n` is prime which can be checked without rerunning the search: the
+/// Miller-Rabin round each of [`WITNESSES_U64`] passed. With n - 1 = d * 2^s and d odd, a
+/// prime n has every witness a either give a^d = 1 (mod n) or reach a^(d * 2^r) = n - 1 for
+/// some r < s. Sorenson and Webster showed that no composite below 3.18 * 10^23 passes all
+/// twelve witnesses, so together they prove any `u64` prime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    pub n: u64,
+    pub d: u64,
+    pub s: u32,
+    /// One round per witness, in the order of [`WITNESSES_U64`]. Witnesses that are multiples
+    /// of n, which only happens when n is one of them, test nothing and are left out.
+    pub rounds: Vec<WitnessRound>,
+}
+
+/// How one witness of a [`Certificate`] passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessRound {
+    pub witness: u64,
+    /// The r with a^(d * 2^r) = n - 1, or None when a^d = 1
+    pub squarings: Option<u32>,
+}
+
+impl Certificate {
+    /// Checks the certificate from scratch: that d and s split n - 1, that it has a round for
+    /// every witness, and that each round holds.
+    pub fn check(&self) -> bool {
+        let n = self.n;
+        if n < 2 || self.s != (n - 1).trailing_zeros() || self.d != (n - 1) >> self.s {
+            return false;
+        }
+        let witnesses: Vec<u64> = WITNESSES_U64.iter().copied().filter(|a| !a.is_multiple_of(n)).collect();
+        if self.rounds.iter().map(|round| round.witness).ne(witnesses) {
+            return false;
+        }
+        self.rounds.iter().all(|round| {
+            let x = pow_mod(round.witness, self.d, n);
+            match round.squarings {
+                None => x == 1,
+                Some(r) => r < self.s && (0..r).fold(x, |x, _| mul_mod(x, x, n)) == n - 1,
+            }
+        })
+    }
+}
+
+/// A [`Certificate`] for `n`, or None if it isn't prime.
+pub fn certificate(n: u64) -> Option<Certificate> {
+    if n < 2 {
+        return None;
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    let mut rounds = vec![];
+    for &a in WITNESSES_U64.iter().filter(|a| !a.is_multiple_of(n)) {
+        let mut x = pow_mod(a, d, n);
+        let squarings = if x == 1 {
+            None
+        } else {
+            let r = (0..s).find(|_| {
+                let reached = x == n - 1;
+                x = mul_mod(x, x, n);
+                reached
+            })?;
+            Some(r)
+        };
+        rounds.push(WitnessRound { witness: a, squarings });
+    }
+    Some(Certificate { n, d, s, rounds })
+}
+
+/// `rounds` witnesses for [`Algorithm::MillerRabinProbabilistic`](crate::Algorithm::MillerRabinProbabilistic),
+/// drawn from `seed` with SplitMix64 so the same seed always gives the same witnesses. Each
+/// is at least 2, and so between 1 and n - 1 for every candidate past 2^64, which is all the
+/// probabilistic test runs on.
+pub fn random_witnesses(seed: u64, rounds: u32) -> Vec<u64> {
+    let mut state = seed;
+    (0..rounds).map(|_| {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)).max(2)
+    }).collect()
+}
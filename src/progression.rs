@@ -0,0 +1,96 @@
+use std::{fmt, ops::Range};
+
+use crate::{PrimeError, PrimeSearcher, Result, Target};
+
+/// The numbers `modulus * k + residue` for k = 0, 1, 2 and so on, searched by
+/// [`PrimeSearcher::find_in_progression`]. The primes that are 1 mod 4 are those of
+/// `Progression::new(4, 1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progression {
+    modulus: u64,
+    residue: u64,
+}
+
+impl Progression {
+    /// Fails unless `modulus` is positive and coprime to `residue`. Otherwise every term is
+    /// divisible by their common factor, so at most one of them can be prime.
+    pub fn new(modulus: u64, residue: u64) -> Result<Self> {
+        if modulus == 0 {
+            return Err(PrimeError::InvalidProgression("the modulus must be positive".into()));
+        }
+        let common = gcd(modulus, residue);
+        if common != 1 {
+            return Err(PrimeError::InvalidProgression(format!(
+                "every term of {}k + {} is divisible by {}, so it holds at most one prime", modulus, residue, common,
+            )));
+        }
+        Ok(Progression { modulus, residue })
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    pub fn residue(&self) -> u64 {
+        self.residue
+    }
+
+    pub fn contains(&self, n: u64) -> bool {
+        n >= self.residue && (n - self.residue).is_multiple_of(self.modulus)
+    }
+
+    /// The first term at or after `n`, or None if it would be past `u64::MAX`.
+    pub fn next_term(&self, n: u64) -> Option<u64> {
+        if n <= self.residue {
+            return Some(self.residue);
+        }
+        match (n - self.residue) % self.modulus {
+            0 => Some(n),
+            behind => n.checked_add(self.modulus - behind),
+        }
+    }
+
+    /// Whether any term lies in `range`.
+    pub fn meets(&self, range: &Range<u64>) -> bool {
+        self.next_term(range.start).is_some_and(|n| n < range.end)
+    }
+
+    // Like meets, warning when there's nothing to search
+    pub(crate) fn check_meets(&self, range: &Range<u64>) -> bool {
+        let meets = self.meets(range);
+        if !meets {
+            warn!("No number in [{}, {}) is of the form {}, so there is no prime to find", range.start, range.end, self);
+        }
+        meets
+    }
+}
+
+impl fmt::Display for Progression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}k + {}", self.modulus, self.residue)
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl PrimeSearcher {
+    /// Like [`find_first`](Self::find_first), but only tests the numbers `a * k + b` in the
+    /// range, returning the smallest prime among them.
+    ///
+    /// Each device thread steps from term to term, so the kernels skip the numbers in between
+    /// rather than testing and discarding them. Progress and the report's tested counts still
+    /// measure how far through the range each device got. Fails if `a` and `b` have a common
+    /// factor, see [`Progression::new`].
+    pub fn find_in_progression(&self, a: u64, b: u64) -> Result<Option<u64>> {
+        let progression = Progression::new(a, b)?;
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_in_progression is not available for searchers created with new_u128".into()));
+        }
+        Ok(self.search_first(&self.range, Target::Progression(progression))?.map(|prime| prime as u64))
+    }
+}
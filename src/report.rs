@@ -0,0 +1,147 @@
+use std::{sync::Mutex, time::{Duration, Instant}};
+
+use crate::DeviceInfo;
+
+/// What one device did during a search.
+#[derive(Debug, Clone)]
+pub struct DeviceReport {
+    pub name: String,
+    /// Candidates the device's threads got through
+    pub tested: u64,
+    /// Candidates left in the device's slice ahead of its slowest thread as of the last status
+    /// read, or of the chunk it is on with dynamic partitioning; 0 once it has stopped on its
+    /// own. Read during a search, a device with far more left than the others is a straggler.
+    pub remaining: u64,
+    /// Time from the start of the search until the device stopped
+    pub wall_time: Duration,
+    /// Highest temperature seen, if the device was monitored
+    pub peak_temperature: Option<u32>,
+    /// Whether this device reported the prime that was returned
+    pub found_prime: bool,
+    /// Time the device spent running the search's kernels, measured on the device. Only
+    /// recorded with [`SearcherConfig::queue_profiling`](crate::SearcherConfig::queue_profiling)
+    pub kernel_time: Option<Duration>,
+    /// Kernel runs added up in `kernel_time`: one per slice and verification round, one per
+    /// chunk with dynamic partitioning and one per segment with the segmented sieve
+    pub kernel_launches: u64,
+}
+
+/// Per-device statistics for the last search, in the order of
+/// [`PrimeSearcher::devices`](crate::PrimeSearcher::devices).
+#[derive(Debug, Clone)]
+pub struct SearchReport {
+    pub devices: Vec<DeviceReport>,
+    pub elapsed: Duration,
+    /// Platform, name and enumeration index of the device that reported the prime that was
+    /// returned, which the position in `devices` alone doesn't pin down on mixed-vendor systems
+    pub found_by: Option<DeviceInfo>,
+}
+
+impl SearchReport {
+    /// Adds `other`, a later search on the same devices, to this report: counts and times are
+    /// summed, the peak temperature is the higher of the two, `remaining` is the later
+    /// search's, and `found_by` keeps the first device that found a prime.
+    pub fn merge(&mut self, other: &SearchReport) {
+        for (device, later) in self.devices.iter_mut().zip(&other.devices) {
+            device.tested += later.tested;
+            device.remaining = later.remaining;
+            device.wall_time += later.wall_time;
+            device.peak_temperature = device.peak_temperature.max(later.peak_temperature);
+            device.found_prime |= later.found_prime;
+            device.kernel_time = match (device.kernel_time, later.kernel_time) {
+                (Some(time), Some(later)) => Some(time + later),
+                (time, later) => time.or(later),
+            };
+            device.kernel_launches += later.kernel_launches;
+        }
+        self.elapsed += other.elapsed;
+        if self.found_by.is_none() {
+            self.found_by = other.found_by.clone();
+        }
+    }
+}
+
+// Filled in by the monitor threads as a search runs
+pub(crate) struct ReportTracker {
+    devices: Vec<DeviceInfo>,
+    state: Mutex<(Instant, SearchReport)>,
+}
+
+impl ReportTracker {
+    pub(crate) fn new(devices: Vec<DeviceInfo>) -> Self {
+        let reports = devices.iter().map(|device| DeviceReport {
+            name: device.name.clone(),
+            tested: 0,
+            remaining: 0,
+            wall_time: Duration::ZERO,
+            peak_temperature: None,
+            found_prime: false,
+            kernel_time: None,
+            kernel_launches: 0,
+        }).collect();
+        let report = SearchReport { devices: reports, elapsed: Duration::ZERO, found_by: None };
+        ReportTracker { devices, state: Mutex::new((Instant::now(), report)) }
+    }
+
+    pub(crate) fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = Instant::now();
+        state.1.elapsed = Duration::ZERO;
+        state.1.found_by = None;
+        for device in &mut state.1.devices {
+            device.tested = 0;
+            device.remaining = 0;
+            device.wall_time = Duration::ZERO;
+            device.peak_temperature = None;
+            device.found_prime = false;
+            device.kernel_time = None;
+            device.kernel_launches = 0;
+        }
+    }
+
+    pub(crate) fn tested(&self, device: usize) -> u64 {
+        self.state.lock().unwrap().1.devices[device].tested
+    }
+
+    // Returns how many more candidates this is than the last count
+    pub(crate) fn record_tested(&self, device: usize, tested: u64) -> u64 {
+        let previous = std::mem::replace(&mut self.state.lock().unwrap().1.devices[device].tested, tested);
+        tested.saturating_sub(previous)
+    }
+
+    pub(crate) fn record_remaining(&self, device: usize, remaining: u64) {
+        self.state.lock().unwrap().1.devices[device].remaining = remaining;
+    }
+
+    pub(crate) fn record_temperature(&self, device: usize, temperature: u32) {
+        let mut state = self.state.lock().unwrap();
+        let peak = &mut state.1.devices[device].peak_temperature;
+        *peak = Some(peak.map_or(temperature, |peak| peak.max(temperature)));
+    }
+
+    // Adds one kernel's profiled run to the device's total
+    pub(crate) fn add_kernel_time(&self, device: usize, time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let report = &mut state.1.devices[device];
+        report.kernel_time = Some(report.kernel_time.unwrap_or_default() + time);
+        report.kernel_launches += 1;
+    }
+
+    pub(crate) fn mark_found(&self, device: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.1.devices[device].found_prime = true;
+        state.1.found_by = Some(self.devices[device].clone());
+    }
+
+    // Called as each device stops; a device relaunched later in the same search is stopped again
+    pub(crate) fn finish_device(&self, device: usize) {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.0.elapsed();
+        state.1.devices[device].wall_time = elapsed;
+        state.1.elapsed = state.1.elapsed.max(elapsed);
+    }
+
+    pub(crate) fn report(&self) -> SearchReport {
+        self.state.lock().unwrap().1.clone()
+    }
+}
@@ -0,0 +1,67 @@
+use ocl::{Event, Kernel, enums::Status};
+use std::{thread, time::Duration};
+
+use crate::{PrimeError, Result, order};
+
+/// How a device's buffer reads and kernel enqueues are retried when the driver reports a
+/// transient failure, such as running out of resources under load.
+///
+/// Each retry waits twice as long as the one before. Other errors, and a transient one that
+/// persists past `max_attempts`, are returned as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per operation, the first one included; 1 disables retries.
+    pub max_attempts: u32,
+    /// How long the first retry waits.
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 4, initial_backoff: Duration::from_millis(50) }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts, waiting 50 ms before the first retry.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy { max_attempts: max_attempts.max(1), ..Self::default() }
+    }
+
+    /// Sets how long the first retry waits.
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    // Runs `op` until it succeeds, fails for good, or the attempts run out, logging each retry
+    pub(crate) fn run<T>(&self, device: usize, what: &str, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    warn!("GPU {}: {} failed ({}), retrying in {:?} ({} of {})", device, what, e, backoff, attempt + 1, self.max_attempts);
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Enqueues a kernel with its arguments set, after everything already on its queue,
+    // returning its completion event
+    pub(crate) fn enqueue(&self, device: usize, kernel: &Kernel) -> Result<Event> {
+        self.run(device, "kernel launch", || order::enqueue_kernel(kernel))
+    }
+}
+
+// Failures a busy or briefly starved driver reports that can succeed on a second try
+fn is_transient(e: &PrimeError) -> bool {
+    let PrimeError::Ocl(e) = e else {
+        return false;
+    };
+    matches!(e.api_status(), Some(Status::CL_OUT_OF_RESOURCES | Status::CL_OUT_OF_HOST_MEMORY | Status::CL_MEM_OBJECT_ALLOCATION_FAILURE))
+}
@@ -0,0 +1,47 @@
+use crate::{Algorithm, PrimeError, PrimeSearcher, Result, verify};
+
+// Edge cases, squares of small primes, Carmichael numbers and a base-2 strong pseudoprime,
+// small enough for trial division to get through in one work-item
+const SMALL_CHECKS: [u64; 24] = [
+    0, 1, 2, 3, 4, 5, 9, 25, 49, 97, 561, 1105, 1729, 2047, 2465, 2821, 6601, 8911,
+    1_000_000_007, 4_294_967_291, 4_294_967_297, 4_294_967_311, 999_999_999_989, 999_985_999_949,
+];
+
+// Strong pseudoprimes to the first several bases, and primes and a semiprime near 2^64 that
+// take the modular multiplication through its full width. Trial division would need billions
+// of divisions for these, so only Miller-Rabin is checked on them.
+const WIDE_CHECKS: [u64; 5] = [
+    3_215_031_751,
+    3_825_123_056_546_413_051,
+    2_305_843_009_213_693_951,
+    18_446_743_979_220_271_189,
+    18_446_744_073_709_551_557,
+];
+
+impl PrimeSearcher {
+    /// Tests a fixed set of primes and composites, including 0, 1, Carmichael numbers and
+    /// strong pseudoprimes, on every device and compares each answer with the CPU, to catch
+    /// a broken driver or miscompiled kernel before a long search.
+    ///
+    /// The searcher's algorithm is checked, or Miller-Rabin for algorithms that don't test
+    /// 64-bit candidates one at a time. Fails with [`PrimeError::SelfCheck`] on the first
+    /// wrong answer.
+    pub fn self_check(&self) -> Result<()> {
+        let algorithm = match self.algorithm {
+            Algorithm::TrialDivision => Algorithm::TrialDivision,
+            Algorithm::Bpsw => Algorithm::Bpsw,
+            _ => Algorithm::MillerRabin,
+        };
+        let wide: &[u64] = if algorithm == Algorithm::TrialDivision { &[] } else { &WIDE_CHECKS };
+        for (i, device) in self.devices.iter().enumerate() {
+            for &n in SMALL_CHECKS.iter().chain(wide) {
+                let reported_prime = self.test_number_on(i, n, algorithm)?;
+                if reported_prime != verify::is_prime(n) {
+                    return Err(PrimeError::SelfCheck { device: device.to_string(), n, reported_prime });
+                }
+            }
+            debug!("GPU {}: self-check passed", i);
+        }
+        Ok(())
+    }
+}
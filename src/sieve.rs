@@ -0,0 +1,169 @@
+use ocl::{Buffer, MemFlags};
+use ocl::enums::{DeviceInfo as DeviceInfoKind, DeviceInfoResult};
+use std::{ops::Range, thread};
+
+use crate::{PrimeSearcher, Result, order, partition_range, record_kernel_time, work_group};
+
+// Numbers each thread of base_primes_up_to marks at a time, keeping its working set in cache
+const HOST_SEGMENT: u64 = 1 << 18;
+
+/// Every prime up to and including `limit`, in ascending order.
+///
+/// The primes up to the square root are sieved on the calling thread, then the rest of the
+/// range is split evenly and sieved segment by segment on one thread per available core.
+pub fn base_primes_up_to(limit: u64) -> Vec<u64> {
+    let root = limit.isqrt();
+    let mut primes = small_primes(root);
+    if root >= limit {
+        return primes;
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunks = partition_range(root + 1..limit.saturating_add(1), threads);
+    let sieved: Vec<Vec<u64>> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks.into_iter()
+            .map(|chunk| {
+                let small = &primes;
+                scope.spawn(move || sieve_range(chunk, small))
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+    primes.extend(sieved.into_iter().flatten());
+    primes
+}
+
+// Plain sieve of Eratosthenes, only ever run up to a square root
+fn small_primes(limit: u64) -> Vec<u64> {
+    let limit = limit as usize;
+    let mut composite = vec![false; limit + 1];
+    let mut primes = vec![];
+    for n in 2..=limit {
+        if composite[n] {
+            continue;
+        }
+        primes.push(n as u64);
+        for multiple in (n * n..=limit).step_by(n) {
+            composite[multiple] = true;
+        }
+    }
+    primes
+}
+
+// Primes in `range`, which must not extend past the square of the largest of `small`
+fn sieve_range(range: Range<u64>, small: &[u64]) -> Vec<u64> {
+    let mut primes = vec![];
+    let mut composite = vec![false; HOST_SEGMENT.min(range.end - range.start) as usize];
+    let mut start = range.start;
+    while start < range.end {
+        let len = HOST_SEGMENT.min(range.end - start);
+        let marks = &mut composite[..len as usize];
+        marks.fill(false);
+        for &p in small.iter().take_while(|&&p| p * p < start + len) {
+            let first = (p * p).max(start.div_ceil(p) * p);
+            for multiple in (first..start + len).step_by(p as usize) {
+                marks[(multiple - start) as usize] = true;
+            }
+        }
+        primes.extend(marks.iter().enumerate().filter(|&(_, &c)| !c).map(|(offset, _)| start + offset as u64));
+        start += len;
+    }
+    primes
+}
+
+impl PrimeSearcher {
+    // find_all for Algorithm::SegmentedSieve: each device sieves its slice one segment at a time
+    pub(crate) fn sieve_all(&self, range: &Range<u64>) -> Result<Vec<u64>> {
+        let slices = self.slices_of(range);
+        self.start_tracking(range, &slices);
+        // Every base prime is at most sqrt(u64::MAX) < 2^32, so they are passed to the kernel as uints
+        let base: Vec<u32> = base_primes_up_to(range.end.saturating_sub(1).isqrt()).into_iter().map(|p| p as u32).collect();
+
+        let results: Result<Vec<Vec<u64>>> = thread::scope(|scope| {
+            let handles: Vec<_> = slices.iter().enumerate()
+                .map(|(i, slice)| {
+                    let base = &base;
+                    scope.spawn(move || self.sieve_slice(i, slice.clone(), base))
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        // Slices are contiguous and ascending, so their primes come out in order
+        Ok(results?.into_iter().flatten().collect())
+    }
+
+    fn sieve_slice(&self, i: usize, slice: Range<u64>, base: &[u32]) -> Result<Vec<u64>> {
+        let mut primes = vec![];
+        if slice.is_empty() {
+            return Ok(primes);
+        }
+
+        let pq = &self.pro_ques[i];
+        let halt = self.cancel.halt_kernels();
+        halt.flag(i).cmd().fill(0, None).enq()?;
+
+        // Segments are capped by the largest buffer the device can allocate
+        let max_alloc = match pq.device().info(DeviceInfoKind::MaxMemAllocSize)? {
+            DeviceInfoResult::MaxMemAllocSize(size) => size,
+            _ => u64::MAX,
+        };
+        let segment_len = (self.segment_size as u64).min(max_alloc).min(slice.end - slice.start).max(1);
+
+        // The kernel strides over the base primes, so it needs a non-empty buffer even without any
+        let host_base = if base.is_empty() { vec![0] } else { base.to_vec() };
+        let base_buffer = Buffer::<u32>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(host_base.len())
+            .copy_host_slice(&host_base)
+            .build()?;
+        let composite = Buffer::<u8>::builder()
+            .queue(pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(segment_len as usize)
+            .build()?;
+
+        let mut marks = vec![0u8; segment_len as usize];
+        let mut segment_start = slice.start;
+        while segment_start < slice.end && !self.cancel.is_cancelled() {
+            let len = segment_len.min(slice.end - segment_start);
+            composite.cmd().fill(0u8, Some(len as usize)).enq()?;
+
+            let kernel = pq.kernel_builder("sieve_segment")
+                .global_work_size(self.thread_counts[i])
+                .local_work_size(work_group(self.local_size))
+                .arg(segment_start)
+                .arg(len)
+                .arg(&base_buffer)
+                .arg(base.len() as u32)
+                .arg(&composite)
+                .arg(halt.flag(i))
+                .build()?;
+            let done = order::enqueue_kernel(&kernel)?;
+            // The read waits for the kernel to finish
+            composite.read(&mut marks[..len as usize]).len(len as usize).ewait(&done).enq()?;
+            if self.queue_profiling {
+                record_kernel_time(&self.report, i, &done);
+            }
+
+            primes.extend(marks[..len as usize].iter().enumerate()
+                .filter(|&(_, &mark)| mark == 0)
+                .map(|(offset, _)| segment_start + offset as u64)
+                .filter(|&n| n >= 2));
+
+            let segment_end = segment_start + len;
+            self.progress.lock().unwrap()[i] = segment_end - 1;
+            let added = self.report.record_tested(i, segment_end - slice.start);
+            if let Some(metrics) = &self.metrics {
+                metrics.add_tested(i, &self.devices[i].name, added);
+            }
+            if let Some(callback) = &self.status_callback {
+                callback(i, &[segment_end]);
+            }
+            segment_start = segment_end;
+        }
+        self.report.finish_device(i);
+        Ok(primes)
+    }
+}
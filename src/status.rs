@@ -0,0 +1,311 @@
+use ocl::{Buffer, Event, FutureMemMap, Queue};
+use ocl::enums::{EventInfo, EventInfoResult};
+use ocl::core::CommandExecutionStatus;
+use std::{sync::{Arc, Mutex, mpsc::{Receiver, Sender}}, time::{Instant, SystemTime}};
+
+use crate::{GpuStats, Metrics, PrimeCallback, PrimeEvent, ProgressCallback, ProgressEvent, Result, RetryPolicy, StatusCallback, StatusThreads, TelemetryLog};
+use crate::eta::{self, Eta, RateEstimate};
+
+// What a monitor thread read from its device on one poll
+pub(crate) struct StatusUpdate {
+    pub(crate) device: usize,
+    // The last candidate each of the device's threads tested
+    pub(crate) thread_statuses: Vec<u64>,
+    // Candidates tested since the previous update
+    pub(crate) tested: u64,
+    // Candidates tested in the whole search so far
+    pub(crate) total_tested: u64,
+    // Candidates left in the device's slice ahead of its slowest thread, 0 once it stops
+    pub(crate) remaining: u64,
+    // Read every monitor interval
+    pub(crate) gpu_stats: Option<GpuStats>,
+    // Read on every poll to apply a thermal limit
+    pub(crate) temperature: Option<u32>,
+}
+
+// What the monitor threads send the display thread
+pub(crate) enum Reading {
+    Status(StatusUpdate),
+    Prime(PrimeEvent),
+}
+
+// Hands the primes a search finds to the prime callback: through the display thread while
+// one is running, so the callback sees them one at a time in the order they were verified,
+// and directly otherwise
+#[derive(Clone, Default)]
+pub(crate) struct PrimeHook {
+    callback: Option<PrimeCallback>,
+    display: Arc<Mutex<Option<Sender<Reading>>>>,
+}
+
+impl PrimeHook {
+    pub(crate) fn new(callback: PrimeCallback) -> Self {
+        PrimeHook { callback: Some(callback), display: Arc::default() }
+    }
+
+    pub(crate) fn callback(&self) -> Option<PrimeCallback> {
+        self.callback.clone()
+    }
+
+    pub(crate) fn attach(&self, display: Sender<Reading>) {
+        *self.display.lock().unwrap() = Some(display);
+    }
+
+    pub(crate) fn detach(&self) {
+        *self.display.lock().unwrap() = None;
+    }
+
+    pub(crate) fn found(&self, device: usize, value: u128) {
+        let Some(callback) = &self.callback else {
+            return;
+        };
+        let event = PrimeEvent { value, device, found_at: SystemTime::now() };
+        match &*self.display.lock().unwrap() {
+            Some(display) => {
+                let _ = display.send(Reading::Prime(event));
+            }
+            None => (callback.lock().unwrap())(event),
+        }
+    }
+}
+
+// Where the monitor threads' readings are shown, logged and passed on
+pub(crate) struct StatusSink {
+    pub(crate) names: Vec<String>,
+    pub(crate) print_status: bool,
+    pub(crate) status_threads: StatusThreads,
+    pub(crate) callback: Option<StatusCallback>,
+    pub(crate) on_progress: Option<ProgressCallback>,
+    // Where each device's latest statuses are kept
+    pub(crate) latest: Arc<Mutex<Vec<Vec<u64>>>>,
+    pub(crate) on_prime: Option<PrimeCallback>,
+    pub(crate) metrics: Option<Arc<Metrics>>,
+    pub(crate) telemetry: Option<Arc<TelemetryLog>>,
+    // The time left, estimated from the rate each device's remaining count goes down
+    pub(crate) eta: Arc<Mutex<Eta>>,
+}
+
+impl StatusSink {
+    // Handles updates one at a time until every monitor thread hangs up, so readings from
+    // different devices never interleave and a slow consumer never delays a read
+    pub(crate) fn consume(&self, readings: Receiver<Reading>) {
+        let mut rates = vec![RateEstimate::new(); self.names.len()];
+        let mut remaining = vec![0; self.names.len()];
+        for reading in readings {
+            let update = match reading {
+                Reading::Status(update) => update,
+                Reading::Prime(event) => {
+                    if let Some(on_prime) = &self.on_prime {
+                        (on_prime.lock().unwrap())(event);
+                    }
+                    continue;
+                }
+            };
+            let i = update.device;
+            let name = &self.names[i];
+            rates[i].record(Instant::now(), update.remaining);
+            remaining[i] = update.remaining;
+            let (device_eta, total_eta) = {
+                let mut eta = self.eta.lock().unwrap();
+                for (j, rate) in rates.iter().enumerate() {
+                    eta.devices[j] = rate.time_left(remaining[j]);
+                }
+                // The whole search goes at the combined rate, once every device still working
+                // has one
+                let working = || rates.iter().zip(&remaining).filter(|&(_, &left)| left > 0);
+                eta.total = working().map(|(rate, _)| rate.rate()).sum::<Option<f64>>()
+                    .and_then(|rate| eta::time_at(remaining.iter().sum(), rate));
+                (eta.devices[i], eta.total)
+            };
+            if let Some(callback) = &self.callback {
+                callback(i, &update.thread_statuses);
+            }
+            if let Some(on_progress) = &self.on_progress {
+                on_progress(ProgressEvent { device: i, tested: update.total_tested, remaining: update.remaining });
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.add_tested(i, name, update.tested);
+                if let Some(temperature) = update.temperature {
+                    metrics.record_temperature(i, name, temperature);
+                }
+                if let Some(stats) = &update.gpu_stats {
+                    metrics.record_stats(i, name, stats);
+                }
+            }
+            if let (Some(log), Some(stats)) = (&self.telemetry, &update.gpu_stats) {
+                if let Err(e) = log.record(i, name, stats, update.remaining) {
+                    warn!("Failed to write telemetry: {}", e);
+                }
+            }
+            if !self.print_status {
+                self.latest.lock().unwrap()[i] = update.thread_statuses;
+                continue;
+            }
+
+            // Log the spread of the device's threads, and the status of a few at trace level. They
+            // go out as one record so each device's block stays together
+            let lowest = update.thread_statuses.iter().copied().min().unwrap_or(0);
+            let highest = update.thread_statuses.iter().copied().max().unwrap_or(0);
+            let mut block = format!(
+                "GPU {}: threads between {} and {}, {} candidates remaining, {} left ({} for the whole search)",
+                i, lowest, highest, update.remaining, eta::format_time_left(device_eta), eta::format_time_left(total_eta),
+            );
+            if log_enabled!(log::Level::Trace) {
+                for (j, tested) in self.status_threads.of(&update.thread_statuses).iter().enumerate() {
+                    block.push_str(&format!("\n  thread {}: {}", j, tested));
+                }
+            }
+            debug!("{}", block);
+            if let Some(stats) = &update.gpu_stats {
+                info!("GPU {}: {}", i, stats);
+            }
+            self.latest.lock().unwrap()[i] = update.thread_statuses;
+        }
+        // Every monitor thread has stopped, so the search's readings are all in
+        if let Err(e) = self.telemetry.as_ref().map_or(Ok(()), |log| log.flush()) {
+            warn!("Failed to write telemetry: {}", e);
+        }
+    }
+}
+
+// Reads a device's status buffer through its control queue. A read on the compute queue
+// would wait for the running kernel to finish, so the monitor thread would see nothing until
+// then; on the control queue it overlaps the kernel, and since it doesn't block either, a
+// slow transfer delays the next reading instead of the monitor thread. The readings are only
+// progress, so a read that still fails after the retries is logged and the last one kept.
+//
+// Reads are ordered after the launch of the kernel they follow: none is made before the kernel
+// has started, by when its status buffer has been zeroed even on an out-of-order queue, and
+// the final one waits on the kernel's event.
+//
+// A status buffer in host-accessible memory is mapped on each poll instead, and unmapped as
+// soon as it has been copied: the kernel keeps writing to it, which it may not do to a buffer
+// that stays mapped.
+pub(crate) struct StatusRead {
+    device: usize,
+    buffer: Arc<Buffer<u64>>,
+    queue: Queue,
+    retry: RetryPolicy,
+    mapped: bool,
+    status: Vec<u64>,
+    kernel: Option<Event>,
+    pending: Option<(Event, PendingRead)>,
+}
+
+// A read in flight and the memory it lands in, which must outlive it
+enum PendingRead {
+    Copy(Box<[u64]>),
+    Map(FutureMemMap<u64>),
+}
+
+impl PendingRead {
+    // Once its event has completed, the reading it brought
+    fn finish(self, status: &mut [u64]) -> Result<()> {
+        match self {
+            PendingRead::Copy(read) => status.copy_from_slice(&read),
+            // Dropping the mapping unmaps it
+            PendingRead::Map(map) => status.copy_from_slice(&map.wait()?),
+        }
+        Ok(())
+    }
+}
+
+impl StatusRead {
+    pub(crate) fn new(device: usize, buffer: Arc<Buffer<u64>>, queue: Queue, retry: RetryPolicy, mapped: bool) -> Self {
+        let status = vec![0; buffer.len()];
+        StatusRead { device, buffer, queue, retry, mapped, status, kernel: None, pending: None }
+    }
+
+    // Waits for the read in flight, if any, and releases its memory without keeping the reading
+    fn discard_pending(&mut self) {
+        if let Some((event, read)) = self.pending.take() {
+            let _ = event.wait_for();
+            if let PendingRead::Map(map) = read {
+                drop(map.wait());
+            }
+        }
+    }
+
+    // Starts reading for a newly launched kernel, which begins from a zeroed buffer
+    pub(crate) fn follow(&mut self, kernel: &Event) {
+        self.discard_pending();
+        self.status.fill(0);
+        self.kernel = Some(kernel.clone());
+    }
+
+    // The latest completed reading, starting another read if none is in flight
+    pub(crate) fn poll(&mut self) -> &[u64] {
+        if let Err(e) = self.try_poll() {
+            warn!("GPU {}: failed to read thread status: {}", self.device, e);
+        }
+        &self.status
+    }
+
+    fn try_poll(&mut self) -> Result<()> {
+        if let Some(kernel) = &self.kernel {
+            if !has_started(kernel)? {
+                return Ok(());
+            }
+        }
+        if let Some((event, _)) = &self.pending {
+            if !event.is_complete()? {
+                return Ok(());
+            }
+            let (_, read) = self.pending.take().unwrap();
+            read.finish(&mut self.status)?;
+        }
+        let mut event = Event::empty();
+        let read = if self.mapped {
+            let map = self.retry.run(self.device, "status map", || {
+                // SAFETY: only one mapping of the buffer exists at a time, as the next is only
+                // made once this one has been read and dropped, and the host only reads it
+                Ok(unsafe { self.buffer.map().read().queue(&self.queue).enew(&mut event).enq_async()? })
+            })?;
+            PendingRead::Map(map)
+        } else {
+            let mut read = vec![0; self.buffer.len()].into_boxed_slice();
+            self.retry.run(self.device, "status read", || {
+                // SAFETY: the destination is kept alive in `pending` until the event completes,
+                // and Drop waits for it
+                unsafe {
+                    self.buffer.read(&mut read[..]).queue(&self.queue).block(false).enew(&mut event).enq()?;
+                }
+                Ok(())
+            })?;
+            PendingRead::Copy(read)
+        };
+        self.pending = Some((event, read));
+        Ok(())
+    }
+
+    // A fresh reading, waiting for it. Called once the kernel has finished, so the last
+    // report covers every candidate.
+    pub(crate) fn wait(&mut self) -> &[u64] {
+        self.discard_pending();
+        let (buffer, queue, status, kernel) = (&self.buffer, &self.queue, &mut self.status, &self.kernel);
+        let read = || {
+            let mut read = buffer.read(&mut *status).queue(queue);
+            if let Some(kernel) = kernel {
+                read = read.ewait(kernel);
+            }
+            Ok(read.enq()?)
+        };
+        if let Err(e) = self.retry.run(self.device, "status read", read) {
+            warn!("GPU {}: failed to read thread status: {}", self.device, e);
+        }
+        &self.status
+    }
+}
+
+fn has_started(event: &Event) -> Result<bool> {
+    Ok(match event.info(EventInfo::CommandExecutionStatus)? {
+        EventInfoResult::CommandExecutionStatus(status) => matches!(status, CommandExecutionStatus::Running | CommandExecutionStatus::Complete),
+        _ => true,
+    })
+}
+
+impl Drop for StatusRead {
+    fn drop(&mut self) {
+        self.discard_pending();
+    }
+}
@@ -0,0 +1,140 @@
+use std::{io::{self, BufReader, BufWriter, Read, Write}, sync::mpsc, thread};
+
+use crate::{LIST_WINDOW, PrimeError, PrimeSearcher, Result, partition_chunks};
+
+/// How [`PrimeSearcher::find_all_streaming`] writes each prime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamFormat {
+    /// The number alone on its line.
+    #[default]
+    Lines,
+    /// Newline-delimited JSON, `{"prime":N}` per line.
+    Json,
+    /// Each prime as 8 little-endian bytes, with no separators, header or trailer. See
+    /// [`read_primes_bin`].
+    Bin,
+}
+
+impl PrimeSearcher {
+    /// Like [`find_all`](Self::find_all), but writes the primes to `writer` in ascending order
+    /// as they come back instead of collecting them, returning how many were written.
+    ///
+    /// The range is searched window by window. Each window's primes are handed to a writer
+    /// thread, which flushes once they are written, so at most one window's worth of primes is
+    /// held in memory and a crash only loses those not yet flushed.
+    pub fn find_all_streaming(&self, writer: impl Write + Send, format: StreamFormat) -> Result<u64> {
+        if self.wide_start.is_some() {
+            return Err(PrimeError::Unsupported("find_all_streaming is not available for searchers created with new_u128".into()));
+        }
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u64>>(1);
+        thread::scope(|scope| {
+            let written = scope.spawn(move || write_primes(writer, format, receiver));
+
+            let mut searched = Ok(());
+            for window in partition_chunks(self.range.clone(), LIST_WINDOW) {
+                let primes = match self.find_all_in(&window) {
+                    Ok(primes) => primes,
+                    Err(e) => {
+                        searched = Err(e);
+                        break;
+                    }
+                };
+                // The writer only hangs up after failing, which its result reports
+                if sender.send(primes).is_err() || self.cancel.is_cancelled() {
+                    break;
+                }
+            }
+            drop(sender);
+
+            let written = written.join().expect("the prime writer thread panicked");
+            searched?;
+            Ok(written?)
+        })
+    }
+
+    /// The primes of the range in ascending order, found as they are asked for.
+    ///
+    /// Each call to `next` that runs out of primes searches the next window of the range,
+    /// blocking until it is done, and yields `None` once the range is exhausted. Windows start
+    /// small, so the first prime comes back quickly, and double up to the size
+    /// [`find_all_streaming`](Self::find_all_streaming) uses. No search runs between calls, so
+    /// dropping the iterator stops the search at the end of the window last searched. After an
+    /// error, or once the searcher is cancelled, it yields nothing more; a cancelled window may
+    /// be missing primes, so none of its primes are yielded.
+    pub fn iter(&self) -> impl Iterator<Item = Result<u64>> + '_ {
+        let mut pending = std::collections::VecDeque::new();
+        let mut start = self.range.start;
+        let mut window_len = ITER_FIRST_WINDOW;
+        let mut done = false;
+        std::iter::from_fn(move || {
+            while pending.is_empty() && !done {
+                if self.wide_start.is_some() {
+                    done = true;
+                    return Some(Err(PrimeError::Unsupported("iter is not available for searchers created with new_u128".into())));
+                }
+                if start >= self.range.end || self.cancel.is_cancelled() {
+                    done = true;
+                    break;
+                }
+                let window = start..start.saturating_add(window_len).min(self.range.end);
+                match self.find_all_in(&window) {
+                    Ok(_) if self.cancel.is_cancelled() => done = true,
+                    Ok(primes) => pending.extend(primes),
+                    Err(e) => {
+                        done = true;
+                        return Some(Err(e));
+                    }
+                }
+                start = window.end;
+                window_len = (window_len * 2).min(LIST_WINDOW);
+            }
+            pending.pop_front().map(Ok)
+        })
+    }
+}
+
+// The size of PrimeSearcher::iter's first window
+const ITER_FIRST_WINDOW: u64 = 1 << 16;
+
+fn write_primes(writer: impl Write, format: StreamFormat, batches: mpsc::Receiver<Vec<u64>>) -> io::Result<u64> {
+    let mut out = BufWriter::new(writer);
+    let mut written = 0;
+    for batch in batches {
+        for prime in &batch {
+            match format {
+                StreamFormat::Lines => writeln!(out, "{}", prime)?,
+                StreamFormat::Json => writeln!(out, "{{\"prime\":{}}}", prime)?,
+                StreamFormat::Bin => out.write_all(&prime.to_le_bytes())?,
+            }
+        }
+        out.flush()?;
+        written += batch.len() as u64;
+    }
+    Ok(written)
+}
+
+/// Writes `primes` in the binary format: each one as a little-endian `u64`, one after another.
+///
+/// The output is 8 bytes per prime in the order given, so a stream of `n` primes is `8 * n`
+/// bytes long and can be concatenated with others. This is what `--format bin` writes.
+pub fn write_primes_bin(mut writer: impl Write, primes: &[u64]) -> io::Result<()> {
+    for prime in primes {
+        writer.write_all(&prime.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads back primes written in the binary format by [`write_primes_bin`], `--format bin` or
+/// [`StreamFormat::Bin`].
+///
+/// The iterator ends at the end of the stream. It also ends at a read error, or at a last
+/// record shorter than 8 bytes, such as one cut short by a writer that was killed.
+pub fn read_primes_bin(reader: impl Read) -> impl Iterator<Item = u64> {
+    let mut reader = BufReader::new(reader);
+    std::iter::from_fn(move || {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes).ok()?;
+        Some(u64::from_le_bytes(bytes))
+    })
+}
@@ -0,0 +1,53 @@
+use std::{fs::{File, OpenOptions}, io::{self, BufWriter, Write}, path::Path, sync::Mutex, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::GpuStats;
+
+/// Columns of the telemetry CSV. Readings a card doesn't report are left empty.
+pub const HEADER: &str = "timestamp,gpu,name,temperature_c,utilization_percent,power_w,graphics_clock_mhz,memory_clock_mhz,remaining_candidates";
+
+/// CSV file the search monitor appends a row to for every GPU reading, one each monitor
+/// interval per device. Rows are buffered, and flushed when a search ends and when the log
+/// is dropped.
+pub struct TelemetryLog {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl TelemetryLog {
+    /// Opens `path` for appending, writing the header if the file is new or empty.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut file = BufWriter::new(file);
+        if empty {
+            writeln!(file, "{}", HEADER)?;
+        }
+        Ok(TelemetryLog { file: Mutex::new(file) })
+    }
+
+    /// Appends a row for device `gpu`'s reading, timestamped in seconds since the Unix epoch,
+    /// with the candidates `remaining` in its slice.
+    pub fn record(&self, gpu: usize, name: &str, stats: &GpuStats, remaining: u64) -> io::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let optional = |value: Option<u32>| value.map_or(String::new(), |value| value.to_string());
+        let power = stats.power_usage.map_or(String::new(), |power| format!("{:.3}", power as f64 / 1000.0));
+        writeln!(
+            self.file.lock().unwrap(),
+            "{:.3},{},{},{},{},{},{},{},{}",
+            now.as_secs_f64(), gpu, quote(name), stats.temperature, optional(stats.utilization), power,
+            optional(stats.graphics_clock), optional(stats.memory_clock), remaining,
+        )
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+// A device name with a comma or quote in it is quoted, with its quotes doubled
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
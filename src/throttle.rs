@@ -0,0 +1,180 @@
+use std::{collections::VecDeque, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+/// Pauses a device's kernels while it runs hotter than `max_temp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermalLimit {
+    /// Temperature in °C above which the device is paused
+    pub max_temp: u32,
+    /// A paused device resumes once it has cooled below `max_temp - hysteresis`
+    pub hysteresis: u32,
+}
+
+impl ThermalLimit {
+    pub const DEFAULT_HYSTERESIS: u32 = 5;
+
+    pub fn new(max_temp: u32) -> Self {
+        ThermalLimit { max_temp, hysteresis: Self::DEFAULT_HYSTERESIS }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: u32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Whether a device at `temperature` should be paused, given whether it is paused now.
+    pub fn should_pause(&self, paused: bool, temperature: u32) -> bool {
+        if paused {
+            temperature >= self.max_temp.saturating_sub(self.hysteresis)
+        } else {
+            temperature > self.max_temp
+        }
+    }
+}
+
+/// Caps the combined power draw of every device in a search by pausing some of them.
+///
+/// While the devices together draw more than `max_watts`, one more is paused at a time, in
+/// turn, each time the readings have had `settle` to reflect the last change. Once the draw
+/// has fallen below `max_watts - hysteresis`, the device paused longest resumes, one at a
+/// time in the same way. At least one device keeps running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerBudget {
+    pub max_watts: u32,
+    pub hysteresis: u32,
+    /// How long after pausing or resuming a device before the next change
+    pub settle: Duration,
+}
+
+/// What a [`PowerBudget`] does on a reading of the combined draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerStep {
+    Hold,
+    PauseOne,
+    ResumeOne,
+}
+
+impl PowerBudget {
+    pub const DEFAULT_HYSTERESIS: u32 = 25;
+    pub const DEFAULT_SETTLE: Duration = Duration::from_secs(3);
+
+    pub fn new(max_watts: u32) -> Self {
+        PowerBudget { max_watts, hysteresis: Self::DEFAULT_HYSTERESIS, settle: Self::DEFAULT_SETTLE }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: u32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn with_settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    /// The next change for `watts` drawn in all, with `running` devices searching and
+    /// `paused` held back, once the readings have settled.
+    pub fn step(&self, watts: u32, running: usize, paused: usize) -> PowerStep {
+        if paused > 0 && (running == 0 || watts < self.max_watts.saturating_sub(self.hysteresis)) {
+            PowerStep::ResumeOne
+        } else if running > 1 && watts > self.max_watts {
+            PowerStep::PauseOne
+        } else {
+            PowerStep::Hold
+        }
+    }
+}
+
+// Applies a power budget across the monitor threads of one search. Each thread records its
+// device's draw on every poll and is told whether the device should be paused, so a decision
+// made on one device's reading reaches another on that device's next poll.
+pub(crate) struct PowerGovernor {
+    budget: PowerBudget,
+    state: Mutex<GovernorState>,
+}
+
+struct GovernorState {
+    // The last draw in mW of each device still searching
+    draw: Vec<Option<u32>>,
+    searching: Vec<bool>,
+    // Paused devices, longest paused first
+    paused: VecDeque<usize>,
+    // The device to consider pausing first next time
+    next: usize,
+    last_change: Option<Instant>,
+}
+
+impl PowerGovernor {
+    pub(crate) fn new(budget: PowerBudget, devices: usize) -> Self {
+        let state = GovernorState {
+            draw: vec![None; devices],
+            searching: vec![false; devices],
+            paused: VecDeque::new(),
+            next: 0,
+            last_change: None,
+        };
+        PowerGovernor { budget, state: Mutex::new(state) }
+    }
+
+    fn record(&self, device: usize, power: Option<u32>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.draw[device] = power;
+        if state.last_change.is_none_or(|last| last.elapsed() >= self.budget.settle) {
+            self.adjust(&mut state);
+        }
+        state.paused.contains(&device)
+    }
+
+    // A device's place in the budget, given up when its monitor thread returns
+    pub(crate) fn seat(governor: &Arc<Self>, device: usize) -> PowerSeat {
+        governor.state.lock().unwrap().searching[device] = true;
+        PowerSeat { governor: Arc::clone(governor), device }
+    }
+
+    fn leave(&self, device: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.searching[device] = false;
+        state.draw[device] = None;
+        state.paused.retain(|&paused| paused != device);
+    }
+
+    fn adjust(&self, state: &mut GovernorState) {
+        let watts = (state.draw.iter().flatten().map(|&mw| mw as u64).sum::<u64>() / 1000) as u32;
+        let devices = state.searching.len();
+        let running = (0..devices).filter(|&i| state.searching[i] && !state.paused.contains(&i)).count();
+        match self.budget.step(watts, running, state.paused.len()) {
+            PowerStep::Hold => return,
+            PowerStep::PauseOne => {
+                let Some(device) = (0..devices).map(|k| (state.next + k) % devices).find(|&i| state.searching[i] && !state.paused.contains(&i)) else {
+                    return;
+                };
+                warn!("GPU {} paused: {} W drawn in all, over the {} W power budget", device, watts, self.budget.max_watts);
+                state.paused.push_back(device);
+                state.next = (device + 1) % devices;
+            }
+            PowerStep::ResumeOne => {
+                let device = state.paused.pop_front().unwrap();
+                info!("GPU {} resumed: {} W drawn in all against the {} W power budget", device, watts, self.budget.max_watts);
+            }
+        }
+        state.last_change = Some(Instant::now());
+    }
+}
+
+pub(crate) struct PowerSeat {
+    governor: Arc<PowerGovernor>,
+    device: usize,
+}
+
+impl PowerSeat {
+    // Records the device's draw in mW, returning whether it should be paused
+    pub(crate) fn record(&self, power: Option<u32>) -> bool {
+        self.governor.record(self.device, power)
+    }
+}
+
+impl Drop for PowerSeat {
+    // However the monitor thread returns, the device stops counting towards the budget
+    fn drop(&mut self) {
+        self.governor.leave(self.device);
+    }
+}
@@ -0,0 +1,210 @@
+// --tui: a live dashboard of every device, drawn from the same readings the search reports
+use opencl_primes::{Backend, GpuStats, PrimeError, eta::format_time_left};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Gauge, Paragraph},
+};
+use std::{
+    io::{self, Write},
+    ops::Range,
+    sync::{Mutex, atomic::{AtomicBool, Ordering}},
+    thread,
+    time::{Duration, Instant},
+};
+
+// How often the dashboard redraws, which is also how long a key press can wait
+const TICK: Duration = Duration::from_millis(250);
+// Throughput is averaged over this long so it doesn't jitter between redraws
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+const LOG_LINES: u16 = 6;
+
+// Log records written while the dashboard is up, which would otherwise draw over it
+static CAPTURED_LOG: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+// Keeps a log record for the dashboard's log panel, if the dashboard is up
+pub(crate) fn capture_log(record: &[u8]) -> bool {
+    let mut captured = CAPTURED_LOG.lock().unwrap();
+    let Some(lines) = captured.as_mut() else {
+        return false;
+    };
+    lines.extend(strip_ansi(&String::from_utf8_lossy(record)).lines().map(str::to_string));
+    true
+}
+
+// Shows the dashboard until `search` returns. q or Ctrl-C cancels the search, which raw mode
+// delivers as a key press rather than a signal.
+pub(crate) fn show_while<T>(backend: &dyn Backend, range: &Range<u64>, search: impl FnOnce() -> T) -> Result<T, PrimeError> {
+    let finished = AtomicBool::new(false);
+    let mut terminal = ratatui::try_init()?;
+    *CAPTURED_LOG.lock().unwrap() = Some(vec![]);
+    let (result, drawn) = thread::scope(|scope| {
+        let dashboard = scope.spawn(|| {
+            let drawn = Dashboard::new(backend, range.clone()).run(&mut terminal, &finished);
+            ratatui::restore();
+            drawn
+        });
+        let result = search();
+        finished.store(true, Ordering::Relaxed);
+        (result, dashboard.join().expect("the dashboard thread panicked"))
+    });
+
+    // Replay what was logged now that it can't draw over anything
+    let captured = CAPTURED_LOG.lock().unwrap().take().unwrap_or_default();
+    let mut stderr = io::stderr().lock();
+    for line in captured {
+        writeln!(stderr, "{}", line)?;
+    }
+    drawn?;
+    Ok(result)
+}
+
+struct Dashboard<'a> {
+    backend: &'a dyn Backend,
+    range: Range<u64>,
+    started: Instant,
+    stopping: bool,
+    // Per device: when the current rate window opened, the count tested then, and the rate
+    // measured over the last full window
+    rates: Vec<(Instant, u64, f64)>,
+}
+
+impl<'a> Dashboard<'a> {
+    fn new(backend: &'a dyn Backend, range: Range<u64>) -> Self {
+        let started = Instant::now();
+        let rates = vec![(started, 0, 0.0); backend.devices().len()];
+        Dashboard { backend, range, started, stopping: false, rates }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal, finished: &AtomicBool) -> io::Result<()> {
+        while !finished.load(Ordering::Relaxed) {
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(TICK)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c) && !self.stopping {
+                    self.stopping = true;
+                    warn!("Interrupted, stopping search...");
+                    if let Err(e) = self.backend.cancel_handle().cancel() {
+                        error!("Failed to stop kernels: {}", e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let devices = self.backend.devices();
+        let report = self.backend.report();
+        let progress = self.backend.progress();
+        let stats = self.backend.gpu_stats();
+        let eta = self.backend.eta();
+        let tested: Vec<u64> = report.devices.iter().map(|device| device.tested).collect();
+        self.update_rates(&tested);
+
+        let [overall, panels, log, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(LOG_LINES + 2),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let len = self.range.end.saturating_sub(self.range.start).max(1);
+        let done = tested.iter().sum::<u64>().min(len);
+        let elapsed = self.started.elapsed().as_secs();
+        let rate: f64 = self.rates.iter().map(|&(_, _, rate)| rate).sum();
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered().title(format!(" [{}, {}) ", self.range.start, self.range.end)))
+                .gauge_style(Style::default().fg(Color::Green))
+                .ratio(done as f64 / len as f64)
+                .label(format!(
+                    "{:.2}%  {}  {:02}:{:02}:{:02}  ETA {}",
+                    100.0 * done as f64 / len as f64, per_sec(rate), elapsed / 3600, elapsed / 60 % 60, elapsed % 60, format_time_left(eta.total),
+                )),
+            overall,
+        );
+
+        let rows = Layout::vertical(vec![Constraint::Length(4); devices.len()]).split(panels);
+        for (i, device) in devices.iter().enumerate() {
+            let Some(&area) = rows.get(i) else {
+                break;
+            };
+            let reading = DeviceReading { stats: stats.get(i).copied().flatten(), reached: progress.get(i).copied().unwrap_or(0), eta: eta.devices.get(i).copied().flatten() };
+            self.draw_device(frame, area, i, &device.to_string(), reading);
+        }
+
+        let lines: Vec<Line> = match &*CAPTURED_LOG.lock().unwrap() {
+            Some(captured) => captured[captured.len().saturating_sub(LOG_LINES as usize)..].iter().map(|line| Line::raw(line.clone())).collect(),
+            None => vec![],
+        };
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Log ")), log);
+
+        let help_text = if self.stopping { "Stopping..." } else { "q: stop the search" };
+        frame.render_widget(Paragraph::new(help_text), help);
+    }
+
+    fn draw_device(&self, frame: &mut Frame, area: Rect, i: usize, name: &str, reading: DeviceReading) {
+        let sensors = match reading.stats {
+            Some(stats) => format!(
+                "Temperature {}°C  Utilization {}  Power {}",
+                stats.temperature,
+                stats.utilization.map_or("-".to_string(), |percent| format!("{}%", percent)),
+                stats.power_usage.map_or("-".to_string(), |mw| format!("{:.1} W", mw as f64 / 1000.0)),
+            ),
+            None => "No monitoring readings".to_string(),
+        };
+        let search = format!("Reached {}  {}  ETA {}", reading.reached, per_sec(self.rates[i].2), format_time_left(reading.eta));
+        frame.render_widget(
+            Paragraph::new(vec![Line::raw(sensors), Line::raw(search)]).block(Block::bordered().title(format!(" GPU {}: {} ", i, name))),
+            area,
+        );
+    }
+
+    fn update_rates(&mut self, tested: &[u64]) {
+        let now = Instant::now();
+        for (rate, &tested) in self.rates.iter_mut().zip(tested) {
+            let elapsed = now.duration_since(rate.0);
+            if elapsed >= RATE_WINDOW {
+                *rate = (now, tested, tested.saturating_sub(rate.1) as f64 / elapsed.as_secs_f64());
+            }
+        }
+    }
+}
+
+// What a device panel shows besides the device's own throughput
+struct DeviceReading {
+    stats: Option<GpuStats>,
+    reached: u64,
+    eta: Option<Duration>,
+}
+
+fn per_sec(rate: f64) -> String {
+    match rate {
+        r if r >= 1e9 => format!("{:.2}G/s", r / 1e9),
+        r if r >= 1e6 => format!("{:.2}M/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.2}k/s", r / 1e3),
+        r => format!("{:.0}/s", r),
+    }
+}
+
+// The log writer colors records for a terminal, which the panel would show as raw escapes
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the CSI sequence
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
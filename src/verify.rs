@@ -0,0 +1,107 @@
+use num_bigint::BigUint;
+use num_traits::One;
+use std::ops::Range;
+
+use crate::primality;
+
+// No small deterministic set is known past 3.3 * 10^24, so wide values get the first 20
+// primes, more than the kernel uses, as a strong probable-prime check
+const WITNESSES_U128: [u32; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+
+// Trial division goes this far before Pollard's rho takes over
+const TRIAL_LIMIT: u64 = 1 << 16;
+
+/// Miller-Rabin test on the CPU, used to double-check primes reported by the kernels. The
+/// same test the kernels run, from [`primality`](crate::primality).
+pub fn is_prime(n: u64) -> bool {
+    primality::is_prime_u64(n)
+}
+
+/// Like [`is_prime`] for values beyond `u64`; deterministic below 2^64 and a strong
+/// probable-prime test above it.
+pub fn is_prime_u128(n: u128) -> bool {
+    if let Ok(n) = u64::try_from(n) {
+        return is_prime(n);
+    }
+    for &p in &WITNESSES_U128 {
+        if n.is_multiple_of(p as u128) {
+            return false;
+        }
+    }
+
+    let n = BigUint::from(n);
+    let n_minus_one = &n - BigUint::one();
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    'witness: for &a in &WITNESSES_U128 {
+        let mut x = BigUint::from(a).modpow(&d, &n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % &n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// A factor of `n` other than 1 and `n`, or `None` if there is none, as for primes, 0 and 1.
+/// Factors below 2^16 are found by trial division, so the smallest is returned when there is
+/// one that small; the rest of `u64` is factored with Pollard's rho. Wider values are only
+/// trial divided, so `None` doesn't prove them prime.
+pub fn find_factor(n: u128) -> Option<u128> {
+    if n < 4 {
+        return None;
+    }
+    if let Some(d) = (2..TRIAL_LIMIT as u128).take_while(|d| d * d <= n).find(|&d| n.is_multiple_of(d)) {
+        return Some(d);
+    }
+    match u64::try_from(n) {
+        Ok(n) if !is_prime(n) => Some(pollard_rho(n) as u128),
+        _ => None,
+    }
+}
+
+// A nontrivial factor of a composite n with no factor below TRIAL_LIMIT
+fn pollard_rho(n: u64) -> u64 {
+    let mul_mod = |a: u64, b: u64| ((a as u128 * b as u128) % n as u128) as u64;
+    for c in 1.. {
+        let step = |x: u64| (mul_mod(x, x) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = step(x);
+            y = step(step(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        // A cycle without a factor, so try another polynomial
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// Why a value a kernel reported as prime failed verification, given the value and for twin
+// primes its partner: a value outside the range the device was searching was corrupted on its
+// way back rather than tested, one inside it with a factor is a composite the kernel passed
+pub(crate) fn diagnose(values: &[u128], searched: Range<u128>) -> String {
+    if !searched.contains(&values[0]) {
+        return format!("it's outside [{}, {}), the range the device was searching, so the result was corrupted on the way back", searched.start, searched.end);
+    }
+    let value = values.iter().copied().find(|&value| !is_prime_u128(value)).unwrap_or(values[0]);
+    match find_factor(value) {
+        Some(factor) => format!("{} is divisible by {}, so the kernel passed a composite", value, factor),
+        None => format!("{} is not prime, though no factor was found", value),
+    }
+}
@@ -0,0 +1,71 @@
+use std::{sync::{Mutex, mpsc::{self, Receiver, Sender}}, thread::JoinHandle, time::{Duration, Instant}};
+
+// How often the joining thread looks for stalled monitor threads
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// When each monitor thread last started a poll. One that hasn't for longer than the timeout
+// is stuck in a call into the driver, and the search stops waiting for it.
+pub(crate) struct Watchdog {
+    beats: Mutex<Vec<Instant>>,
+    timeout: Duration,
+}
+
+impl Watchdog {
+    pub(crate) fn new(threads: usize, timeout: Duration) -> Self {
+        Watchdog { beats: Mutex::new(vec![Instant::now(); threads]), timeout }
+    }
+
+    pub(crate) fn beat(&self, i: usize) {
+        self.beats.lock().unwrap()[i] = Instant::now();
+    }
+
+    fn stalled(&self, i: usize) -> bool {
+        self.beats.lock().unwrap()[i].elapsed() > self.timeout
+    }
+}
+
+// Tells the joining thread a monitor thread is done, when it returns or panics
+pub(crate) struct Finished(pub(crate) usize, pub(crate) Sender<usize>);
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        let _ = self.1.send(self.0);
+    }
+}
+
+pub(crate) fn finished_channel() -> (Sender<usize>, Receiver<usize>) {
+    mpsc::channel()
+}
+
+// Joins the monitor threads as they finish. Those the watchdog finds stalled are left
+// running and come back as None, with their indices.
+pub(crate) fn join_all<T>(threads: Vec<JoinHandle<T>>, finished: Receiver<usize>, watchdog: &Watchdog) -> (Vec<Option<T>>, Vec<usize>) {
+    let mut threads: Vec<Option<JoinHandle<T>>> = threads.into_iter().map(Some).collect();
+    let mut results: Vec<Option<T>> = threads.iter().map(|_| None).collect();
+    let mut abandoned = vec![];
+    let mut join = |i: usize, threads: &mut Vec<Option<JoinHandle<T>>>| {
+        if let Some(thread) = threads[i].take() {
+            results[i] = Some(thread.join().unwrap());
+        }
+    };
+    while threads.iter().any(Option::is_some) {
+        // A thread that has reported only has to return
+        if let Ok(i) = finished.recv_timeout(CHECK_INTERVAL) {
+            join(i, &mut threads);
+            while let Ok(i) = finished.try_recv() {
+                join(i, &mut threads);
+            }
+        }
+        for i in 0..threads.len() {
+            match &threads[i] {
+                Some(thread) if thread.is_finished() => join(i, &mut threads),
+                Some(_) if watchdog.stalled(i) => {
+                    threads[i] = None;
+                    abandoned.push(i);
+                }
+                _ => {}
+            }
+        }
+    }
+    (results, abandoned)
+}
@@ -0,0 +1,191 @@
+extern crate opencl_primes;
+
+mod common;
+
+use opencl_primes::{Algorithm, Direction, MAX_MERSENNE_EXPONENT, PartitionStrategy, PrimeError, PrimeSearcher, StreamFormat, verify::is_prime};
+use std::ops::Range;
+
+fn searcher(range: Range<u64>) -> Option<PrimeSearcher> {
+    common::skip_without_devices(PrimeSearcher::new(range)).map(|searcher| searcher.with_monitoring(false))
+}
+
+#[test]
+fn trial_division_and_miller_rabin_agree() {
+    let range = 1_000_000_000..1_000_003_000;
+    let Some(searcher) = searcher(range) else { return };
+
+    let trial = searcher.find_all().unwrap();
+    let miller_rabin = searcher.with_algorithm(Algorithm::MillerRabin).find_all().unwrap();
+
+    assert!(!trial.is_empty());
+    assert_eq!(trial, miller_rabin);
+}
+
+#[test]
+fn segmented_sieve_matches_miller_rabin_across_segments() {
+    let range = 1_000_000_000..1_000_003_000;
+    let Some(searcher) = searcher(range) else { return };
+
+    let searcher = searcher.with_algorithm(Algorithm::MillerRabin);
+    let miller_rabin = searcher.find_all().unwrap();
+    // Several segments per device, none aligned to the slice boundaries
+    let sieve = searcher.with_algorithm(Algorithm::SegmentedSieve).with_segment_size(1000).find_all().unwrap();
+
+    assert_eq!(sieve, miller_rabin);
+}
+
+#[test]
+fn twin_straddling_a_slice_boundary_is_found() {
+    // The only twins in range; with two devices the boundary falls between them at 1_000_000_008
+    let Some(searcher) = searcher(1_000_000_000..1_000_000_016) else { return };
+    let twin = searcher.with_algorithm(Algorithm::MillerRabin).find_twin().unwrap();
+    assert_eq!(twin, Some((1_000_000_007, 1_000_000_009)));
+}
+
+#[test]
+fn find_first_returns_the_smallest_prime_every_time() {
+    let Some(searcher) = searcher(1_000_000_000..1_000_003_000) else { return };
+    let searcher = searcher.with_algorithm(Algorithm::MillerRabin);
+    for _ in 0..10 {
+        assert_eq!(searcher.find_first().unwrap(), Some(1_000_000_007));
+    }
+}
+
+#[test]
+fn count_matches_cpu() {
+    let range = 1_000_000..1_100_000;
+    let Some(mut searcher) = searcher(range.clone()) else { return };
+    let expected = range.filter(|&n| is_prime(n)).count() as u64;
+
+    for algorithm in [Algorithm::TrialDivision, Algorithm::MillerRabin, Algorithm::SegmentedSieve] {
+        searcher = searcher.with_algorithm(algorithm);
+        assert_eq!(searcher.count().unwrap(), expected, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn lucas_lehmer_finds_small_mersenne_primes() {
+    let Some(searcher) = searcher(0..0) else { return };
+    let exponents: Vec<u32> = (2..=MAX_MERSENNE_EXPONENT).collect();
+    let known = [2, 3, 5, 7, 13, 17, 19, 31, 61, 89, 107, 127];
+
+    let tested = searcher.test_mersenne(&exponents).unwrap();
+    assert_eq!(tested.len(), exponents.len());
+    for (p, prime) in tested {
+        assert_eq!(prime, known.contains(&p), "2^{} - 1", p);
+    }
+    assert!(searcher.test_mersenne(&[MAX_MERSENNE_EXPONENT + 1]).is_err());
+}
+
+#[test]
+fn dynamic_partition_matches_cpu() {
+    let range = 1_000_000..1_200_000;
+    let Some(searcher) = searcher(range.clone()) else { return };
+    let searcher = searcher
+        .with_algorithm(Algorithm::MillerRabin)
+        .with_partition_strategy(PartitionStrategy::Dynamic { chunk_size: 7_919 });
+    let expected: Vec<u64> = range.filter(|&n| is_prime(n)).collect();
+
+    assert_eq!(searcher.find_all().unwrap(), expected);
+    assert_eq!(searcher.count().unwrap(), expected.len() as u64);
+    assert_eq!(searcher.find_first().unwrap(), expected.first().copied());
+    let twin = expected.windows(2).find(|pair| pair[1] == pair[0] + 2).map(|pair| (pair[0], pair[1]));
+    assert_eq!(searcher.find_twin().unwrap(), twin);
+}
+
+#[test]
+fn find_gap_returns_the_first_large_gap() {
+    let Some(searcher) = searcher(0..100_000) else { return };
+    let searcher = searcher.with_algorithm(Algorithm::MillerRabin);
+    // 1327 to 1361 is the first gap of 34, and 31397 to 31469 the first of 72
+    assert_eq!(searcher.find_gap(34).unwrap(), Some((1327, 1361)));
+    assert_eq!(searcher.find_gap(72).unwrap(), Some((31397, 31469)));
+    assert_eq!(searcher.find_gap(1000).unwrap(), None);
+}
+
+#[test]
+fn streaming_writes_every_prime_in_order() {
+    let Some(searcher) = searcher(1_000_000..1_100_000) else { return };
+    let searcher = searcher.with_algorithm(Algorithm::MillerRabin);
+    let expected = searcher.find_all().unwrap();
+
+    let mut lines = vec![];
+    assert_eq!(searcher.find_all_streaming(&mut lines, StreamFormat::Lines).unwrap(), expected.len() as u64);
+    let streamed: Vec<u64> = String::from_utf8(lines).unwrap().lines().map(|line| line.parse().unwrap()).collect();
+    assert_eq!(streamed, expected);
+
+    let mut json = vec![];
+    searcher.find_all_streaming(&mut json, StreamFormat::Json).unwrap();
+    assert_eq!(String::from_utf8(json).unwrap().lines().next(), Some(format!("{{\"prime\":{}}}", expected[0]).as_str()));
+}
+
+#[test]
+fn trial_division_rejects_large_squares() {
+    // The square of the largest prime below 2^32, where i * i would wrap before reaching it.
+    // Two numbers rather than one, which the host would test without the kernels
+    let p: u64 = 4_294_967_291;
+    let Some(searcher) = searcher(p * p - 1..p * p + 1) else { return };
+    let searcher = searcher.with_algorithm(Algorithm::TrialDivision).with_verification(false);
+    assert_eq!(searcher.find_first().unwrap(), None);
+}
+
+#[test]
+fn capacities_the_kernel_can_not_index_are_refused() {
+    for partition_strategy in [PartitionStrategy::Even, PartitionStrategy::Dynamic { chunk_size: 100 }] {
+        let Some(searcher) = searcher(1_000..2_000) else { return };
+        let searcher = searcher.with_partition_strategy(partition_strategy);
+        assert!(matches!(searcher.find_all_with_capacity(u32::MAX as usize + 1), Err(PrimeError::InvalidRange(_))));
+        assert_eq!(searcher.find_all_with_capacity(200).unwrap().len(), 135);
+    }
+}
+
+#[test]
+fn find_n_returns_the_first_primes() {
+    let range = 1_000_000_000..1_002_000_000;
+    let Some(searcher) = searcher(range.clone()) else { return };
+    let searcher = searcher.with_algorithm(Algorithm::MillerRabin);
+    let expected: Vec<u64> = range.filter(|&n| is_prime(n)).collect();
+
+    assert_eq!(searcher.find_n(0).unwrap(), Vec::<u64>::new());
+    assert_eq!(searcher.find_n(10).unwrap(), expected[..10]);
+    assert_eq!(searcher.find_n(20_000).unwrap(), expected[..20_000]);
+    // Fewer primes than asked for
+    assert_eq!(searcher.find_n(expected.len() + 1).unwrap(), expected);
+}
+
+#[test]
+fn test_number_matches_cpu_for_each_algorithm() {
+    let Some(mut searcher) = searcher(0..0) else { return };
+    let small = [0, 1, 2, 3, 4, 47, 49, 53, 561, 1_000_000_007];
+    // Trial division takes one thread far too long on these
+    let large = [4_294_967_291 * 4_294_967_291, u64::MAX - 58, u64::MAX];
+    for algorithm in [Algorithm::TrialDivision, Algorithm::MillerRabin, Algorithm::Wide128, Algorithm::Bpsw] {
+        searcher = searcher.with_algorithm(algorithm);
+        let numbers = if algorithm == Algorithm::TrialDivision { &small[..] } else { &[&small[..], &large[..]].concat() };
+        for &n in numbers {
+            assert_eq!(searcher.test_number(n).unwrap(), is_prime(n), "{} with {:?}", n, algorithm);
+        }
+    }
+}
+
+#[test]
+fn searching_down_finds_the_largest_prime() {
+    let range = 1_000_000_000..1_000_003_000;
+    let Some(searcher) = searcher(range.clone()) else { return };
+    let mut searcher = searcher.with_direction(Direction::Down);
+
+    let largest = range.rev().find(|&n| is_prime(n));
+    for algorithm in [Algorithm::TrialDivision, Algorithm::MillerRabin] {
+        searcher = searcher.with_algorithm(algorithm);
+        assert_eq!(searcher.find_first().unwrap(), largest, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn searching_down_handles_ranges_without_primes() {
+    // 24..29 holds no primes, and 2..3 holds only its start
+    let Some(empty) = searcher(24..29) else { return };
+    assert_eq!(empty.with_direction(Direction::Down).find_first().unwrap(), None);
+    let Some(start_only) = searcher(2..3) else { return };
+    assert_eq!(start_only.with_direction(Direction::Down).find_first().unwrap(), Some(2));
+}
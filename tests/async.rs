@@ -0,0 +1,34 @@
+extern crate futures;
+extern crate opencl_primes;
+extern crate tokio;
+
+mod common;
+
+use opencl_primes::{Algorithm, PrimeSearcher};
+use std::ops::Range;
+
+fn searcher(range: Range<u64>) -> Option<PrimeSearcher> {
+    common::skip_without_devices(PrimeSearcher::new(range)).map(|searcher| searcher.with_monitoring(false).with_algorithm(Algorithm::MillerRabin))
+}
+
+// Lets the future be driven from tasks that move between worker threads
+fn assert_send<T: Send>(future: T) -> T {
+    future
+}
+
+#[test]
+fn find_first_async_matches_find_first_with_block_on() {
+    let Some(searcher) = searcher(1_000_000_000..1_000_100_000) else { return };
+    let prime = futures::executor::block_on(assert_send(searcher.find_first_async())).unwrap();
+    assert_eq!(prime, Some(1_000_000_007));
+    assert_eq!(prime, searcher.find_first().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn find_first_async_runs_on_tokio() {
+    let Some(empty) = searcher(1_000_000_000..1_000_000_007) else { return };
+    assert_eq!(empty.find_first_async().await.unwrap(), None);
+
+    let small = searcher(4..30).unwrap();
+    assert_eq!(small.find_first_async().await.unwrap(), Some(5));
+}
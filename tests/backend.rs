@@ -0,0 +1,134 @@
+extern crate opencl_primes;
+
+mod common;
+
+use opencl_primes::{Backend, CancelHandle, CpuSearcher, DeviceInfo, DeviceReport, PartitionStrategy, PrimeSearcher, Result, SearchReport};
+use opencl_primes::primality::is_prime_u64;
+use std::{ops::Range, time::Duration};
+
+// Answers every search from a fixed, sorted list of primes
+struct MockBackend {
+    primes: Vec<u64>,
+    cancel: CancelHandle,
+}
+
+impl MockBackend {
+    fn new(primes: &[u64]) -> Self {
+        MockBackend { primes: primes.to_vec(), cancel: CancelHandle::without_kernels() }
+    }
+
+    fn within(&self, range: &Range<u64>) -> impl Iterator<Item = u64> + '_ {
+        let range = range.clone();
+        self.primes.iter().copied().filter(move |p| range.contains(p))
+    }
+}
+
+impl Backend for MockBackend {
+    fn find_first(&self, range: Range<u64>) -> Result<Option<u64>> {
+        Ok(self.within(&range).next())
+    }
+
+    fn find_twin(&self, range: Range<u64>) -> Result<Option<(u64, u64)>> {
+        let primes: Vec<u64> = self.within(&range).collect();
+        Ok(primes.windows(2).find(|pair| pair[1] == pair[0] + 2).map(|pair| (pair[0], pair[1])))
+    }
+
+    fn find_all(&self, range: Range<u64>) -> Result<Vec<u64>> {
+        Ok(self.within(&range).collect())
+    }
+
+    fn count(&self, range: Range<u64>) -> Result<u64> {
+        Ok(self.within(&range).count() as u64)
+    }
+
+    fn devices(&self) -> Vec<DeviceInfo> {
+        vec![DeviceInfo { index: 0, platform: "Mock".into(), name: "Mock".into(), compute_units: 1, ..DeviceInfo::default() }]
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    fn report(&self) -> SearchReport {
+        let device = DeviceReport { name: "Mock".into(), tested: 0, remaining: 0, wall_time: Duration::ZERO, peak_temperature: None, found_prime: false, kernel_time: None, kernel_launches: 0 };
+        SearchReport { devices: vec![device], elapsed: Duration::ZERO, found_by: None }
+    }
+
+    fn progress(&self) -> Vec<u64> {
+        vec![0]
+    }
+}
+
+// The CPU, and the GPUs under each partition strategy when there are any
+fn backends() -> Vec<(String, Box<dyn Backend>)> {
+    let mut backends: Vec<(String, Box<dyn Backend>)> = vec![("cpu".into(), Box::new(CpuSearcher::new(0..0).unwrap()))];
+    for strategy in [PartitionStrategy::Even, PartitionStrategy::Weighted, PartitionStrategy::Dynamic { chunk_size: 10_000 }] {
+        let Some(searcher) = common::skip_without_devices(PrimeSearcher::new(0..0)) else { break };
+        backends.push((format!("{:?}", strategy), Box::new(searcher.with_monitoring(false).with_partition_strategy(strategy))));
+    }
+    backends
+}
+
+#[test]
+fn count_and_find_all_cover_every_device_past_the_first_prime() {
+    // Thousands of primes, in every device's slice, so each device finds some long before
+    // the others finish
+    let range = 1_000_000_000..1_000_200_000;
+    let expected: Vec<u64> = range.clone().filter(|&n| is_prime_u64(n)).collect();
+    for (name, backend) in backends() {
+        assert_eq!(backend.count(range.clone()).unwrap(), expected.len() as u64, "{}", name);
+        assert_eq!(backend.find_all(range.clone()).unwrap(), expected, "{}", name);
+        assert_eq!(backend.find_first(range.clone()).unwrap(), expected.first().copied(), "{}", name);
+    }
+}
+
+#[test]
+fn cpu_backend_agrees_with_mock() {
+    let primes = [101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199];
+    let backends: Vec<Box<dyn Backend>> = vec![Box::new(MockBackend::new(&primes)), Box::new(CpuSearcher::new(0..0).unwrap())];
+
+    for range in [100..200, 114..127, 128..131, 150..150] {
+        let answers: Vec<_> = backends.iter()
+            .map(|backend| {
+                (backend.find_first(range.clone()).unwrap(), backend.find_twin(range.clone()).unwrap(),
+                    backend.find_all(range.clone()).unwrap(), backend.count(range.clone()).unwrap())
+            })
+            .collect();
+        assert_eq!(answers[0], answers[1], "{:?}", range);
+    }
+    assert!(backends.iter().all(|backend| backend.gpu_stats().iter().all(Option::is_none)));
+}
+
+#[test]
+fn find_next_collects_primes_across_windows_and_stops_at_the_end() {
+    // Gaps much longer than the first window
+    let primes = [101, 103, 10_007, 1_000_003, 1_000_033];
+    let backend = MockBackend::new(&primes);
+    assert_eq!(backend.find_next(101, 2, u64::MAX).unwrap(), [103, 10_007]);
+    assert_eq!(backend.find_next(101, 10, u64::MAX).unwrap(), [103, 10_007, 1_000_003, 1_000_033]);
+    assert_eq!(backend.find_next(101, 10, 1_000_003).unwrap(), [103, 10_007]);
+    assert_eq!(backend.find_next(1_000_033, 1, u64::MAX).unwrap(), Vec::<u64>::new());
+
+    let cpu = CpuSearcher::new(0..0).unwrap();
+    assert_eq!(cpu.find_next(100, 5, 200).unwrap(), [101, 103, 107, 109, 113]);
+}
+
+#[test]
+fn merged_reports_add_up_searches() {
+    let device = |tested, secs, peak_temperature, found_prime| DeviceReport {
+        name: "Mock".into(), tested, remaining: 100 - tested, wall_time: Duration::from_secs(secs), peak_temperature, found_prime,
+        kernel_time: (secs > 1).then(|| Duration::from_secs(secs)), kernel_launches: if secs > 1 { 3 } else { 0 },
+    };
+    let found_by = DeviceInfo { name: "Mock".into(), ..DeviceInfo::default() };
+    let mut total = SearchReport { devices: vec![device(10, 1, Some(60), false)], elapsed: Duration::from_secs(1), found_by: None };
+    total.merge(&SearchReport { devices: vec![device(5, 2, Some(55), true)], elapsed: Duration::from_secs(3), found_by: Some(found_by) });
+    total.merge(&SearchReport { devices: vec![device(1, 1, None, false)], elapsed: Duration::from_secs(1), found_by: None });
+
+    let merged = &total.devices[0];
+    assert_eq!((merged.tested, merged.wall_time, merged.peak_temperature, merged.found_prime), (16, Duration::from_secs(4), Some(60), true));
+    assert_eq!((merged.kernel_time, merged.kernel_launches), (Some(Duration::from_secs(2)), 3));
+    // What the last search left
+    assert_eq!(merged.remaining, 99);
+    assert_eq!(total.elapsed, Duration::from_secs(5));
+    assert_eq!(total.found_by.map(|device| device.name).as_deref(), Some("Mock"));
+}
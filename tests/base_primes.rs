@@ -0,0 +1,53 @@
+extern crate opencl_primes;
+
+use opencl_primes::{base_primes_up_to, verify::is_prime};
+use std::time::Instant;
+
+// Single-threaded baseline
+fn sieve(limit: u64) -> Vec<u64> {
+    let mut composite = vec![false; limit as usize + 1];
+    let mut primes = vec![];
+    for n in 2..=limit as usize {
+        if !composite[n] {
+            primes.push(n as u64);
+            for multiple in (n * n..=limit as usize).step_by(n) {
+                composite[multiple] = true;
+            }
+        }
+    }
+    primes
+}
+
+#[test]
+fn matches_single_threaded_sieve() {
+    for limit in (0..200).chain([1 << 16, (1 << 16) + 1, 3_000_017]) {
+        assert_eq!(base_primes_up_to(limit), sieve(limit), "{}", limit);
+    }
+}
+
+#[test]
+fn includes_a_prime_limit() {
+    let primes = base_primes_up_to(1_000_003);
+    assert_eq!(primes.last(), Some(&1_000_003));
+    assert!(primes.iter().all(|&p| is_prime(p)));
+}
+
+// sqrt(10^13)-sized work is too quick to time reliably, so this sieves well past it
+#[test]
+#[ignore = "timing-sensitive; run with --ignored on an idle multi-core machine"]
+fn scales_across_threads() {
+    let limit = 400_000_000;
+    let start = Instant::now();
+    let baseline = sieve(limit);
+    let single = start.elapsed();
+
+    let start = Instant::now();
+    let parallel = base_primes_up_to(limit);
+    let multi = start.elapsed();
+
+    assert_eq!(parallel, baseline);
+    println!("single-threaded {:?}, parallel {:?}", single, multi);
+    if std::thread::available_parallelism().map_or(1, |n| n.get()) > 1 {
+        assert!(multi < single, "parallel sieve took {:?}, baseline {:?}", multi, single);
+    }
+}
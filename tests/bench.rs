@@ -0,0 +1,36 @@
+extern crate opencl_primes;
+
+use opencl_primes::{BenchResult, TuneResult};
+use std::time::Duration;
+
+// A one-second run testing `rate` candidates
+fn run(threads: usize, rate: u64) -> (usize, BenchResult) {
+    (threads, BenchResult {
+        candidates: rate,
+        bytes: threads as u64 * 8,
+        elapsed: Duration::from_secs(1),
+        batches: 1,
+        read_time: Duration::ZERO,
+        baseline_candidates: 0,
+        baseline_elapsed: Duration::ZERO,
+    })
+}
+
+#[test]
+fn tuning_picks_each_devices_fastest_count() {
+    let device = TuneResult { runs: vec![run(256, 100), run(512, 300), run(1024, 200)] };
+    assert_eq!(device.best(), Some(512));
+    assert_eq!(TuneResult::default().best(), None);
+}
+
+#[test]
+fn the_shared_count_runs_smaller_devices_at_their_limit() {
+    // The second device tops out at 512 threads, and keeps that rate when asked for more
+    let large = TuneResult { runs: vec![run(256, 100), run(512, 200), run(1024, 400), run(2048, 350)] };
+    let small = TuneResult { runs: vec![run(256, 300), run(512, 250)] };
+    assert_eq!(large.best(), Some(1024));
+    assert_eq!(small.best(), Some(256));
+    assert_eq!(TuneResult::best_shared(&[large.clone(), small.clone()]), Some((1024, 650.0)));
+    assert_eq!(TuneResult::best_shared(&[small]), Some((256, 300.0)));
+    assert_eq!(TuneResult::best_shared(&[]), None);
+}
@@ -0,0 +1,21 @@
+extern crate opencl_primes;
+
+use opencl_primes::{Algorithm, Direction, PartitionStrategy, PrimeError, PrimeSearcher};
+use std::ops::Range;
+
+// The combination is checked before any device is set up, so these run without OpenCL
+#[test]
+fn builder_rejects_bad_combinations_before_setting_up_devices() {
+    assert!(matches!(PrimeSearcher::builder().build(), Err(PrimeError::InvalidRange(_))));
+    assert!(matches!(
+        PrimeSearcher::builder().range(100..200).algorithm(Algorithm::LucasLehmer).build(),
+        Err(PrimeError::Unsupported(_))
+    ));
+    assert!(matches!(PrimeSearcher::builder().range(Range { start: 200, end: 100 }).build(), Err(PrimeError::InvalidRange(_))));
+    assert!(matches!(PrimeSearcher::builder().range(100..100).build(), Err(PrimeError::InvalidRange(_))));
+    assert!(matches!(
+        PrimeSearcher::builder().range(100..200).direction(Direction::Down).partition_strategy(PartitionStrategy::Dynamic { chunk_size: 10 }).build(),
+        Err(PrimeError::Unsupported(_))
+    ));
+    assert!(matches!(PrimeSearcher::builder().range(100..200).local_size(Some(0)).build(), Err(PrimeError::Unsupported(_))));
+}
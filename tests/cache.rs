@@ -0,0 +1,36 @@
+extern crate opencl_primes;
+
+use opencl_primes::KernelCache;
+use std::{env, fs, path::PathBuf, process};
+
+// The only test that sets these variables, so the others running alongside never see them
+#[test]
+fn default_dir_follows_the_environment() {
+    env::set_var("OPENCL_PRIMES_CACHE_DIR", "/tmp/primes-cache");
+    assert_eq!(KernelCache::default_dir(), Some(PathBuf::from("/tmp/primes-cache")));
+
+    env::set_var("OPENCL_PRIMES_CACHE_DIR", "");
+    env::set_var("HOME", "/home/someone");
+    env::set_var("XDG_CACHE_HOME", "/var/cache/someone");
+    if cfg!(target_os = "linux") {
+        assert_eq!(KernelCache::default_dir(), Some(PathBuf::from("/var/cache/someone/opencl-primes")));
+        // A relative XDG_CACHE_HOME is ignored
+        env::set_var("XDG_CACHE_HOME", "cache");
+        assert_eq!(KernelCache::default_dir(), Some(PathBuf::from("/home/someone/.cache/opencl-primes")));
+    }
+    env::remove_var("OPENCL_PRIMES_CACHE_DIR");
+    env::remove_var("XDG_CACHE_HOME");
+}
+
+#[test]
+fn open_creates_the_directory_or_says_why_it_cant() {
+    let dir = env::temp_dir().join(format!("opencl-primes-cache-{}", process::id()));
+    let cache = KernelCache::open(dir.join("nested")).unwrap();
+    assert!(cache.dir().is_dir());
+
+    // Nothing can be created under a file
+    let file = dir.join("file");
+    fs::write(&file, b"").unwrap();
+    assert!(KernelCache::open(file.join("cache")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
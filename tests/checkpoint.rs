@@ -0,0 +1,89 @@
+extern crate opencl_primes;
+
+use opencl_primes::{Checkpoint, DeviceInfo, DeviceProgress, LastPrime, PrimeError};
+use std::{env, fs, process};
+
+fn device(index: usize) -> DeviceInfo {
+    DeviceInfo { index, platform: "Test".into(), name: format!("GPU {}", index), compute_units: 1, ..DeviceInfo::default() }
+}
+
+fn progress(index: usize, next: u64, end: u64) -> DeviceProgress {
+    DeviceProgress { device: device(index).to_string(), next, end }
+}
+
+#[test]
+fn checkpoint_round_trips_and_rejects_other_ranges() {
+    let path = env::temp_dir().join(format!("opencl-primes-checkpoint-{}.toml", process::id()));
+    let checkpoint = Checkpoint { start: 100, end: 200, next: 150, devices: vec![] };
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, checkpoint);
+    assert!(loaded.check_range(&(100..200)).is_ok());
+    assert!(matches!(loaded.check_range(&(100..300)), Err(PrimeError::InvalidRange(_))));
+}
+
+#[test]
+fn per_device_progress_round_trips_and_is_validated() {
+    let path = env::temp_dir().join(format!("opencl-primes-checkpoint-devices-{}.toml", process::id()));
+    let checkpoint = Checkpoint { start: 0, end: 300, next: 50, devices: vec![progress(0, 50, 100), progress(1, 180, 300)] };
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path);
+
+    let overlapping = Checkpoint { devices: vec![progress(0, 50, 100), progress(1, 90, 300)], ..checkpoint.clone() };
+    overlapping.save(&path).unwrap();
+    let rejected = Checkpoint::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.unwrap(), checkpoint);
+    assert!(matches!(rejected, Err(PrimeError::Checkpoint(_))));
+}
+
+#[test]
+fn resume_on_the_same_devices_continues_each_slice() {
+    let checkpoint = Checkpoint { start: 0, end: 300, next: 50, devices: vec![progress(0, 50, 100), progress(1, 100, 200), progress(2, 250, 300)] };
+    assert_eq!(checkpoint.resume_slices(&[device(0), device(1), device(2)]), vec![50..100, 100..200, 250..300]);
+}
+
+#[test]
+fn resume_on_fewer_devices_splits_the_unfinished_numbers() {
+    // Device 1 finished its slice and device 2 was halfway through when it was removed
+    let checkpoint = Checkpoint { start: 0, end: 300, next: 60, devices: vec![progress(0, 60, 100), progress(1, 200, 200), progress(2, 240, 300)] };
+    assert_eq!(checkpoint.unfinished(), vec![60..100, 240..300]);
+
+    // 100 numbers are left, 50 for each remaining device
+    let slices = checkpoint.resume_slices(&[device(0), device(1)]);
+    assert_eq!(slices, vec![60..250, 250..300]);
+
+    // Nothing unfinished is left out, and only the second slice starts past the first
+    let covered = |n: u64| slices.iter().any(|slice| slice.contains(&n));
+    assert!(checkpoint.unfinished().into_iter().flatten().all(covered));
+    assert!(slices[0].end <= slices[1].start);
+}
+
+#[test]
+fn resume_without_device_progress_starts_from_next() {
+    let checkpoint = Checkpoint { start: 0, end: 100, next: 40, devices: vec![] };
+    assert_eq!(checkpoint.resume_slices(&[device(0), device(1)]), vec![40..70, 70..100]);
+}
+
+#[test]
+fn last_prime_continues_just_after_it() {
+    let path = env::temp_dir().join(format!("opencl-primes-last-prime-{}.toml", process::id()));
+    let missing = LastPrime::load(&path).unwrap();
+    let last = LastPrime { prime: 101, start: 100, end: 200 };
+    last.save(&path).unwrap();
+    let loaded = LastPrime::load(&path).unwrap();
+    fs::write(&path, "prime = 300\nstart = 100\nend = 200\n").unwrap();
+    let outside = LastPrime::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(missing, None);
+    assert_eq!(loaded, Some(last.clone()));
+    assert!(matches!(outside, Err(PrimeError::Checkpoint(_))));
+    assert_eq!(last.next_range(None, None).unwrap(), 102..200);
+    assert_eq!(last.next_range(Some(102), Some(1000)).unwrap(), 102..1000);
+    assert!(matches!(last.next_range(Some(100), None), Err(PrimeError::InvalidRange(_))));
+    assert!(matches!(last.next_range(None, Some(102)), Err(PrimeError::InvalidRange(_))));
+}
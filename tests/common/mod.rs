@@ -0,0 +1,16 @@
+// Shared by the test files that need an OpenCL device
+
+use opencl_primes::PrimeError;
+
+// The value, or None when there's no device the kernels can run on, saying the test is skipped.
+// Any other error fails the test.
+pub fn skip_without_devices<T>(result: Result<T, PrimeError>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(PrimeError::NoDevices | PrimeError::UnsupportedDevices(_)) => {
+            eprintln!("skipped: no OpenCL devices");
+            None
+        }
+        Err(e) => panic!("{}", e),
+    }
+}
@@ -0,0 +1,24 @@
+extern crate opencl_primes;
+
+use opencl_primes::{CpuSearcher, verify::is_prime};
+
+#[test]
+fn cpu_searcher_matches_per_candidate_check() {
+    let range = 1_000_000_000..1_000_100_000;
+    let searcher = CpuSearcher::new(range.clone()).unwrap().with_threads(4);
+    let expected: Vec<u64> = range.filter(|&n| is_prime(n)).collect();
+
+    assert_eq!(searcher.find_all().unwrap(), expected);
+    assert_eq!(searcher.count().unwrap(), expected.len() as u64);
+    assert_eq!(searcher.find_first().unwrap(), Some(1_000_000_007));
+    assert_eq!(searcher.find_twin().unwrap(), Some((1_000_000_007, 1_000_000_009)));
+    assert_eq!(searcher.report().found_by.map(|device| device.to_string()), Some(searcher.device().to_string()));
+}
+
+#[test]
+fn cpu_twin_stays_inside_the_range() {
+    let searcher = CpuSearcher::new(1_000_000_000..1_000_000_009).unwrap();
+    assert_eq!(searcher.find_twin().unwrap(), None);
+    assert!(searcher.report().found_by.is_none());
+    assert_eq!(CpuSearcher::new(0..2).unwrap().find_first().unwrap(), None);
+}
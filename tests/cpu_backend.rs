@@ -0,0 +1,140 @@
+extern crate opencl_primes;
+
+use opencl_primes::{Backend, CancelHandle, CpuSearcher, Progression};
+use proptest::prelude::*;
+use std::{ops::Range, thread, time::{Duration, Instant}};
+
+// Runs without any OpenCL device, so these cover the searches in CI
+fn backend() -> Box<dyn Backend> {
+    Box::new(CpuSearcher::new(0..0).unwrap().with_threads(4))
+}
+
+// Plain sieve of Eratosthenes over [0, limit)
+fn reference_primes(limit: u64) -> Vec<u64> {
+    let mut composite = vec![false; limit as usize];
+    let mut primes = vec![];
+    for n in 2..limit {
+        if !composite[n as usize] {
+            primes.push(n);
+            for multiple in (n * n..limit).step_by(n as usize) {
+                composite[multiple as usize] = true;
+            }
+        }
+    }
+    primes
+}
+
+#[test]
+fn primes_between_100_and_200() {
+    let backend = backend();
+    let expected = [101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199];
+
+    assert_eq!(backend.find_all(100..200).unwrap(), expected);
+    assert_eq!(backend.count(100..200).unwrap(), 21);
+    assert_eq!(backend.find_first(100..200).unwrap(), Some(101));
+    assert_eq!(backend.find_twin(100..200).unwrap(), Some((101, 103)));
+}
+
+#[test]
+fn small_known_ranges() {
+    let backend = backend();
+    assert_eq!(backend.find_all(0..30).unwrap(), [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    assert_eq!(backend.count(0..1_000_000).unwrap(), 78_498);
+    // The gap after 1327 is the first of length 34
+    assert_eq!(backend.find_first(1328..1361).unwrap(), None);
+    assert_eq!(backend.find_first(1328..1362).unwrap(), Some(1361));
+    assert_eq!(backend.count(50..50).unwrap(), 0);
+    assert!(backend.find_all(Range { start: 10, end: 5 }).is_err());
+}
+
+#[test]
+fn ranges_of_one_number_or_none() {
+    let backend = backend();
+    let prime = 1_000_003;
+    for (range, expected) in [(prime..prime + 1, vec![prime]), (prime + 1..prime + 2, vec![]), (prime..prime, vec![]), (0..1, vec![]), (2..3, vec![2])] {
+        assert_eq!(backend.find_first(range.clone()).unwrap(), expected.first().copied(), "{:?}", range);
+        assert_eq!(backend.find_all(range.clone()).unwrap(), expected, "{:?}", range);
+        assert_eq!(backend.count(range.clone()).unwrap(), expected.len() as u64, "{:?}", range);
+        assert_eq!(backend.find_twin(range.clone()).unwrap(), None, "{:?}", range);
+    }
+}
+
+#[test]
+fn many_threads_search_and_stop_together() {
+    let searcher = CpuSearcher::new(0..1_000_000).unwrap().with_threads(64);
+    assert_eq!(searcher.count().unwrap(), 78_498);
+
+    // A search far too long to finish, cancelled while its workers are all polling the flag
+    let searcher = CpuSearcher::new(0..u64::MAX / 2).unwrap().with_threads(64);
+    let cancel = searcher.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel().unwrap();
+        Instant::now()
+    });
+    searcher.count().unwrap();
+    let stopped = Instant::now();
+    let cancelled = canceller.join().unwrap();
+
+    assert!(stopped.duration_since(cancelled) < Duration::from_secs(5));
+    assert!(searcher.progress() < u64::MAX / 2);
+    // Cancellation is sticky
+    assert_eq!(searcher.count().unwrap(), 0);
+}
+
+#[test]
+fn every_clone_of_a_cancel_handle_sees_the_cancel() {
+    let cancel = CancelHandle::without_kernels();
+    let pollers: Vec<_> = (0..64).map(|_| {
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            let mut polls = 0u64;
+            while !cancel.is_cancelled() {
+                polls += 1;
+            }
+            polls
+        })
+    }).collect();
+    thread::sleep(Duration::from_millis(50));
+    cancel.cancel().unwrap();
+
+    for poller in pollers {
+        poller.join().unwrap();
+    }
+    assert!(cancel.is_cancelled());
+}
+
+#[test]
+fn progression_search_finds_the_smallest_prime_of_the_form() {
+    let primes = reference_primes(100_000);
+    let backend = backend();
+    for (a, b) in [(4, 1), (4, 3), (10, 9), (1000, 7), (7, 100)] {
+        let progression = Progression::new(a, b).unwrap();
+        for range in [0..100, 500..30_000, 99_000..100_000] {
+            let expected = primes.iter().copied().find(|&p| range.contains(&p) && p >= b && p % a == b % a);
+            assert_eq!(backend.find_in_progression(range.clone(), progression).unwrap(), expected, "{}k + {} over {:?}", a, b, range);
+        }
+    }
+    assert_eq!(CpuSearcher::new(100..200).unwrap().find_in_progression(4, 3).unwrap(), Some(103));
+    assert!(CpuSearcher::new(100..200).unwrap().find_in_progression(4, 2).is_err());
+}
+
+const LIMIT: u64 = 200_000;
+
+proptest! {
+    #[test]
+    fn count_matches_reference_sieve(a in 0..LIMIT, b in 0..LIMIT) {
+        let range: Range<u64> = a.min(b)..a.max(b);
+        let expected = reference_primes(LIMIT).into_iter().filter(|p| range.contains(p)).count() as u64;
+        prop_assert_eq!(backend().count(range).unwrap(), expected);
+    }
+
+    #[test]
+    fn find_first_and_find_all_match_reference_sieve(start in 0..LIMIT, len in 0..5_000u64) {
+        let range = start..(start + len).min(LIMIT);
+        let expected: Vec<u64> = reference_primes(LIMIT).into_iter().filter(|p| range.contains(p)).collect();
+        let backend = backend();
+        prop_assert_eq!(backend.find_first(range.clone()).unwrap(), expected.first().copied());
+        prop_assert_eq!(backend.find_all(range).unwrap(), expected);
+    }
+}
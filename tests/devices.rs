@@ -0,0 +1,53 @@
+extern crate opencl_primes;
+
+mod common;
+
+use opencl_primes::{DeviceFilter, DeviceInfo, PrimeError, enumerate_devices, int64_incompatibility, list_devices};
+
+#[test]
+fn device_filter_needs_both_index_and_name_to_match() {
+    assert!(DeviceFilter::default().matches(3, "Any GPU"));
+
+    let filter = DeviceFilter { indices: vec![0, 2], name: Some("rtx".into()) };
+    assert!(filter.matches(2, "NVIDIA GeForce RTX 3090"));
+    assert!(!filter.matches(1, "NVIDIA GeForce RTX 3090"));
+    assert!(!filter.matches(0, "Intel UHD Graphics"));
+}
+
+#[test]
+fn devices_without_64_bit_integers_or_atomics_are_unsupported() {
+    let atomics = "cl_khr_fp64 cl_khr_int64_base_atomics cl_khr_int64_extended_atomics";
+    assert_eq!(int64_incompatibility(atomics, "FULL_PROFILE", 1), None);
+    assert_eq!(int64_incompatibility(&format!("{} cles_khr_int64", atomics), "EMBEDDED_PROFILE", 1), None);
+    assert!(int64_incompatibility(atomics, "EMBEDDED_PROFILE", 1).is_some_and(|reason| reason.contains("64-bit integer")));
+    assert!(int64_incompatibility(atomics, "FULL_PROFILE", 0).is_some_and(|reason| reason.contains("64-bit integer")));
+    let reason = int64_incompatibility("cl_khr_int64_base_atomics", "FULL_PROFILE", 1).unwrap();
+    assert!(reason.contains("cl_khr_int64_extended_atomics") && !reason.contains("base"), "{}", reason);
+}
+
+#[test]
+fn enumeration_lists_what_an_empty_filter_selects() {
+    let Some(all) = common::skip_without_devices(enumerate_devices()).filter(|all| !all.is_empty()) else { return };
+    for (index, device) in all.iter().enumerate() {
+        assert_eq!(device.index, index);
+    }
+    // Numbered by PCI address, whose fixed-width hex sorts as text, then by name
+    let key = |device: &DeviceInfo| (device.pci_address.is_none(), device.pci_address.clone(), device.name.clone());
+    assert!(all.windows(2).all(|pair| key(&pair[0]) <= key(&pair[1])), "{:?}", all);
+    let again: Vec<_> = enumerate_devices().unwrap().into_iter().map(|device| (device.index, device.name, device.pci_address)).collect();
+    assert_eq!(again, all.iter().map(|device| (device.index, device.name.clone(), device.pci_address.clone())).collect::<Vec<_>>());
+    // Devices that can't run the kernels are never selected
+    let supported: Vec<_> = all.iter().filter(|device| device.unsupported.is_none()).collect();
+    let selected = match list_devices(&DeviceFilter::default()) {
+        Ok(selected) => selected,
+        Err(PrimeError::UnsupportedDevices(_)) if supported.is_empty() => vec![],
+        Err(e) => panic!("{}", e),
+    };
+    assert_eq!(supported.len(), selected.len());
+    for (device, listed) in supported.into_iter().zip(&selected) {
+        assert_eq!(device.index, listed.index);
+        assert_eq!((&device.name, &device.platform), (&listed.name, &listed.platform));
+        assert!(device.compute_units > 0 && device.max_work_group_size > 0 && device.global_memory > 0, "{:?}", device);
+        assert!(device.opencl_version.starts_with("OpenCL"), "{:?}", device);
+    }
+}
@@ -0,0 +1,161 @@
+extern crate opencl_primes;
+
+use opencl_primes::{CancelHandle, Coordinator, CpuSearcher, PrimeError, run_worker};
+use opencl_primes::distributed::{MAX_MESSAGE_LEN, Message, read_message, write_message};
+use std::{io::BufReader, net::{SocketAddr, TcpStream}, ops::Range, thread, time::Duration};
+
+fn coordinator(range: Range<u64>, chunk_size: u64) -> Coordinator {
+    Coordinator::bind("127.0.0.1:0", range, chunk_size).unwrap()
+}
+
+// Workers on the CPU backend, each on its own thread, returning how many chunks each searched
+fn spawn_workers(addr: SocketAddr, count: usize) -> Vec<thread::JoinHandle<u64>> {
+    (0..count).map(|_| thread::spawn(move || run_worker(addr, &CpuSearcher::new(0..0).unwrap()).unwrap())).collect()
+}
+
+// A worker speaking the protocol by hand
+struct FakeWorker {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl FakeWorker {
+    fn connect(addr: SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).unwrap();
+        FakeWorker { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream }
+    }
+
+    fn request(&mut self) -> Message {
+        write_message(&mut self.writer, &Message::RequestChunk).unwrap();
+        read_message(&mut self.reader).unwrap()
+    }
+}
+
+#[test]
+fn messages_round_trip() {
+    let messages = [
+        Message::RequestChunk,
+        Message::AssignChunk { id: 3, start: 1 << 40, end: u64::MAX },
+        Message::ReportResult { id: 3, primes: vec![1_099_511_627_791] },
+        Message::ReportResult { id: 4, primes: vec![] },
+        Message::Done,
+    ];
+    let mut bytes = vec![];
+    for message in &messages {
+        write_message(&mut bytes, message).unwrap();
+    }
+    let mut reader = &bytes[..];
+    for message in &messages {
+        assert_eq!(&read_message(&mut reader).unwrap(), message);
+    }
+    assert!(read_message(&mut reader).is_err());
+}
+
+#[test]
+fn oversized_messages_are_refused_unread() {
+    let mut bytes = (MAX_MESSAGE_LEN + 1).to_be_bytes().to_vec();
+    bytes.extend(b"\"Done\"");
+    assert!(read_message(&bytes[..]).is_err());
+}
+
+#[test]
+fn workers_find_the_smallest_prime() {
+    let range = 1_000_000_000_000..1_000_000_100_000;
+    let expected = CpuSearcher::new(range.clone()).unwrap().find_first().unwrap();
+    let coordinator = coordinator(range, 7);
+    let workers = spawn_workers(coordinator.local_addr().unwrap(), 3);
+
+    assert_eq!(coordinator.run(&CancelHandle::without_kernels()).unwrap(), expected);
+    drop(coordinator);
+    // All six chunks up to the one holding the prime are searched, by whichever worker was free
+    let searched: u64 = workers.into_iter().map(|worker| worker.join().unwrap()).sum();
+    assert!(searched >= 6, "{} chunks searched", searched);
+}
+
+#[test]
+fn a_range_without_primes_finds_none() {
+    let coordinator = coordinator(24..29, 2);
+    let workers = spawn_workers(coordinator.local_addr().unwrap(), 2);
+    assert_eq!(coordinator.run(&CancelHandle::without_kernels()).unwrap(), None);
+    assert_eq!(coordinator.chunks_left(), 0);
+    drop(coordinator);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[test]
+fn a_lost_workers_chunk_is_handed_out_again() {
+    let coordinator = coordinator(90..200, 10);
+    let addr = coordinator.local_addr().unwrap();
+    let (prime, workers) = thread::scope(|scope| {
+        let prime = scope.spawn(|| coordinator.run(&CancelHandle::without_kernels()).unwrap());
+        let mut lost = FakeWorker::connect(addr);
+        assert_eq!(lost.request(), Message::AssignChunk { id: 0, start: 90, end: 100 });
+        // Disconnects with [90, 100) unreported
+        drop(lost);
+        thread::sleep(Duration::from_millis(100));
+        let workers = spawn_workers(addr, 1);
+        (prime.join().unwrap(), workers)
+    });
+    assert_eq!(prime, Some(97));
+    drop(coordinator);
+    workers.into_iter().for_each(|worker| assert!(worker.join().unwrap() >= 1));
+}
+
+#[test]
+fn wrong_primes_are_not_accepted() {
+    let coordinator = coordinator(90..200, 10);
+    let addr = coordinator.local_addr().unwrap();
+    let (prime, workers) = thread::scope(|scope| {
+        let prime = scope.spawn(|| coordinator.run(&CancelHandle::without_kernels()).unwrap());
+        let mut liar = FakeWorker::connect(addr);
+        let Message::AssignChunk { id, .. } = liar.request() else { panic!("no chunk assigned") };
+        // 91 = 7 * 13
+        write_message(&mut liar.writer, &Message::ReportResult { id, primes: vec![91] }).unwrap();
+        // The chunk goes back for someone else, so the liar gets the next one, and 150 is
+        // outside that
+        assert_eq!(liar.request(), Message::AssignChunk { id: id + 1, start: 100, end: 110 });
+        write_message(&mut liar.writer, &Message::ReportResult { id: id + 1, primes: vec![150] }).unwrap();
+        // Both chunks are left for the others
+        assert_eq!(liar.request(), Message::AssignChunk { id: id + 2, start: 110, end: 120 });
+        drop(liar);
+        let workers = spawn_workers(addr, 1);
+        (prime.join().unwrap(), workers)
+    });
+    assert_eq!(prime, Some(97));
+    drop(coordinator);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[test]
+fn a_chunk_no_worker_can_be_trusted_with_ends_the_run() {
+    let coordinator = coordinator(90..100, 10);
+    let addr = coordinator.local_addr().unwrap();
+    thread::scope(|scope| {
+        let result = scope.spawn(|| coordinator.run(&CancelHandle::without_kernels()));
+        let mut liar = FakeWorker::connect(addr);
+        assert_eq!(liar.request(), Message::AssignChunk { id: 0, start: 90, end: 100 });
+        write_message(&mut liar.writer, &Message::ReportResult { id: 0, primes: vec![91] }).unwrap();
+        // The only chunk can't go back to the only worker
+        assert_eq!(liar.request(), Message::Done);
+        assert!(matches!(result.join().unwrap(), Err(PrimeError::UntrustedWorkers { start: 90, end: 100 })));
+    });
+}
+
+#[test]
+fn cancelling_tells_waiting_workers_to_stop() {
+    let coordinator = coordinator(90..200, 10);
+    let addr = coordinator.local_addr().unwrap();
+    let cancel = CancelHandle::without_kernels();
+    thread::scope(|scope| {
+        let prime = scope.spawn(|| coordinator.run(&cancel).unwrap());
+        let mut worker = FakeWorker::connect(addr);
+        assert!(matches!(worker.request(), Message::AssignChunk { .. }));
+        cancel.cancel().unwrap();
+        assert_eq!(prime.join().unwrap(), None);
+        assert_eq!(worker.request(), Message::Done);
+    });
+}
@@ -0,0 +1,53 @@
+extern crate opencl_primes;
+
+use opencl_primes::eta::{RateEstimate, format_time_left};
+use std::time::{Duration, Instant};
+
+#[test]
+fn rate_estimate_waits_for_a_second_reading() {
+    let start = Instant::now();
+    let mut rate = RateEstimate::new();
+    assert_eq!(rate.time_left(1_000), None);
+    rate.record(start, 1_000);
+    assert_eq!(rate.time_left(1_000), None);
+    // Readings closer together than the sampling interval are skipped
+    rate.record(start + Duration::from_millis(100), 990);
+    assert_eq!(rate.rate(), None);
+
+    rate.record(start + Duration::from_secs(2), 800);
+    assert_eq!(rate.rate(), Some(100.0));
+    assert_eq!(rate.time_left(800), Some(Duration::from_secs(8)));
+    assert_eq!(rate.time_left(0), Some(Duration::ZERO));
+}
+
+#[test]
+fn rate_estimate_smooths_and_survives_new_chunks() {
+    let start = Instant::now();
+    let mut rate = RateEstimate::new();
+    rate.record(start, 1_000);
+    rate.record(start + Duration::from_secs(1), 900);
+    // A burst only moves the rate part of the way
+    rate.record(start + Duration::from_secs(2), 500);
+    let smoothed = rate.rate().unwrap();
+    assert!(smoothed > 100.0 && smoothed < 400.0, "{}", smoothed);
+
+    // A new chunk has more left but doesn't count as going backwards
+    rate.record(start + Duration::from_secs(3), 5_000);
+    assert_eq!(rate.rate(), Some(smoothed));
+    rate.record(start + Duration::from_secs(4), 5_000);
+    assert!(rate.rate().unwrap() < smoothed);
+
+    // Stalled for good, there's no time to give
+    let mut stalled = RateEstimate::new();
+    stalled.record(start, 10);
+    stalled.record(start + Duration::from_secs(1), 10);
+    assert_eq!(stalled.time_left(10), None);
+}
+
+#[test]
+fn time_left_formats() {
+    assert_eq!(format_time_left(None), "estimating...");
+    assert_eq!(format_time_left(Some(Duration::from_secs(3))), "3s");
+    assert_eq!(format_time_left(Some(Duration::from_secs(123))), "2m 03s");
+    assert_eq!(format_time_left(Some(Duration::from_secs(3723))), "1h 02m 03s");
+}
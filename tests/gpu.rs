@@ -0,0 +1,358 @@
+extern crate opencl_primes;
+
+mod common;
+
+// Run with `cargo test --features gpu-tests`. Each test searches a small range on every
+// device with the kernels built from source and checks the result against the CPU backend.
+use opencl_primes::{Algorithm, Backend, CpuSearcher, DeviceFilter, Direction, PartitionStrategy, PrimeError, PrimeSearcher, SearcherConfig, ThreadCount, list_devices};
+use std::{collections::HashMap, ops::Range, sync::{Arc, Mutex}, thread, time::{Duration, Instant, SystemTime}};
+
+// Whether there is a device to run on, saying so when there isn't
+fn has_gpu() -> bool {
+    common::skip_without_devices(list_devices(&DeviceFilter::default())).is_some_and(|devices| !devices.is_empty())
+}
+
+// Without a kernel cache, so every searcher builds the kernels from source
+fn searcher(range: Range<u64>) -> PrimeSearcher {
+    PrimeSearcher::new(range).unwrap().with_monitoring(false)
+}
+
+fn reference(range: Range<u64>) -> CpuSearcher {
+    CpuSearcher::new(range).unwrap()
+}
+
+#[test]
+fn first_prime_matches_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    for range in [0..100, 1_000_000_000..1_000_100_000, 18_446_744_073_709_551_000..u64::MAX] {
+        let expected = reference(range.clone()).find_first().unwrap();
+        for algorithm in [Algorithm::TrialDivision, Algorithm::MillerRabin] {
+            assert_eq!(searcher(range.clone()).with_algorithm(algorithm).find_first().unwrap(), expected, "{:?} over {:?}", algorithm, range);
+        }
+    }
+}
+
+#[test]
+fn largest_prime_matches_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 1_000_000_000..1_000_100_000;
+    let expected = reference(range.clone()).find_all().unwrap().last().copied();
+    assert_eq!(searcher(range).with_direction(Direction::Down).find_first().unwrap(), expected);
+}
+
+#[test]
+fn every_prime_and_the_count_match_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 4_000_000_000..4_000_050_000;
+    let cpu = reference(range.clone());
+    let gpu = searcher(range).with_algorithm(Algorithm::MillerRabin);
+    assert_eq!(gpu.find_all().unwrap(), cpu.find_all().unwrap());
+    assert_eq!(gpu.count().unwrap(), cpu.count().unwrap());
+    assert_eq!(gpu.find_twin().unwrap(), cpu.find_twin().unwrap());
+}
+
+#[test]
+fn searches_at_the_top_of_the_range_match_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    // Where a thread's next candidate would wrap past u64::MAX
+    let range = 18_446_744_073_709_551_000..u64::MAX;
+    let cpu = reference(range.clone());
+    let gpu = searcher(range).with_algorithm(Algorithm::MillerRabin);
+    assert_eq!(gpu.find_all().unwrap(), cpu.find_all().unwrap());
+    assert_eq!(gpu.count().unwrap(), cpu.count().unwrap());
+    assert_eq!(gpu.find_twin().unwrap(), cpu.find_twin().unwrap());
+}
+
+#[test]
+fn self_check_passes_for_every_algorithm() {
+    if !has_gpu() {
+        return;
+    }
+    for algorithm in [Algorithm::TrialDivision, Algorithm::MillerRabin, Algorithm::SegmentedSieve] {
+        searcher(0..0).with_algorithm(algorithm).self_check().unwrap();
+    }
+}
+
+#[test]
+fn moving_a_searcher_is_cheaper_than_setting_one_up() {
+    if !has_gpu() {
+        return;
+    }
+    let ranges = [1_000..2_000, 1_000_000_000..1_000_100_000, 0..100];
+    let started = Instant::now();
+    for range in ranges.clone() {
+        searcher(range);
+    }
+    let setup = started.elapsed();
+
+    let mut moved = searcher(0..0);
+    let started = Instant::now();
+    for range in ranges.clone() {
+        moved.set_range(range).unwrap();
+    }
+    let moving = started.elapsed();
+    eprintln!("setting up {} searchers took {:?}, moving one {:?}", ranges.len(), setup, moving);
+    assert!(moving < setup);
+
+    for range in ranges {
+        moved.set_range(range.clone()).unwrap();
+        assert_eq!(moved.find_first().unwrap(), reference(range.clone()).find_first().unwrap(), "{:?}", range);
+        assert_eq!(moved.count().unwrap(), reference(range.clone()).count().unwrap(), "{:?}", range);
+    }
+    assert!(matches!(moved.set_range(Range { start: 10, end: 5 }), Err(PrimeError::InvalidRange(_))));
+}
+
+#[test]
+fn status_readings_cover_every_thread_after_clamping() {
+    if !has_gpu() {
+        return;
+    }
+    // More threads than any device runs at once, then rounded down to whole work groups
+    for local_size in [None, Some(32)] {
+        let lengths = Arc::new(Mutex::new(vec![]));
+        let searcher = {
+            let lengths = Arc::clone(&lengths);
+            searcher(1_000_000_000..1_000_100_000)
+                .with_thread_count(ThreadCount::Explicit(usize::MAX / 2)).unwrap()
+                .with_local_size(local_size).unwrap()
+                .with_status_callback(move |i, statuses| lengths.lock().unwrap().push((i, statuses.len())))
+        };
+        assert!(searcher.count().unwrap() > 0);
+        let lengths = lengths.lock().unwrap();
+        assert!(!lengths.is_empty());
+        for &(i, len) in lengths.iter() {
+            assert_eq!(len, searcher.thread_counts()[i], "{:?}", local_size);
+        }
+        // The last reading of each device is kept
+        for (statuses, &threads) in searcher.thread_statuses().iter().zip(searcher.thread_counts()) {
+            assert_eq!(statuses.len(), threads);
+        }
+    }
+}
+
+#[test]
+fn progress_follows_dynamic_chunks() {
+    if !has_gpu() {
+        return;
+    }
+    let tested = Arc::new(Mutex::new(HashMap::new()));
+    let searcher = {
+        let tested = Arc::clone(&tested);
+        searcher(1_000_000_000..1_000_100_000)
+            .with_partition_strategy(PartitionStrategy::Dynamic { chunk_size: 10_000 })
+            .with_progress_callback(move |event| {
+                tested.lock().unwrap().insert(event.device, event.tested);
+            })
+    };
+    assert!(searcher.count().unwrap() > 0);
+    // Each device's last event covers every chunk it was handed
+    assert_eq!(tested.lock().unwrap().values().sum::<u64>(), 100_000);
+}
+
+#[test]
+fn completion_is_seen_before_the_next_poll() {
+    if !has_gpu() {
+        return;
+    }
+    // The kernel finishes long before the first poll is due
+    let searcher = searcher(1_000_000..1_001_000).with_poll_interval(Duration::from_secs(30));
+    let started = Instant::now();
+    assert_eq!(searcher.find_first().unwrap(), Some(1_000_003));
+    assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+}
+
+#[test]
+fn profiled_queues_report_kernel_time() {
+    if !has_gpu() {
+        return;
+    }
+    for queue_profiling in [false, true] {
+        let config = SearcherConfig { queue_profiling, ..SearcherConfig::default() };
+        let searcher = PrimeSearcher::new_with_config(1_000_000..2_000_000, &config).unwrap().with_monitoring(false);
+        let (primes, report) = searcher.find_all_with_report().unwrap();
+        assert_eq!(primes, reference(1_000_000..2_000_000).find_all().unwrap());
+        for device in report.devices {
+            assert_eq!(device.kernel_time.is_some_and(|time| time > Duration::ZERO), queue_profiling, "{}", device.name);
+            assert_eq!(device.kernel_launches > 0, queue_profiling, "{}", device.name);
+        }
+    }
+
+    // Every chunk of a dynamically partitioned search, and every segment of the sieve, is a
+    // kernel run of its own
+    let config = SearcherConfig { queue_profiling: true, ..SearcherConfig::default() };
+    let dynamic = PrimeSearcher::new_with_config(1_000_000..2_000_000, &config).unwrap()
+        .with_monitoring(false)
+        .with_partition_strategy(PartitionStrategy::Dynamic { chunk_size: 100_000 });
+    let (_, report) = dynamic.find_all_with_report().unwrap();
+    assert!(report.devices.iter().map(|device| device.kernel_launches).sum::<u64>() >= 10);
+    let sieve = PrimeSearcher::new_with_config(1_000_000..2_000_000, &config).unwrap()
+        .with_monitoring(false)
+        .with_algorithm(Algorithm::SegmentedSieve)
+        .with_segment_size(1 << 16);
+    let (_, report) = sieve.find_all_with_report().unwrap();
+    assert!(report.devices.iter().map(|device| device.kernel_launches).sum::<u64>() >= 1_000_000 / (1 << 16));
+}
+
+#[test]
+fn ranges_of_one_number_or_none_are_tested_without_the_kernels() {
+    if !has_gpu() {
+        return;
+    }
+    let prime = 1_000_003;
+    for (range, expected) in [(prime..prime + 1, vec![prime]), (prime + 1..prime + 2, vec![]), (prime..prime, vec![]), (0..1, vec![]), (2..3, vec![2])] {
+        let searcher = searcher(range.clone());
+        assert_eq!(searcher.find_first().unwrap(), expected.first().copied(), "{:?}", range);
+        assert_eq!(searcher.find_all().unwrap(), expected, "{:?}", range);
+        assert_eq!(searcher.count().unwrap(), expected.len() as u64, "{:?}", range);
+        assert_eq!(searcher.find_twin().unwrap(), None, "{:?}", range);
+    }
+}
+
+#[test]
+fn out_of_order_queues_match_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 1_000_000_000..1_000_100_000;
+    let config = SearcherConfig { out_of_order: true, ..SearcherConfig::default() };
+    let expected = reference(range.clone());
+    for strategy in [PartitionStrategy::Even, PartitionStrategy::Dynamic { chunk_size: 10_000 }] {
+        let searcher = PrimeSearcher::new_with_config(range.clone(), &config).unwrap().with_monitoring(false).with_partition_strategy(strategy);
+        assert_eq!(searcher.find_first().unwrap(), expected.find_first().unwrap(), "{:?}", strategy);
+        assert_eq!(searcher.find_all().unwrap(), expected.find_all().unwrap(), "{:?}", strategy);
+        assert_eq!(searcher.count().unwrap(), expected.count().unwrap(), "{:?}", strategy);
+    }
+    let searcher = PrimeSearcher::new_with_config(0..0, &config).unwrap();
+    assert_eq!(searcher.test_mersenne(&[7, 11]).unwrap(), [(7, true), (11, false)]);
+    assert!(searcher.test_number(1_000_003).unwrap());
+}
+
+#[test]
+fn mapped_status_buffers_read_the_same_progress() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 1_000_000_000..1_000_100_000;
+    let config = SearcherConfig { mapped_status: true, ..SearcherConfig::default() };
+    let expected = reference(range.clone());
+    let searcher = PrimeSearcher::new_with_config(range.clone(), &config).unwrap().with_poll_interval(Duration::from_millis(1));
+    let (primes, report) = searcher.find_all_with_report().unwrap();
+    assert_eq!(primes, expected.find_all().unwrap());
+    assert!(report.devices.iter().all(|device| device.tested > 0), "{:?}", report);
+    let bench = searcher.bench(Duration::from_millis(200)).unwrap();
+    assert!(bench.iter().all(|result| result.batches > 0 && result.read_time > Duration::ZERO), "{:?}", bench);
+}
+
+#[test]
+fn tuning_leaves_each_device_at_its_fastest_count() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 1_000_000_000..1_000_100_000;
+    let mut searcher = PrimeSearcher::new(range.clone()).unwrap();
+    let results = searcher.tune(Duration::from_millis(50)).unwrap();
+    assert_eq!(results.len(), searcher.devices().len());
+    for (result, &threads) in results.iter().zip(searcher.thread_counts()) {
+        assert!(result.runs[0].0 <= 256, "{:?}", result);
+        assert!(result.runs.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[1].0 <= pair[0].0 * 2), "{:?}", result);
+        assert_eq!(result.best(), Some(threads));
+    }
+    // The searcher still searches with the status buffers resized
+    assert_eq!(searcher.find_all().unwrap(), reference(range).find_all().unwrap());
+}
+
+#[test]
+fn reports_show_the_candidates_each_device_has_left() {
+    if !has_gpu() {
+        return;
+    }
+    // Far more than the test waits for
+    let range = 1_000_000_000..100_000_000_000;
+    let long = searcher(range.clone()).with_algorithm(Algorithm::MillerRabin).with_poll_interval(Duration::from_millis(1));
+    let left = thread::scope(|scope| {
+        let count = scope.spawn(|| long.count());
+        let started = Instant::now();
+        let left = loop {
+            let left: u64 = long.report().devices.iter().map(|device| device.remaining).sum();
+            if left > 0 || started.elapsed() > Duration::from_secs(30) {
+                break left;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        long.cancel_handle().cancel().unwrap();
+        count.join().unwrap().unwrap();
+        left
+    });
+    assert!(left > 0 && left < range.end - range.start, "{}", left);
+
+    let finished = searcher(1_000_000_000..1_000_100_000);
+    finished.count().unwrap();
+    assert!(finished.report().devices.iter().all(|device| device.remaining == 0), "{:?}", finished.report());
+}
+
+#[test]
+fn prime_callback_sees_each_verified_prime() {
+    if !has_gpu() {
+        return;
+    }
+    for strategy in [PartitionStrategy::Even, PartitionStrategy::Dynamic { chunk_size: 1000 }] {
+        let events = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&events);
+        let searcher = searcher(1_000_000..1_100_000)
+            .with_partition_strategy(strategy)
+            .with_prime_callback(move |event| seen.lock().unwrap().push(event));
+        let started = SystemTime::now();
+        let prime = searcher.find_first().unwrap();
+
+        // The search only returns once the callback has seen every prime
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().map(|event| event.value).min(), prime.map(u128::from), "{:?}", strategy);
+        for event in events.iter() {
+            assert!(opencl_primes::verify::is_prime_u128(event.value), "{:?}", event);
+            assert!(event.device < searcher.devices().len() && event.found_at >= started, "{:?}", event);
+        }
+    }
+}
+
+#[test]
+fn progression_search_matches_the_cpu() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 1_000_000_000..1_010_000_000;
+    for (a, b) in [(4, 1), (4, 3), (1_000_000, 1), (30, 7)] {
+        let expected = reference(range.clone()).find_in_progression(a, b).unwrap();
+        for strategy in [PartitionStrategy::Even, PartitionStrategy::Dynamic { chunk_size: 1 << 20 }] {
+            let prime = searcher(range.clone()).with_partition_strategy(strategy).with_algorithm(Algorithm::MillerRabin).find_in_progression(a, b).unwrap();
+            assert_eq!(prime, expected, "{}k + {} with {:?}", a, b, strategy);
+        }
+    }
+    // The last terms below 2^64 can't step past the end of the range
+    let top = u64::MAX - 1_000_000..u64::MAX;
+    assert_eq!(searcher(top.clone()).find_in_progression(6, 5).unwrap(), reference(top).find_in_progression(6, 5).unwrap());
+}
+
+#[test]
+fn prime_iterator_matches_find_all() {
+    if !has_gpu() {
+        return;
+    }
+    let range = 10_000_000..10_500_000;
+    let expected = reference(range.clone()).find_all().unwrap();
+    let searcher = searcher(range);
+    let primes = searcher.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(primes, expected);
+
+    // An iterator dropped early leaves the searcher ready for the next search
+    let first: Vec<u64> = searcher.iter().take(3).map(Result::unwrap).collect();
+    assert_eq!(first, expected[..3]);
+    assert_eq!(searcher.iter().filter_map(Result::ok).find(|prime| prime % 10 == 9), expected.iter().copied().find(|prime| prime % 10 == 9));
+}
@@ -0,0 +1,24 @@
+extern crate opencl_primes;
+
+use opencl_primes::{Metrics, MetricsServer};
+use std::{io::{Read, Write}, net::TcpStream, sync::Arc};
+
+fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn serves_metrics_and_shuts_down() {
+    let server = MetricsServer::start(0, Arc::new(Metrics::new())).unwrap();
+    let port = server.port().unwrap();
+
+    assert!(get(port, "/metrics").starts_with("HTTP/1.1 200"));
+    assert!(get(port, "/other").starts_with("HTTP/1.1 404"));
+
+    // Returns once the server thread has exited
+    server.shutdown();
+}
@@ -0,0 +1,43 @@
+extern crate opencl_primes;
+
+use opencl_primes::{Monitor, Result, StatusThreads};
+
+// Reports fixed readings
+struct Fixed {
+    temperature: Option<u32>,
+    utilization: Option<u32>,
+}
+
+impl Monitor for Fixed {
+    fn temperature(&self) -> Result<Option<u32>> {
+        Ok(self.temperature)
+    }
+
+    fn utilization(&self) -> Result<Option<u32>> {
+        Ok(self.utilization)
+    }
+
+    fn power(&self) -> Result<Option<u32>> {
+        Ok(Some(150_000))
+    }
+}
+
+#[test]
+fn stats_combine_the_readings() {
+    let stats = Fixed { temperature: Some(70), utilization: None }.stats().unwrap().unwrap();
+    assert_eq!((stats.temperature, stats.utilization, stats.power_usage, stats.graphics_clock), (70, None, Some(150_000), None));
+    assert_eq!(stats.to_string(), "Temperature: 70°C, Power: 150.0 W");
+
+    // Without a temperature there is nothing worth reporting
+    assert!(Fixed { temperature: None, utilization: Some(99) }.stats().unwrap().is_none());
+}
+
+#[test]
+fn status_threads_pick_the_first_threads() {
+    let statuses = [5, 8, 13, 21];
+    assert_eq!(StatusThreads::default(), StatusThreads::First(10));
+    assert_eq!(StatusThreads::default().of(&statuses), statuses);
+    assert_eq!(StatusThreads::First(2).of(&statuses), [5, 8]);
+    assert!(StatusThreads::First(0).of(&statuses).is_empty());
+    assert_eq!(StatusThreads::All.of(&statuses), statuses);
+}
@@ -0,0 +1,65 @@
+extern crate opencl_primes;
+extern crate proptest;
+
+use opencl_primes::{partition_range, partition_weighted};
+use proptest::prelude::*;
+use std::ops::Range;
+
+// Contiguous slices that start at range.start and end at range.end cover the range exactly
+// once, with nothing dropped and nothing searched twice
+fn assert_tiles(range: &Range<u64>, slices: &[Range<u64>], n: usize) {
+    assert_eq!(slices.len(), n, "{:?} split {} ways", range, n);
+    assert_eq!(slices[0].start, range.start, "{:?} split {} ways: {:?}", range, n, slices);
+    assert_eq!(slices[n - 1].end, range.end, "{:?} split {} ways: {:?}", range, n, slices);
+    for slice in slices {
+        assert!(slice.start <= slice.end, "{:?} split {} ways: {:?}", range, n, slices);
+    }
+    for pair in slices.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start, "{:?} split {} ways: {:?}", range, n, slices);
+    }
+    let covered: u64 = slices.iter().map(|slice| slice.end - slice.start).sum();
+    assert_eq!(covered, range.end - range.start);
+}
+
+#[test]
+fn partition_range_tiles_small_ranges() {
+    for start in [0, 1, 7, 1_000] {
+        for len in 0..40 {
+            // Up to more devices than numbers
+            for n in 1..50 {
+                let range = start..start + len;
+                assert_tiles(&range, &partition_range(range.clone(), n), n);
+            }
+        }
+    }
+}
+
+#[test]
+fn partition_range_tiles_ranges_at_the_top_of_u64() {
+    for range in [0..u64::MAX, u64::MAX - 10..u64::MAX, u64::MAX..u64::MAX] {
+        for n in [1, 2, 3, 7, 16, 1_000] {
+            assert_tiles(&range, &partition_range(range.clone(), n), n);
+        }
+    }
+}
+
+#[test]
+fn partition_range_leaves_the_remainder_to_the_last_slice() {
+    assert_eq!(partition_range(0..10, 3), [0..3, 3..6, 6..10]);
+    assert_eq!(partition_range(5..7, 4), [5..5, 5..5, 5..5, 5..7]);
+    assert_eq!(partition_range(0..10, 1), [Range { start: 0, end: 10 }]);
+}
+
+proptest! {
+    #[test]
+    fn partition_range_tiles_any_range(a in any::<u64>(), b in any::<u64>(), n in 1..64usize) {
+        let range = a.min(b)..a.max(b);
+        assert_tiles(&range, &partition_range(range.clone(), n), n);
+    }
+
+    #[test]
+    fn partition_weighted_tiles_any_range(a in any::<u64>(), b in any::<u64>(), weights in prop::collection::vec(0..1_000u64, 1..16)) {
+        let range = a.min(b)..a.max(b);
+        assert_tiles(&range, &partition_weighted(range.clone(), &weights), weights.len());
+    }
+}
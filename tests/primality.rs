@@ -0,0 +1,187 @@
+extern crate opencl_primes;
+
+mod common;
+
+use opencl_primes::{Algorithm, KERNEL_SRC, PrimeError, PrimeSearcher};
+use opencl_primes::primality::{SMALL_PRIMES, WHEEL_210, WITNESSES_U64, WITNESSES_WIDE, certificate, has_small_factor, is_prime_bpsw, is_prime_by_trial_division, is_prime_u64, random_witnesses, trial_divisor_in_bound};
+use opencl_primes::verify::is_prime_u128;
+
+fn c_array(values: &[u64], format: impl Fn(u64) -> String) -> String {
+    format!("{{{}}}", values.iter().map(|&v| format(v)).collect::<Vec<_>>().join(", "))
+}
+
+fn is_prime_naive(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
+
+#[test]
+fn kernel_source_uses_the_shared_constants() {
+    let witnesses = |values: &[u64]| format!("const ulong witnesses[{}] = {};", values.len(), c_array(values, |v| v.to_string()));
+    assert!(KERNEL_SRC.contains(&witnesses(&WITNESSES_U64)), "the 64-bit kernel's witnesses differ from WITNESSES_U64");
+    assert!(KERNEL_SRC.contains(&witnesses(&WITNESSES_WIDE)), "the wide kernel's witnesses differ from WITNESSES_WIDE");
+
+    let wheel = format!("__constant ulong WHEEL_210[4] = {};", c_array(&WHEEL_210, |v| format!("{:#x}UL", v)));
+    assert!(KERNEL_SRC.contains(&wheel), "the kernel's wheel differs from WHEEL_210");
+
+    // The two products the kernel reduces by before testing 11..47
+    let a: u64 = SMALL_PRIMES[4..9].iter().product();
+    let b: u64 = SMALL_PRIMES[9..].iter().product();
+    assert!(KERNEL_SRC.contains(&format!("n % {};", a)) && KERNEL_SRC.contains(&format!("n % {}UL;", b)));
+}
+
+#[test]
+fn small_factor_precheck_matches_division() {
+    for n in (48..100_000).chain(u64::MAX - 10_000..=u64::MAX) {
+        assert_eq!(has_small_factor(n), SMALL_PRIMES.iter().any(|&p| n.is_multiple_of(p)), "{}", n);
+    }
+}
+
+#[test]
+fn reference_matches_trial_division() {
+    for n in (0..20_000).chain(4_294_967_000..4_294_968_000) {
+        assert_eq!(is_prime_u64(n), is_prime_naive(n), "{}", n);
+    }
+}
+
+#[test]
+fn trial_division_matches_miller_rabin() {
+    for n in (0..20_000).chain(4_294_967_000..4_294_968_000) {
+        assert_eq!(is_prime_by_trial_division(n), is_prime_u64(n), "{}", n);
+    }
+    // Squares of primes, whose only factor is the last divisor the loop tries
+    for p in [65_521u64, 65_537, 1_000_003] {
+        assert!(!is_prime_by_trial_division(p * p), "{}", p * p);
+    }
+    assert!(KERNEL_SRC.contains("i <= n / i; i += 6"), "the kernel's trial division bound differs from trial_divisor_in_bound");
+}
+
+#[test]
+fn trial_division_bound_stops_near_u64_max() {
+    // The largest prime below 2^64, whose loop has to run to the end of the bound
+    let n: u64 = 18_446_744_073_709_551_557;
+    // The last divisor below sqrt(n) the loop tries, which is the largest prime below 2^32
+    assert!(trial_divisor_in_bound(4_294_967_291, n));
+    // The next one is past 2^32, where i * i wraps to 2^33 + 1 and would keep the loop going
+    let next = 4_294_967_297;
+    assert!(!trial_divisor_in_bound(next, n));
+    assert!(next.wrapping_mul(next) <= n);
+    // Every divisor past the bound is out, up to where i + 6 would wrap
+    for i in (next..u64::MAX - 6).step_by(1 << 40) {
+        assert!(!trial_divisor_in_bound(i, n), "{}", i);
+    }
+}
+
+#[test]
+fn bpsw_matches_miller_rabin() {
+    // Strong pseudoprimes to base 2, some to every base up to 23, which only the Lucas test
+    // catches, and strong Lucas pseudoprimes, which only base 2 catches
+    let pseudoprimes = [2047, 3277, 4033, 4681, 8321, 3_215_031_751, 2_152_302_898_747, 3_474_749_660_383, 341_550_071_728_321, 3_825_123_056_546_413_051];
+    let lucas_pseudoprimes = [5459, 5777, 10_877, 16_109, 18_971, 22_499, 24_569, 25_199, 40_309, 58_519];
+    // Squares have no Selfridge parameter, so the search for one has to give up on them
+    let squares = [49, 121, 3481, 1_000_003 * 1_000_003, 4_294_967_291 * 4_294_967_291];
+    let special = pseudoprimes.iter().chain(&lucas_pseudoprimes).chain(&squares).copied();
+    for n in (0..200_000).chain(4_294_967_000..4_294_968_000).chain(u64::MAX - 100_000..=u64::MAX).chain(special) {
+        assert_eq!(is_prime_bpsw(n), is_prime_u64(n), "{}", n);
+    }
+    assert!(pseudoprimes.iter().chain(&lucas_pseudoprimes).chain(&squares).all(|&n| !is_prime_u64(n)));
+}
+
+#[test]
+fn kernel_matches_reference() {
+    let range = 1_000_000_000_000..1_000_000_020_000;
+    // Without verification the CPU can't quietly correct the kernel's answers
+    let Some(searcher) = common::skip_without_devices(PrimeSearcher::new(range.clone())) else { return };
+    let mut searcher = searcher.with_monitoring(false).with_verification(false);
+
+    let expected: Vec<u64> = range.filter(|&n| is_prime_u64(n)).collect();
+    for algorithm in [Algorithm::MillerRabin, Algorithm::Wide128, Algorithm::Bpsw] {
+        searcher = searcher.with_algorithm(algorithm);
+        assert_eq!(searcher.find_all().unwrap(), expected, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn random_witnesses_repeat_for_a_seed() {
+    let witnesses = random_witnesses(42, 16);
+    assert_eq!(witnesses.len(), 16);
+    assert!(witnesses.iter().all(|&a| a >= 2));
+    assert_eq!(witnesses, random_witnesses(42, 16));
+    assert_ne!(witnesses, random_witnesses(43, 16));
+    // More rounds extend the same sequence
+    assert_eq!(random_witnesses(42, 20)[..16], witnesses[..]);
+
+    assert_eq!(Algorithm::MillerRabinProbabilistic { rounds: 10 }.error_probability(), Some(1.0 / 1_048_576.0));
+    assert_eq!(Algorithm::MillerRabin.error_probability(), None);
+}
+
+#[test]
+fn probabilistic_kernel_matches_reference_past_u64() {
+    let start = 1u128 << 64;
+    let Some(searcher) = common::skip_without_devices(PrimeSearcher::new_u128(start..start + 2_000)) else { return };
+    let mut searcher = searcher.with_monitoring(false).with_verification(false).with_seed(7);
+    let expected = (start..start + 2_000).find(|&n| is_prime_u128(n));
+    assert!(expected.is_some());
+    for rounds in [4, 20] {
+        searcher = searcher.with_algorithm(Algorithm::MillerRabinProbabilistic { rounds });
+        assert_eq!(searcher.find_first_u128().unwrap(), expected, "{} rounds", rounds);
+    }
+    searcher = searcher.with_algorithm(Algorithm::MillerRabinProbabilistic { rounds: 0 });
+    assert!(matches!(searcher.find_first_u128(), Err(PrimeError::Unsupported(_))));
+}
+
+#[test]
+fn bpsw_kernel_matches_reference_past_u64() {
+    let start = (1u128 << 64) * 1_000;
+    let Some(searcher) = common::skip_without_devices(PrimeSearcher::new_u128(start..start + 5_000)) else { return };
+    let mut searcher = searcher.with_monitoring(false).with_verification(false);
+    let expected = (start..start + 5_000).find(|&n| is_prime_u128(n));
+    assert!(expected.is_some());
+    for algorithm in [Algorithm::Wide128, Algorithm::Bpsw] {
+        searcher = searcher.with_algorithm(algorithm);
+        assert_eq!(searcher.find_first_u128().unwrap(), expected, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn certificates_are_given_for_primes_only() {
+    for n in (0..2000).chain([u64::MAX - 58, u64::MAX - 1, 3_215_031_751, 18_446_744_073_709_551_557]) {
+        match certificate(n) {
+            Some(certificate) => {
+                assert!(is_prime_u64(n), "certificate for composite {}", n);
+                assert_eq!(certificate.n, n);
+                assert_eq!(certificate.d << certificate.s, n - 1);
+                assert!(certificate.check(), "certificate for {} doesn't check", n);
+            }
+            None => assert!(!is_prime_u64(n), "no certificate for prime {}", n),
+        }
+    }
+}
+
+#[test]
+fn certificates_record_every_witness() {
+    let large = certificate(1_000_003).unwrap();
+    assert_eq!(large.rounds.iter().map(|round| round.witness).collect::<Vec<_>>(), WITNESSES_U64);
+    // A witness that is n itself tests nothing
+    let small = certificate(7).unwrap();
+    assert!(small.rounds.iter().all(|round| round.witness != 7));
+    assert_eq!(small.rounds.len(), WITNESSES_U64.len() - 1);
+}
+
+#[test]
+fn tampered_certificates_fail_the_check() {
+    let genuine = certificate(1_000_003).unwrap();
+    let mut wrong_n = genuine.clone();
+    wrong_n.n = 1_000_001;
+    assert!(!wrong_n.check());
+    let mut missing_round = genuine.clone();
+    missing_round.rounds.pop();
+    assert!(!missing_round.check());
+    let mut wrong_squarings = genuine.clone();
+    for round in &mut wrong_squarings.rounds {
+        round.squarings = match round.squarings {
+            None => Some(0),
+            Some(_) => None,
+        };
+    }
+    assert!(!wrong_squarings.check());
+}
@@ -0,0 +1,34 @@
+extern crate opencl_primes;
+
+use opencl_primes::{PrimeError, Progression};
+
+#[test]
+fn progressions_with_a_common_factor_are_rejected() {
+    assert!(matches!(Progression::new(6, 4), Err(PrimeError::InvalidProgression(_))));
+    assert!(matches!(Progression::new(0, 1), Err(PrimeError::InvalidProgression(_))));
+    assert!(matches!(Progression::new(2, 0), Err(PrimeError::InvalidProgression(_))));
+    assert!(Progression::new(1, 0).is_ok());
+    assert!(Progression::new(4, 13).is_ok());
+}
+
+#[test]
+fn terms_start_at_the_residue() {
+    let progression = Progression::new(4, 13).unwrap();
+    assert_eq!(progression.to_string(), "4k + 13");
+    assert_eq!(progression.next_term(0), Some(13));
+    assert_eq!(progression.next_term(14), Some(17));
+    assert_eq!(progression.next_term(17), Some(17));
+    assert!(progression.contains(21) && !progression.contains(9) && !progression.contains(22));
+
+    assert!(progression.meets(&(17..18)));
+    assert!(!progression.meets(&(18..21)));
+    assert!(!progression.meets(&(0..13)));
+}
+
+#[test]
+fn no_term_past_the_largest_u64() {
+    let progression = Progression::new(1 << 40, 1).unwrap();
+    assert_eq!(progression.next_term(u64::MAX - 10), None);
+    assert!(!progression.meets(&(u64::MAX - 10..u64::MAX)));
+    assert_eq!(Progression::new(2, 1).unwrap().next_term(u64::MAX), Some(u64::MAX));
+}
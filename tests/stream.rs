@@ -0,0 +1,24 @@
+extern crate opencl_primes;
+
+use opencl_primes::{read_primes_bin, write_primes_bin};
+
+#[test]
+fn binary_primes_round_trip() {
+    let primes = [2, 3, 5, 4_294_967_311, u64::MAX - 58];
+    let mut bytes = vec![];
+    write_primes_bin(&mut bytes, &primes).unwrap();
+
+    assert_eq!(bytes.len(), 8 * primes.len());
+    assert_eq!(&bytes[..8], &[2, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(read_primes_bin(&bytes[..]).collect::<Vec<_>>(), primes);
+}
+
+#[test]
+fn binary_reader_stops_at_a_truncated_record() {
+    let mut bytes = vec![];
+    write_primes_bin(&mut bytes, &[7, 11]).unwrap();
+    bytes.truncate(13);
+
+    assert_eq!(read_primes_bin(&bytes[..]).collect::<Vec<_>>(), [7]);
+    assert_eq!(read_primes_bin(&[][..]).count(), 0);
+}
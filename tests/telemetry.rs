@@ -0,0 +1,30 @@
+extern crate opencl_primes;
+
+use opencl_primes::{GpuStats, TelemetryLog, telemetry::HEADER};
+use std::{env, fs, process};
+
+#[test]
+fn appends_rows_under_a_single_header() {
+    let path = env::temp_dir().join(format!("opencl-primes-telemetry-{}.csv", process::id()));
+    let _ = fs::remove_file(&path);
+    let full = GpuStats { utilization: Some(97), temperature: 71, power_usage: Some(215_500), graphics_clock: Some(1905), memory_clock: Some(9501) };
+    let bare = GpuStats { utilization: None, temperature: 64, power_usage: None, graphics_clock: None, memory_clock: None };
+
+    let log = TelemetryLog::create(&path).unwrap();
+    log.record(0, "NVIDIA GeForce RTX 3090", &full, 123_456).unwrap();
+    drop(log);
+    // Reopening appends without repeating the header
+    let log = TelemetryLog::create(&path).unwrap();
+    log.record(1, "Card \"A\", rev 2", &bare, 0).unwrap();
+    log.flush().unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", text);
+    assert_eq!(lines[0], HEADER);
+    let (timestamp, row) = lines[1].split_once(',').unwrap();
+    assert!(timestamp.parse::<f64>().unwrap() > 0.0);
+    assert_eq!(row, "0,NVIDIA GeForce RTX 3090,71,97,215.500,1905,9501,123456");
+    assert!(lines[2].ends_with(",1,\"Card \"\"A\"\", rev 2\",64,,,,,0"), "{}", lines[2]);
+}
@@ -0,0 +1,35 @@
+extern crate opencl_primes;
+
+use opencl_primes::{PowerBudget, PowerStep, ThermalLimit};
+
+#[test]
+fn thermal_limit_pauses_above_max_and_resumes_below_hysteresis() {
+    let limit = ThermalLimit::new(80);
+    assert!(!limit.should_pause(false, 80));
+    assert!(limit.should_pause(false, 81));
+    // Cooling a little is not enough to resume
+    assert!(limit.should_pause(true, 78));
+    assert!(limit.should_pause(true, 75));
+    assert!(!limit.should_pause(true, 74));
+}
+
+#[test]
+fn power_budget_pauses_over_budget_and_resumes_below_hysteresis() {
+    let budget = PowerBudget::new(300).with_hysteresis(50);
+    assert_eq!(budget.step(300, 3, 0), PowerStep::Hold);
+    assert_eq!(budget.step(301, 3, 0), PowerStep::PauseOne);
+    // Dropping a little under the budget is not enough to resume
+    assert_eq!(budget.step(280, 2, 1), PowerStep::Hold);
+    assert_eq!(budget.step(250, 2, 1), PowerStep::Hold);
+    assert_eq!(budget.step(249, 2, 1), PowerStep::ResumeOne);
+    // Nothing to resume
+    assert_eq!(budget.step(0, 3, 0), PowerStep::Hold);
+}
+
+#[test]
+fn power_budget_keeps_one_device_running() {
+    let budget = PowerBudget::new(100);
+    assert_eq!(budget.step(500, 1, 2), PowerStep::Hold);
+    // Once the running devices finish, a paused one resumes whatever the draw
+    assert_eq!(budget.step(500, 0, 2), PowerStep::ResumeOne);
+}
@@ -0,0 +1,42 @@
+extern crate opencl_primes;
+
+use opencl_primes::verify::{find_factor, is_prime, is_prime_u128};
+
+fn is_prime_trial(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
+
+#[test]
+fn cpu_check_matches_trial_division() {
+    for n in (0..10_000).chain(1_000_000_000..1_000_010_000) {
+        assert_eq!(is_prime(n), is_prime_trial(n), "{}", n);
+    }
+}
+
+#[test]
+fn cpu_check_handles_wide_values() {
+    // Largest prime below 2^64, a strong pseudoprime to the first twelve prime bases, and M127
+    assert!(is_prime(18_446_744_073_709_551_557));
+    assert!(!is_prime(3_825_123_056_546_413_051));
+    assert!(is_prime_u128((1 << 127) - 1));
+    assert!(!is_prime_u128(((1 << 61) - 1) * ((1 << 67) - 1)));
+    assert!(!is_prime_u128(u128::MAX));
+}
+
+#[test]
+fn factors_composites_past_trial_division() {
+    assert_eq!(find_factor(0), None);
+    assert_eq!(find_factor(1), None);
+    assert_eq!(find_factor(97), None);
+    assert_eq!(find_factor(91), Some(7));
+    // Smallest factor first while it's small enough for trial division
+    assert_eq!(find_factor(3 * 5 * 65_537), Some(3));
+    assert_eq!(find_factor(18_446_744_073_709_551_557), None);
+
+    // Two factors above 2^16, and a square of one
+    for n in [4_294_967_291 * 4_294_967_279, 1_000_003 * 1_000_033, 4_294_967_291 * 4_294_967_291] {
+        let factor = find_factor(n as u128).unwrap() as u64;
+        assert!(factor > 1 && factor < n && n.is_multiple_of(factor), "{} of {}", factor, n);
+    }
+    assert_eq!(find_factor(3 * (1 << 89) - 3), Some(3));
+}